@@ -1,23 +1,49 @@
+use crate::events::{
+    ApproveOperatorEvent, ClaimFeesEvent, ClaimPurchasedEvent, CreateStreamEvent, ExitStreamEvent,
+    FinalizeStreamEvent, GrantOperatorEvent, RevokeOperatorApprovalEvent, RevokeOperatorEvent,
+    SubscribeEvent, TransferPositionEvent, UpdateConfigEvent, UpdateOperatorEvent,
+    UpdatePositionEvent, UpdateProtocolAdminEvent, UpdateStreamEvent, UpdateThresholdEvent,
+    WithdrawEvent,
+};
+use crate::hooks::{hook_messages, HOOK_REPLY_ID};
 use crate::killswitch::execute_cancel_stream_with_threshold;
 use crate::msg::{
-    AveragePriceResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, LatestStreamedPriceResponse,
-    MigrateMsg, PositionResponse, PositionsResponse, QueryMsg, StreamResponse, StreamsResponse,
-    SudoMsg,
+    AccountOperatorResponse, AccountOperatorsResponse, AveragePriceResponse, ConfigResponse,
+    EstimateSubscriptionResponse, ExecuteMsg, ExitPreviewResponse, FeeCollectorMsg,
+    FinalizePreviewResponse, InstantiateMsg, LatestStreamedPriceResponse, MigrateMsg,
+    PositionOperatorResponse, PositionOperatorsResponse, PositionResponse, PositionResult,
+    PositionsBatchResponse, PositionsResponse, PreCommittedResponse, QueryMsg, StreamHookEvent,
+    StreamResponse, StreamResult, StreamsByIdsResponse, StreamsResponse, SudoMsg, ThresholdStatus,
+    UnclaimedFeesResponse,
+};
+use crate::state::{
+    accrue_fee, add_position_stats, burn_position_token, clear_position_operators,
+    mint_position_token, next_stream_id, remove_position_stats, transfer_position_token,
+    update_status_stats, Config, ExitFeeTier, FeeCollector, FeeSide, OperatorGrant,
+    OperatorPermission, Position, ProtocolStats, Status, Stream, ACCOUNT_OPERATORS, CONFIG,
+    LEGACY_CONFIG, POSITIONS, POSITION_OPERATORS, STATS, STREAMS, SUBSCRIBERS, UNCLAIMED_FEES,
 };
-use crate::state::{next_stream_id, Config, Position, Status, Stream, CONFIG, POSITIONS, STREAMS};
-use crate::threshold::ThresholdState;
-use crate::{killswitch, ContractError};
+use crate::threshold::{migrate_legacy_thresholds, Threshold, ThresholdState};
+use crate::{hooks, killswitch, nft, ContractError};
 use cosmwasm_std::{
-    attr, entry_point, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256,
-    Deps, DepsMut, Env, Fraction, MessageInfo, Order, Response, StdError, StdResult, Timestamp,
-    Uint128, Uint256, Uint64,
+    attr, entry_point, to_json_binary, Addr, Api, BankMsg, Binary, BlockInfo, Coin, CosmosMsg,
+    Decimal, Decimal256, Deps, DepsMut, Env, Event, Fraction, MessageInfo, Order, Reply, Response,
+    StdError, StdResult, Storage, Timestamp, Uint128, Uint256, Uint64,
 };
 use cw2::{get_contract_version, set_contract_version};
 use semver::Version;
+use std::collections::BTreeMap;
 
-use crate::helpers::{check_name_and_url, from_semver, get_decimals};
+use crate::discount::{
+    execute_set_fee_discount, execute_set_holder_fee_discounts, fee_discount_for,
+    query_fee_discount, query_holder_fee_discounts,
+};
+use crate::helpers::{
+    check_creation_fee_options, check_exit_fee_tiers, check_fee_side, check_name_and_url,
+    from_semver, get_decimals, split_fee, to_std_err, validate_fee_collectors,
+};
 use cw_storage_plus::Bound;
-use cw_utils::{maybe_addr, must_pay};
+use cw_utils::{maybe_addr, must_pay, Expiration};
 
 // Version and contract info for migration
 const CONTRACT_NAME: &str = "crates.io:cw-streamswap";
@@ -36,21 +62,31 @@ pub fn instantiate(
         return Err(ContractError::InvalidExitFeePercent {});
     }
 
+    check_exit_fee_tiers(msg.exit_fee_percent, &msg.exit_fee_tiers)?;
+    check_fee_side(&msg.fee_side)?;
+
     if msg.stream_creation_fee.is_zero() {
         return Err(ContractError::InvalidStreamCreationFee {});
     }
+    check_creation_fee_options(&msg.stream_creation_denom, &msg.stream_creation_fee_options)?;
 
     let config = Config {
         min_stream_seconds: msg.min_stream_seconds,
         min_seconds_until_start_time: msg.min_seconds_until_start_time,
         stream_creation_denom: msg.stream_creation_denom.clone(),
         stream_creation_fee: msg.stream_creation_fee,
+        stream_creation_fee_options: msg.stream_creation_fee_options,
         exit_fee_percent: msg.exit_fee_percent,
-        fee_collector: deps.api.addr_validate(&msg.fee_collector)?,
+        exit_fee_tiers: msg.exit_fee_tiers,
+        fee_side: msg.fee_side,
+        fee_collectors: validate_fee_collectors(deps.api, msg.fee_collectors)?,
         protocol_admin: deps.api.addr_validate(&msg.protocol_admin)?,
         accepted_in_denom: msg.accepted_in_denom,
+        keeper_reward_percent: Decimal::zero(),
+        position_nfts: false,
     };
     CONFIG.save(deps.storage, &config)?;
+    STATS.save(deps.storage, &ProtocolStats::default())?;
 
     let attrs = vec![
         attr("action", "instantiate"),
@@ -62,7 +98,10 @@ pub fn instantiate(
         attr("stream_creation_denom", msg.stream_creation_denom),
         attr("stream_creation_fee", msg.stream_creation_fee),
         attr("exit_fee_percent", msg.exit_fee_percent.to_string()),
-        attr("fee_collector", msg.fee_collector),
+        attr(
+            "fee_collectors",
+            fee_collectors_attr(&config.fee_collectors),
+        ),
         attr("protocol_admin", msg.protocol_admin),
     ];
     Ok(Response::default().add_attributes(attrs))
@@ -94,6 +133,26 @@ pub fn execute(
             stream_id,
             new_operator,
         } => execute_update_operator(deps, env, info, stream_id, new_operator),
+        ExecuteMsg::GrantOperator {
+            stream_id,
+            operator,
+            permissions,
+            expires,
+        } => execute_grant_operator(deps, env, info, stream_id, operator, permissions, expires),
+        ExecuteMsg::RevokeOperator {
+            stream_id,
+            operator,
+        } => execute_revoke_operator(deps, info, stream_id, operator),
+        ExecuteMsg::ApproveOperator { operator, expires } => {
+            execute_approve_operator(deps, env, info, operator, expires)
+        }
+        ExecuteMsg::RevokeOperatorApproval { operator } => {
+            execute_revoke_operator_approval(deps, info, operator)
+        }
+        ExecuteMsg::TransferPosition {
+            stream_id,
+            recipient,
+        } => execute_transfer_position(deps, env, info, stream_id, recipient),
         ExecuteMsg::UpdatePosition {
             stream_id,
             operator_target,
@@ -102,6 +161,16 @@ pub fn execute(
         ExecuteMsg::CancelStreamWithThreshold { stream_id } => {
             execute_cancel_stream_with_threshold(deps, env, info, stream_id)
         }
+        ExecuteMsg::ProcessRefunds { stream_id, limit } => {
+            killswitch::execute_process_refunds(deps, env, stream_id, limit)
+        }
+        ExecuteMsg::DistributeExits { stream_id, limit } => {
+            execute_distribute_exits(deps, env, info, stream_id, limit)
+        }
+        ExecuteMsg::UpdateThreshold {
+            stream_id,
+            threshold,
+        } => execute_update_threshold(deps, env, info, stream_id, threshold),
         ExecuteMsg::Subscribe {
             stream_id,
             operator_target,
@@ -134,6 +203,7 @@ pub fn execute(
             stream_id,
             cap,
             operator_target,
+            recipient,
         } => {
             let stream = STREAMS.load(deps.storage, stream_id)?;
             if stream.start_time > env.block.time {
@@ -145,6 +215,7 @@ pub fn execute(
                     stream,
                     cap,
                     operator_target,
+                    recipient,
                 )?)
             } else {
                 Ok(execute_withdraw(
@@ -155,9 +226,15 @@ pub fn execute(
                     stream,
                     cap,
                     operator_target,
+                    recipient,
                 )?)
             }
         }
+        ExecuteMsg::ClaimPurchased {
+            stream_id,
+            cap,
+            operator_target,
+        } => execute_claim_purchased(deps, env, info, stream_id, cap, operator_target),
         ExecuteMsg::FinalizeStream {
             stream_id,
             new_treasury,
@@ -165,7 +242,8 @@ pub fn execute(
         ExecuteMsg::ExitStream {
             stream_id,
             operator_target,
-        } => execute_exit_stream(deps, env, info, stream_id, operator_target),
+            recipient,
+        } => execute_exit_stream(deps, env, info, stream_id, operator_target, recipient),
 
         ExecuteMsg::PauseStream { stream_id } => {
             killswitch::execute_pause_stream(deps, env, info, stream_id)
@@ -176,15 +254,72 @@ pub fn execute(
         ExecuteMsg::CancelStream { stream_id } => {
             killswitch::execute_cancel_stream(deps, env, info, stream_id)
         }
+        ExecuteMsg::SetFeeDiscount { address, discount } => {
+            execute_set_fee_discount(deps, info, address, discount)
+        }
+        ExecuteMsg::SetHolderFeeDiscounts { discounts } => {
+            execute_set_holder_fee_discounts(deps, info, discounts)
+        }
+        ExecuteMsg::ClaimFees {
+            collector,
+            recipient,
+        } => execute_claim_fees(deps, info, collector, recipient),
+        ExecuteMsg::AddHook {
+            stream_id,
+            contract,
+        } => hooks::execute_add_hook(deps, info, stream_id, contract),
+        ExecuteMsg::RemoveHook {
+            stream_id,
+            contract,
+        } => hooks::execute_remove_hook(deps, info, stream_id, contract),
+        ExecuteMsg::TransferNft {
+            recipient,
+            token_id,
+        } => nft::execute_transfer_nft(deps, env, info, recipient, token_id),
+        ExecuteMsg::SendNft {
+            contract,
+            token_id,
+            msg,
+        } => nft::execute_send_nft(deps, env, info, contract, token_id, msg),
+        ExecuteMsg::Approve {
+            spender,
+            token_id,
+            expires,
+        } => nft::execute_approve(deps, env, info, spender, token_id, expires),
+        ExecuteMsg::Revoke { spender, token_id } => {
+            nft::execute_revoke(deps, env, info, spender, token_id)
+        }
+        ExecuteMsg::ApproveAll { operator, expires } => {
+            nft::execute_approve_all(deps, env, info, operator, expires)
+        }
+        ExecuteMsg::RevokeAll { operator } => nft::execute_revoke_all(deps, info, operator),
+        ExecuteMsg::Burn { token_id } => nft::execute_burn(deps, env, info, token_id),
         ExecuteMsg::WithdrawPaused {
             stream_id,
             cap,
             operator_target,
-        } => killswitch::execute_withdraw_paused(deps, env, info, stream_id, cap, operator_target),
+            recipient,
+        } => killswitch::execute_withdraw_paused(
+            deps,
+            env,
+            info,
+            stream_id,
+            cap,
+            operator_target,
+            recipient,
+        ),
         ExecuteMsg::ExitCancelled {
             stream_id,
             operator_target,
-        } => killswitch::execute_exit_cancelled(deps, env, info, stream_id, operator_target),
+            recipient,
+        } => killswitch::execute_exit_cancelled(
+            deps,
+            env,
+            info,
+            stream_id,
+            operator_target,
+            recipient,
+        ),
         ExecuteMsg::UpdateProtocolAdmin {
             new_protocol_admin: new_admin,
         } => execute_update_protocol_admin(deps, env, info, new_admin),
@@ -193,9 +328,14 @@ pub fn execute(
             min_duration_until_start_time,
             stream_creation_denom,
             stream_creation_fee,
-            fee_collector,
+            stream_creation_fee_options,
+            fee_collectors,
             accepted_in_denom,
             exit_fee_percent,
+            exit_fee_tiers,
+            fee_side,
+            keeper_reward_percent,
+            position_nfts,
         } => execute_update_config(
             deps,
             env,
@@ -204,9 +344,14 @@ pub fn execute(
             min_duration_until_start_time,
             stream_creation_denom,
             stream_creation_fee,
-            fee_collector,
+            stream_creation_fee_options,
+            fee_collectors,
             accepted_in_denom,
             exit_fee_percent,
+            exit_fee_tiers,
+            fee_side,
+            keeper_reward_percent,
+            position_nfts,
         ),
     }
 }
@@ -223,7 +368,7 @@ pub fn execute_create_stream(
     out_supply: Uint128,
     start_time: Timestamp,
     end_time: Timestamp,
-    threshold: Option<Uint128>,
+    threshold: Option<Threshold>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     if end_time < start_time {
@@ -252,14 +397,37 @@ pub fn execute_create_stream(
         return Err(ContractError::ZeroOutSupply {});
     }
 
-    if out_denom == config.stream_creation_denom {
+    let fee_discount = fee_discount_for(deps.as_ref(), &info.sender)?;
+    let mut fee_options = config.creation_fee_options();
+    let mut exit_fee_percent = config.exit_fee_percent;
+    let mut exit_fee_tiers = config.exit_fee_tiers;
+    if let Some(discount) = &fee_discount {
+        for option in fee_options.iter_mut() {
+            option.amount = discount.creation_fee(option.amount)?;
+        }
+        exit_fee_percent = discount.exit_fee_percent(exit_fee_percent)?;
+        for tier in exit_fee_tiers.iter_mut() {
+            tier.fee_percent = discount.exit_fee_percent(tier.fee_percent)?;
+        }
+    }
+
+    // creation fee option is picked by the fee denom sent along out_denom,
+    // falling back to an option paid in out_denom itself
+    let creation_fee = fee_options
+        .iter()
+        .find(|o| o.denom != out_denom && info.funds.iter().any(|p| p.denom == o.denom))
+        .or_else(|| fee_options.iter().find(|o| o.denom == out_denom))
+        .unwrap_or(&fee_options[0])
+        .clone();
+
+    if out_denom == creation_fee.denom {
         let total_funds = info
             .funds
             .iter()
-            .find(|p| p.denom == config.stream_creation_denom)
+            .find(|p| p.denom == creation_fee.denom)
             .ok_or(ContractError::NoFundsSent {})?;
 
-        if total_funds.amount != config.stream_creation_fee + out_supply {
+        if total_funds.amount != creation_fee.amount + out_supply {
             return Err(ContractError::StreamOutSupplyFundsRequired {});
         }
         // check for extra funds sent in msg
@@ -277,19 +445,22 @@ pub fn execute_create_stream(
             return Err(ContractError::StreamOutSupplyFundsRequired {});
         }
 
-        let creation_fee = info
-            .funds
-            .iter()
-            .find(|p| p.denom == config.stream_creation_denom)
-            .ok_or(ContractError::NoFundsSent {})?;
-        if creation_fee.amount != config.stream_creation_fee {
-            return Err(ContractError::StreamCreationFeeRequired {});
+        // fully discounted creation fee is not sent at all
+        if !creation_fee.amount.is_zero() {
+            let fee_funds = info
+                .funds
+                .iter()
+                .find(|p| p.denom == creation_fee.denom)
+                .ok_or(ContractError::NoFundsSent {})?;
+            if fee_funds.amount != creation_fee.amount {
+                return Err(ContractError::StreamCreationFeeRequired {});
+            }
         }
 
         if info
             .funds
             .iter()
-            .any(|p| p.denom != out_denom && p.denom != config.stream_creation_denom)
+            .any(|p| p.denom != out_denom && p.denom != creation_fee.denom)
         {
             return Err(ContractError::InvalidFunds {});
         }
@@ -297,7 +468,7 @@ pub fn execute_create_stream(
 
     check_name_and_url(&name, &url)?;
 
-    let stream = Stream::new(
+    let mut stream = Stream::new(
         name.clone(),
         deps.api.addr_validate(&treasury)?,
         url.clone(),
@@ -307,17 +478,24 @@ pub fn execute_create_stream(
         start_time,
         end_time,
         start_time,
-        config.stream_creation_denom,
-        config.stream_creation_fee,
-        config.exit_fee_percent,
+        creation_fee.denom,
+        creation_fee.amount,
+        exit_fee_percent,
+        exit_fee_tiers,
     );
+    stream.fee_discount = fee_discount;
+    stream.fee_side = config.fee_side;
     let id = next_stream_id(deps.storage)?;
     STREAMS.save(deps.storage, id, &stream)?;
 
+    let mut stats = STATS.load(deps.storage)?;
+    stats.add_stream(&stream.status);
+    STATS.save(deps.storage, &stats)?;
+
     let threshold_state = ThresholdState::new();
-    threshold_state.set_threshold_if_any(threshold, id, deps.storage)?;
+    threshold_state.set_threshold_if_any(threshold.clone(), id, deps.storage)?;
 
-    let attr = vec![
+    let mut attrs = vec![
         attr("action", "create_stream"),
         attr("id", id.to_string()),
         attr("treasury", treasury),
@@ -329,7 +507,31 @@ pub fn execute_create_stream(
         attr("start_time", start_time.to_string()),
         attr("end_time", end_time.to_string()),
     ];
-    Ok(Response::default().add_attributes(attr))
+    if let Some(discount) = &stream.fee_discount {
+        attrs.push(attr(
+            "creation_fee_discount",
+            discount.creation_fee_discount.to_string(),
+        ));
+        attrs.push(attr(
+            "exit_fee_discount",
+            discount.exit_fee_discount.to_string(),
+        ));
+    }
+    Ok(Response::default()
+        .add_attributes(attrs)
+        .add_event(
+            CreateStreamEvent {
+                stream_id: id,
+                stream: &stream,
+                threshold: threshold.as_ref(),
+            }
+            .into(),
+        )
+        .add_submessages(hook_messages(
+            deps.storage,
+            id,
+            StreamHookEvent::Create { stream_id: id },
+        )?))
 }
 
 pub fn execute_update_protocol_admin(
@@ -342,15 +544,21 @@ pub fn execute_update_protocol_admin(
     if info.sender != config.protocol_admin {
         return Err(ContractError::Unauthorized {});
     }
+    let previous = config.protocol_admin;
     config.protocol_admin = deps.api.addr_validate(&new_admin)?;
     CONFIG.save(deps.storage, &config)?;
+    let event: Event = UpdateProtocolAdminEvent {
+        previous: &previous,
+        protocol_admin: &config.protocol_admin,
+    }
+    .into();
 
     let attrs = vec![
         attr("action", "update_protocol_admin"),
         attr("new_admin", new_admin),
     ];
 
-    Ok(Response::default().add_attributes(attrs))
+    Ok(Response::default().add_event(event).add_attributes(attrs))
 }
 
 /// Updates stream to calculate released distribution and spent amount
@@ -363,6 +571,7 @@ pub fn execute_update_stream(
     if stream.is_paused() {
         return Err(ContractError::StreamPaused {});
     }
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
     let (_, dist_amount) = update_stream(env.block.time, &mut stream)?;
     STREAMS.save(deps.storage, stream_id, &stream)?;
 
@@ -372,7 +581,14 @@ pub fn execute_update_stream(
         attr("new_distribution_amount", dist_amount),
         attr("dist_index", stream.dist_index.to_string()),
     ];
-    let res = Response::new().add_attributes(attrs);
+    let res = Response::new().add_attributes(attrs).add_event(
+        UpdateStreamEvent {
+            stream_id,
+            stream: &stream,
+            distributed: dist_amount,
+        }
+        .into(),
+    );
     Ok(res)
 }
 
@@ -426,6 +642,20 @@ pub fn update_stream(
     Ok((diff, new_distribution_balance))
 }
 
+// waiting streams become active once start_time is reached, deposits before it don't activate them
+pub(crate) fn activate_stream(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    stream: &mut Stream,
+) -> StdResult<()> {
+    let status = stream.status_at(now);
+    if status != stream.status {
+        update_status_stats(storage, &stream.status, &status)?;
+        stream.status = status;
+    }
+    Ok(())
+}
+
 fn calculate_diff(end_time: Timestamp, last_updated: Timestamp, now: Timestamp) -> Decimal {
     // diff = (now - last_updated) / (end_time - last_updated)
     let now = if now > end_time { end_time } else { now };
@@ -449,7 +679,14 @@ pub fn execute_update_position(
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    check_access(&info, &position.owner, &position.operator)?;
+    check_access(
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        OperatorPermission::Update,
+    )?;
 
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
     // check if stream is paused
@@ -458,6 +695,7 @@ pub fn execute_update_position(
     }
 
     // sync stream
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
     update_stream(env.block.time, &mut stream)?;
     STREAMS.save(deps.storage, stream_id, &stream)?;
 
@@ -477,7 +715,135 @@ pub fn execute_update_position(
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("operator_target", operator_target)
         .add_attribute("purchased", purchased)
-        .add_attribute("spent", spent))
+        .add_attribute("spent", spent)
+        .add_event(
+            UpdatePositionEvent {
+                stream_id,
+                stream: &stream,
+                position: &position,
+                purchased,
+                spent,
+            }
+            .into(),
+        ))
+}
+
+pub fn execute_transfer_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    // only the owner can transfer a position, operators cannot
+    let (stream, position, merged) = transfer_position(
+        deps.storage,
+        env.block.time,
+        stream_id,
+        &info.sender,
+        &recipient,
+    )?;
+    let event: Event = TransferPositionEvent {
+        stream_id,
+        stream: &stream,
+        owner: &info.sender,
+        position: &position,
+        merged,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "transfer_position")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("owner", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("merged", merged.to_string())
+        .add_attribute("in_balance", position.in_balance)
+        .add_attribute("shares", position.shares)
+        .add_attribute("purchased", position.purchased)
+        .add_attributes(position.token_id.map(|token_id| attr("token_id", token_id))))
+}
+
+// moves the position of the owner to the recipient, merging it into the recipient's position
+// if there is one. On merge the transferred token is burned unless the recipient has no token.
+// returns the synced stream, the recipient's position and if it was merged
+pub(crate) fn transfer_position(
+    storage: &mut dyn Storage,
+    now: Timestamp,
+    stream_id: u64,
+    owner: &Addr,
+    recipient: &Addr,
+) -> Result<(Stream, Position, bool), ContractError> {
+    if recipient == owner {
+        return Err(ContractError::InvalidTransferRecipient {});
+    }
+    let mut position = POSITIONS.load(storage, (stream_id, owner))?;
+
+    let mut stream = STREAMS.load(storage, stream_id)?;
+    if stream.is_killswitch_active() {
+        return Err(ContractError::StreamKillswitchActive {});
+    }
+
+    // sync stream and both positions so the merged position starts from the latest distribution
+    activate_stream(storage, now, &mut stream)?;
+    update_stream(now, &mut stream)?;
+    update_position(
+        stream.dist_index,
+        stream.shares,
+        stream.last_updated,
+        stream.in_supply,
+        &mut position,
+    )?;
+
+    POSITIONS.remove(storage, (stream_id, owner));
+    clear_position_operators(storage, stream_id, owner)?;
+    remove_position_stats(storage)?;
+
+    let merged = match POSITIONS.may_load(storage, (stream_id, recipient))? {
+        Some(mut existing) => {
+            update_position(
+                stream.dist_index,
+                stream.shares,
+                stream.last_updated,
+                stream.in_supply,
+                &mut existing,
+            )?;
+            // two subscribers become one
+            if !existing.shares.is_zero() && !position.shares.is_zero() {
+                stream.subscribers = stream.subscribers.saturating_sub(1);
+            }
+            existing.in_balance = existing.in_balance.checked_add(position.in_balance)?;
+            existing.shares = existing.shares.checked_add(position.shares)?;
+            existing.purchased = existing.purchased.checked_add(position.purchased)?;
+            existing.pending_purchase = existing
+                .pending_purchase
+                .checked_add(position.pending_purchase)?;
+            existing.spent = existing.spent.checked_add(position.spent)?;
+            existing.claimed = existing.claimed.checked_add(position.claimed)?;
+            if existing.token_id.is_some() {
+                burn_position_token(storage, &position)?;
+            } else if let Some(token_id) = position.token_id.take() {
+                transfer_position_token(storage, &token_id, recipient)?;
+                existing.token_id = Some(token_id);
+            }
+            position = existing;
+            true
+        }
+        None => {
+            position.owner = recipient.clone();
+            if let Some(token_id) = &position.token_id {
+                transfer_position_token(storage, token_id, recipient)?;
+            }
+            add_position_stats(storage, recipient)?;
+            false
+        }
+    };
+    POSITIONS.save(storage, (stream_id, recipient), &position)?;
+    STREAMS.save(storage, stream_id, &stream)?;
+
+    Ok((stream, position, merged))
 }
 
 // calculate the user purchase based on the positions index and the global index.
@@ -544,10 +910,7 @@ pub fn execute_subscribe(
     if env.block.time >= stream.end_time {
         return Err(ContractError::StreamEnded {});
     }
-    //On first subscibe change status to Active
-    if stream.status == Status::Waiting {
-        stream.status = Status::Active
-    }
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
 
     let in_amount = must_pay(&info, &stream.in_denom)?;
     let new_shares;
@@ -556,7 +919,8 @@ pub fn execute_subscribe(
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let position = POSITIONS.may_load(deps.storage, (stream_id, &operator_target))?;
-    match position {
+    let mut token_id = None;
+    let position = match position {
         None => {
             // operator cannot create a position in behalf of anyone
             if operator_target != info.sender {
@@ -565,18 +929,41 @@ pub fn execute_subscribe(
             update_stream(env.block.time, &mut stream)?;
             new_shares = stream.compute_shares_amount(in_amount, false);
             // new positions do not update purchase as it has no effect on distribution
-            let new_position = Position::new(
+            let mut new_position = Position::new(
                 info.sender,
                 in_amount,
                 new_shares,
                 Some(stream.dist_index),
                 env.block.time,
-                operator,
             );
+            if CONFIG.load(deps.storage)?.position_nfts {
+                token_id = Some(mint_position_token(
+                    deps.storage,
+                    stream_id,
+                    &mut new_position,
+                )?);
+            }
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &new_position)?;
+            if let Some(operator) = &operator {
+                POSITION_OPERATORS.save(
+                    deps.storage,
+                    (stream_id, &operator_target, operator),
+                    &OperatorGrant::full(),
+                )?;
+            }
+            add_position_stats(deps.storage, &operator_target)?;
+            stream.subscribers += 1;
+            new_position
         }
         Some(mut position) => {
-            check_access(&info, &position.owner, &position.operator)?;
+            check_access(
+                deps.storage,
+                &env.block,
+                &info,
+                stream_id,
+                &position,
+                OperatorPermission::Subscribe,
+            )?;
 
             // incoming tokens should not participate in prev distribution
             update_stream(env.block.time, &mut stream)?;
@@ -589,11 +976,16 @@ pub fn execute_subscribe(
                 &mut position,
             )?;
 
+            // positions withdrawn in full count again once they subscribe
+            if position.shares.is_zero() {
+                stream.subscribers += 1;
+            }
             position.in_balance = position.in_balance.checked_add(in_amount)?;
             position.shares = position.shares.checked_add(new_shares)?;
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &position)?;
+            position
         }
-    }
+    };
 
     // increase in supply and shares
     stream.in_supply = stream.in_supply.checked_add(in_amount)?;
@@ -603,9 +995,30 @@ pub fn execute_subscribe(
     let res = Response::new()
         .add_attribute("action", "subscribe")
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("owner", operator_target)
+        .add_attribute("owner", operator_target.clone())
         .add_attribute("in_supply", stream.in_supply)
-        .add_attribute("in_amount", in_amount);
+        .add_attribute("in_amount", in_amount)
+        .add_attributes(token_id.map(|token_id| attr("token_id", token_id)))
+        .add_event(
+            SubscribeEvent {
+                stream_id,
+                stream: &stream,
+                position: &position,
+                in_amount,
+                shares: new_shares,
+                pending: false,
+            }
+            .into(),
+        )
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Subscribe {
+                stream_id,
+                owner: operator_target.to_string(),
+                in_amount,
+            },
+        )?);
 
     Ok(res)
 }
@@ -630,40 +1043,92 @@ pub fn execute_subscribe_pending(
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let position = POSITIONS.may_load(deps.storage, (stream_id, &operator_target))?;
-    match position {
+    let mut token_id = None;
+    let position = match position {
         None => {
             // operator cannot create a position in behalf of anyone
             if operator_target != info.sender {
                 return Err(ContractError::Unauthorized {});
             }
-            let new_position = Position::new(
+            let mut new_position = Position::new(
                 info.sender,
                 in_amount,
                 new_shares,
                 Some(stream.dist_index),
                 env.block.time,
-                operator,
             );
+            if CONFIG.load(deps.storage)?.position_nfts {
+                token_id = Some(mint_position_token(
+                    deps.storage,
+                    stream_id,
+                    &mut new_position,
+                )?);
+            }
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &new_position)?;
+            if let Some(operator) = &operator {
+                POSITION_OPERATORS.save(
+                    deps.storage,
+                    (stream_id, &operator_target, operator),
+                    &OperatorGrant::full(),
+                )?;
+            }
+            add_position_stats(deps.storage, &operator_target)?;
+            stream.subscribers += 1;
+            new_position
         }
         Some(mut position) => {
-            check_access(&info, &position.owner, &position.operator)?;
+            check_access(
+                deps.storage,
+                &env.block,
+                &info,
+                stream_id,
+                &position,
+                OperatorPermission::Subscribe,
+            )?;
             // if subscibed already, we wont update its position but just increase its in_balance and shares
+            // positions withdrawn in full count again once they subscribe
+            if position.shares.is_zero() {
+                stream.subscribers += 1;
+            }
             position.in_balance = position.in_balance.checked_add(in_amount)?;
             position.shares = position.shares.checked_add(new_shares)?;
             POSITIONS.save(deps.storage, (stream_id, &operator_target), &position)?;
+            position
         }
-    }
+    };
     stream.in_supply = stream.in_supply.checked_add(in_amount)?;
+    // deposits before start are commitments, they can be withdrawn in full until start
+    stream.pre_committed_in = stream.pre_committed_in.checked_add(in_amount)?;
     stream.shares = stream.shares.checked_add(new_shares)?;
     STREAMS.save(deps.storage, stream_id, &stream)?;
 
     Ok(Response::new()
         .add_attribute("action", "subscribe_pending")
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("owner", operator_target)
+        .add_attribute("owner", operator_target.clone())
         .add_attribute("in_supply", stream.in_supply)
-        .add_attribute("in_amount", in_amount))
+        .add_attribute("in_amount", in_amount)
+        .add_attributes(token_id.map(|token_id| attr("token_id", token_id)))
+        .add_event(
+            SubscribeEvent {
+                stream_id,
+                stream: &stream,
+                position: &position,
+                in_amount,
+                shares: new_shares,
+                pending: true,
+            }
+            .into(),
+        )
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Subscribe {
+                stream_id,
+                owner: operator_target.to_string(),
+                in_amount,
+            },
+        )?))
 }
 
 pub fn execute_update_operator(
@@ -673,20 +1138,207 @@ pub fn execute_update_operator(
     stream_id: u64,
     operator: Option<String>,
 ) -> Result<Response, ContractError> {
-    let mut position = POSITIONS.load(deps.storage, (stream_id, &info.sender))?;
+    POSITIONS.load(deps.storage, (stream_id, &info.sender))?;
 
     let operator = maybe_addr(deps.api, operator)?;
-    position.operator = operator.clone();
-
-    POSITIONS.save(deps.storage, (stream_id, &info.sender), &position)?;
+    if operator.as_ref() == Some(&info.sender) {
+        return Err(ContractError::InvalidOperator {});
+    }
+    // the operator replaces all operators of the position with full control
+    clear_position_operators(deps.storage, stream_id, &info.sender)?;
+    if let Some(operator) = &operator {
+        POSITION_OPERATORS.save(
+            deps.storage,
+            (stream_id, &info.sender, operator),
+            &OperatorGrant::full(),
+        )?;
+    }
+    let event: Event = UpdateOperatorEvent {
+        stream_id,
+        owner: &info.sender,
+        operator: operator.as_ref(),
+    }
+    .into();
 
     Ok(Response::new()
+        .add_event(event)
         .add_attribute("action", "update_operator")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("owner", info.sender)
         .add_attribute("operator", operator.unwrap_or_else(|| Addr::unchecked(""))))
 }
 
+pub fn execute_grant_operator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    operator: String,
+    permissions: Vec<OperatorPermission>,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    // only the owner manages operators of its position
+    if !POSITIONS.has(deps.storage, (stream_id, &info.sender)) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let operator = deps.api.addr_validate(&operator)?;
+    if operator == info.sender {
+        return Err(ContractError::InvalidOperator {});
+    }
+    if permissions.is_empty() {
+        return Err(ContractError::InvalidOperatorPermissions {});
+    }
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+
+    let permissions_attr = permissions
+        .iter()
+        .map(|permission| format!("{:?}", permission).to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",");
+    let grant = OperatorGrant {
+        permissions,
+        expires,
+    };
+    POSITION_OPERATORS.save(deps.storage, (stream_id, &info.sender, &operator), &grant)?;
+    let event: Event = GrantOperatorEvent {
+        stream_id,
+        owner: &info.sender,
+        operator: &operator,
+        grant: &grant,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "grant_operator")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("owner", info.sender)
+        .add_attribute("operator", operator)
+        .add_attribute("permissions", permissions_attr)
+        .add_attribute("expires", expires.to_string()))
+}
+
+pub fn execute_approve_operator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    if operator == info.sender {
+        return Err(ContractError::InvalidOperator {});
+    }
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+    ACCOUNT_OPERATORS.save(deps.storage, (&info.sender, &operator), &expires)?;
+    let event: Event = ApproveOperatorEvent {
+        owner: &info.sender,
+        operator: &operator,
+        expires: &expires,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "approve_operator")
+        .add_attribute("owner", info.sender)
+        .add_attribute("operator", operator)
+        .add_attribute("expires", expires.to_string()))
+}
+
+pub fn execute_revoke_operator_approval(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: String,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    if !ACCOUNT_OPERATORS.has(deps.storage, (&info.sender, &operator)) {
+        return Err(ContractError::Unauthorized {});
+    }
+    ACCOUNT_OPERATORS.remove(deps.storage, (&info.sender, &operator));
+    let event: Event = RevokeOperatorApprovalEvent {
+        owner: &info.sender,
+        operator: &operator,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "revoke_operator_approval")
+        .add_attribute("owner", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn execute_revoke_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    stream_id: u64,
+    operator: String,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    POSITION_OPERATORS
+        .load(deps.storage, (stream_id, &info.sender, &operator))
+        .map_err(|_| ContractError::Unauthorized {})?;
+    POSITION_OPERATORS.remove(deps.storage, (stream_id, &info.sender, &operator));
+    let event: Event = RevokeOperatorEvent {
+        stream_id,
+        owner: &info.sender,
+        operator: &operator,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "revoke_operator")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("owner", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn execute_update_threshold(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    threshold: Option<Threshold>,
+) -> Result<Response, ContractError> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    if info.sender != stream.treasury {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let previous = ThresholdState::new().update_threshold(
+        stream_id,
+        deps.storage,
+        &stream,
+        env.block.time,
+        threshold.clone(),
+    )?;
+
+    let event: Event = UpdateThresholdEvent {
+        stream_id,
+        stream: &stream,
+        previous: previous.as_ref(),
+        threshold: threshold.as_ref(),
+    }
+    .into();
+    let threshold_attr =
+        |threshold: Option<Threshold>| threshold.map_or("none".to_string(), |t| t.to_string());
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "update_threshold")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("previous_threshold", threshold_attr(previous))
+        .add_attribute("threshold", threshold_attr(threshold)))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn execute_withdraw(
     deps: DepsMut,
     env: Env,
@@ -695,6 +1347,7 @@ pub fn execute_withdraw(
     mut stream: Stream,
     cap: Option<Uint128>,
     operator_target: Option<String>,
+    recipient: Option<String>,
 ) -> Result<Response, ContractError> {
     // check if stream is paused
     if stream.is_killswitch_active() {
@@ -708,8 +1361,25 @@ pub fn execute_withdraw(
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    check_access(&info, &position.owner, &position.operator)?;
+    check_access(
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        OperatorPermission::Withdraw,
+    )?;
+    let recipient = check_recipient(
+        deps.api,
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        recipient,
+    )?;
 
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
     update_stream(env.block.time, &mut stream)?;
     update_position(
         stream.dist_index,
@@ -740,6 +1410,10 @@ pub fn execute_withdraw(
     stream.shares = stream.shares.checked_sub(shares_amount)?;
     position.in_balance = position.in_balance.checked_sub(withdraw_amount)?;
     position.shares = position.shares.checked_sub(shares_amount)?;
+    // positions withdrawn in full no longer count as subscribers
+    if position.shares.is_zero() {
+        stream.subscribers = stream.subscribers.saturating_sub(1);
+    }
 
     STREAMS.save(deps.storage, stream_id, &stream)?;
     POSITIONS.save(deps.storage, (stream_id, &position.owner), &position)?;
@@ -749,36 +1423,75 @@ pub fn execute_withdraw(
         attr("stream_id", stream_id.to_string()),
         attr("operator_target", operator_target.clone()),
         attr("withdraw_amount", withdraw_amount),
+        attr("recipient", recipient.clone()),
     ];
 
+    let event: Event = WithdrawEvent {
+        stream_id,
+        stream: &stream,
+        position: &position,
+        recipient: &recipient,
+        amount: withdraw_amount,
+        shares: shares_amount,
+    }
+    .into();
+
     // send funds to withdraw address or to the sender
     let res = Response::new()
         .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: operator_target.to_string(),
+            to_address: recipient.to_string(),
             amount: vec![Coin {
                 denom: stream.in_denom,
                 amount: withdraw_amount,
             }],
         }))
-        .add_attributes(attributes);
+        .add_attributes(attributes)
+        .add_event(event)
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Withdraw {
+                stream_id,
+                owner: position.owner.to_string(),
+                amount: withdraw_amount,
+            },
+        )?);
 
     Ok(res)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_withdraw_pending(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     stream_id: u64,
     mut stream: Stream,
     cap: Option<Uint128>,
     operator_target: Option<String>,
+    recipient: Option<String>,
 ) -> Result<Response, ContractError> {
     // check if stream is paused
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    check_access(&info, &position.owner, &position.operator)?;
+    check_access(
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        OperatorPermission::Withdraw,
+    )?;
+    let recipient = check_recipient(
+        deps.api,
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        recipient,
+    )?;
 
     let withdraw_amount = cap.unwrap_or(position.in_balance);
     // if amount to withdraw more then deduced buy balance throw error
@@ -798,9 +1511,14 @@ pub fn execute_withdraw_pending(
     };
 
     stream.in_supply = stream.in_supply.checked_sub(withdraw_amount)?;
+    stream.pre_committed_in = stream.pre_committed_in.checked_sub(withdraw_amount)?;
     stream.shares = stream.shares.checked_sub(shares_amount)?;
     position.in_balance = position.in_balance.checked_sub(withdraw_amount)?;
     position.shares = position.shares.checked_sub(shares_amount)?;
+    // positions withdrawn in full no longer count as subscribers
+    if position.shares.is_zero() {
+        stream.subscribers = stream.subscribers.saturating_sub(1);
+    }
 
     STREAMS.save(deps.storage, stream_id, &stream)?;
     POSITIONS.save(deps.storage, (stream_id, &position.owner), &position)?;
@@ -810,50 +1528,153 @@ pub fn execute_withdraw_pending(
         attr("stream_id", stream_id.to_string()),
         attr("operator_target", operator_target.clone()),
         attr("withdraw_amount", withdraw_amount),
+        attr("recipient", recipient.clone()),
     ];
 
+    let event: Event = WithdrawEvent {
+        stream_id,
+        stream: &stream,
+        position: &position,
+        recipient: &recipient,
+        amount: withdraw_amount,
+        shares: shares_amount,
+    }
+    .into();
+
     // send funds to withdraw address or to the sender
     let res = Response::new()
         .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: operator_target.to_string(),
+            to_address: recipient.to_string(),
             amount: vec![Coin {
                 denom: stream.in_denom,
                 amount: withdraw_amount,
             }],
         }))
-        .add_attributes(attributes);
+        .add_attributes(attributes)
+        .add_event(event)
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Withdraw {
+                stream_id,
+                owner: position.owner.to_string(),
+                amount: withdraw_amount,
+            },
+        )?);
 
     Ok(res)
 }
 
-pub fn execute_finalize_stream(
+pub fn execute_claim_purchased(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     stream_id: u64,
-    new_treasury: Option<String>,
+    cap: Option<Uint128>,
+    operator_target: Option<String>,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
-    // check if the stream is already finalized
-    if stream.status == Status::Finalized {
-        return Err(ContractError::StreamAlreadyFinalized {});
-    }
-    // check if killswitch is active
+    // check if stream is paused
     if stream.is_killswitch_active() {
         return Err(ContractError::StreamKillswitchActive {});
     }
-    if stream.treasury != info.sender {
-        return Err(ContractError::Unauthorized {});
+    // after the stream ends purchased tokens are paid with exit
+    if env.block.time >= stream.end_time {
+        return Err(ContractError::StreamEnded {});
     }
-    if env.block.time <= stream.end_time {
-        return Err(ContractError::StreamNotEnded {});
+
+    let operator_target =
+        maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
+    let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
+    check_access(
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        OperatorPermission::Exit,
+    )?;
+
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
+    update_stream(env.block.time, &mut stream)?;
+    update_position(
+        stream.dist_index,
+        stream.shares,
+        stream.last_updated,
+        stream.in_supply,
+        &mut position,
+    )?;
+
+    // claimed tokens can't be refunded, claims are allowed once the threshold can't be missed
+    let threshold_state = ThresholdState::new();
+    threshold_state.error_if_not_guaranteed(stream_id, deps.storage, &stream)?;
+
+    // out side fee is withheld at the current spend rate, exit settles the final fee
+    let (_, out_percent) = stream
+        .fee_side
+        .split(stream.exit_fee_percent_for(position.spent))?;
+    let swap_fee_out = Decimal::from_ratio(position.purchased, Uint128::one())
+        .checked_mul(out_percent)?
+        * Uint128::one();
+    let claimable = position
+        .purchased
+        .checked_sub(swap_fee_out)?
+        .saturating_sub(position.claimed);
+
+    let claim_amount = cap.unwrap_or(claimable);
+    if claim_amount > claimable {
+        return Err(ContractError::ClaimAmountExceedsPurchased(claim_amount));
     }
-    if stream.last_updated < stream.end_time {
-        update_stream(env.block.time, &mut stream)?;
+    if claim_amount.is_zero() {
+        return Err(ContractError::InvalidClaimAmount {});
+    }
+
+    position.claimed = position.claimed.checked_add(claim_amount)?;
+    stream.out_claimed = stream.out_claimed.checked_add(claim_amount)?;
+    STREAMS.save(deps.storage, stream_id, &stream)?;
+    POSITIONS.save(deps.storage, (stream_id, &position.owner), &position)?;
+
+    let event: Event = ClaimPurchasedEvent {
+        stream_id,
+        stream: &stream,
+        position: &position,
+        recipient: &operator_target,
+        amount: claim_amount,
+    }
+    .into();
+    Ok(Response::new()
+        .add_event(event)
+        .add_message(CosmosMsg::Bank(BankMsg::Send {
+            to_address: operator_target.to_string(),
+            amount: vec![Coin {
+                denom: stream.out_denom,
+                amount: claim_amount,
+            }],
+        }))
+        .add_attribute("action", "claim_purchased")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("operator_target", operator_target)
+        .add_attribute("claim_amount", claim_amount)
+        .add_attribute("claimed", position.claimed))
+}
+
+pub fn execute_finalize_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    new_treasury: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    if stream.treasury != info.sender {
+        return Err(ContractError::Unauthorized {});
     }
+    check_finalize_stream(env.block.time, &mut stream)?;
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
 
     if stream.status == Status::Active {
-        stream.status = Status::Finalized
+        stream.status = Status::Finalized;
+        update_status_stats(deps.storage, &Status::Active, &stream.status)?;
     }
     // If threshold is set and not reached, finalize will fail
     // Creator should execute cancel_stream_with_threshold to cancel the stream
@@ -865,12 +1686,19 @@ pub fn execute_finalize_stream(
     let config = CONFIG.load(deps.storage)?;
     let treasury = maybe_addr(deps.api, new_treasury)?.unwrap_or_else(|| stream.treasury.clone());
 
-    //Stream's swap fee collected at fixed rate from accumulated spent_in of positions(ie stream.spent_in)
-    let swap_fee = Decimal::from_ratio(stream.spent_in, Uint128::one())
-        .checked_mul(stream.stream_exit_fee_percent)?
-        * Uint128::one();
+    let (creator_revenue, swap_fee, keeper_reserve) =
+        compute_finalize_stream(&stream, config.keeper_reward_percent)?;
+    stream.keeper_reserve = keeper_reserve;
+    STREAMS.save(deps.storage, stream_id, &stream)?;
 
-    let creator_revenue = stream.spent_in.checked_sub(swap_fee)?;
+    let mut stats = STATS.load(deps.storage)?;
+    stats.add_in_volume(Coin::new(stream.spent_in.u128(), &stream.in_denom));
+    stats.add_fee(Coin::new(
+        stream.stream_creation_fee.u128(),
+        &stream.stream_creation_denom,
+    ));
+    stats.add_fee(Coin::new(swap_fee.u128(), &stream.in_denom));
+    STATS.save(deps.storage, &stats)?;
 
     //Creator's revenue claimed at finalize
     let revenue_msg = CosmosMsg::Bank(BankMsg::Send {
@@ -880,28 +1708,24 @@ pub fn execute_finalize_stream(
             amount: creator_revenue,
         }],
     });
-    //Exact fee for stream creation charged at creation but claimed at finalize
-    let creation_fee_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: config.fee_collector.to_string(),
-        amount: vec![Coin {
-            denom: stream.stream_creation_denom,
-            amount: stream.stream_creation_fee,
-        }],
-    });
-
-    let swap_fee_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: config.fee_collector.to_string(),
-        amount: vec![Coin {
-            denom: stream.in_denom,
-            amount: swap_fee,
-        }],
-    });
-
     let mut messages = if stream.spent_in != Uint128::zero() {
-        vec![revenue_msg, creation_fee_msg, swap_fee_msg]
+        vec![revenue_msg]
     } else {
-        vec![creation_fee_msg]
+        vec![]
     };
+    //Exact fee for stream creation charged at creation but accrued to collectors at finalize
+    accrue_fees(
+        deps.storage,
+        &config.fee_collectors,
+        &stream.stream_creation_denom,
+        stream.stream_creation_fee,
+    )?;
+    accrue_fees(
+        deps.storage,
+        &config.fee_collectors,
+        &stream.in_denom,
+        swap_fee,
+    )?;
 
     // In case the stream is ended without any shares in it. We need to refund the remaining out tokens although that is unlikely to happen
     if stream.out_remaining > Uint128::zero() {
@@ -909,111 +1733,454 @@ pub fn execute_finalize_stream(
         let remaining_msg = CosmosMsg::Bank(BankMsg::Send {
             to_address: treasury.to_string(),
             amount: vec![Coin {
-                denom: stream.out_denom,
+                denom: stream.out_denom.clone(),
                 amount: remaining_out,
             }],
         });
         messages.push(remaining_msg);
     }
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "finalize_stream"),
+    let hooks = hook_messages(
+        deps.storage,
+        stream_id,
+        StreamHookEvent::Finalize { stream_id },
+    )?;
+    let event: Event = FinalizeStreamEvent {
+        stream_id,
+        stream: &stream,
+        treasury: &treasury,
+        creator_revenue,
+        swap_fee,
+    }
+    .into();
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(hooks)
+        .add_event(event)
+        .add_attributes(vec![
+            attr("action", "finalize_stream"),
+            attr("stream_id", stream_id.to_string()),
+            attr("treasury", treasury.as_str()),
+            attr(
+                "fee_collectors",
+                fee_collectors_attr(&config.fee_collectors),
+            ),
+            attr("creators_revenue", creator_revenue),
+            attr("refunded_out_remaining", stream.out_remaining.to_string()),
+            attr(
+                "total_sold",
+                stream
+                    .out_supply
+                    .checked_sub(stream.out_remaining)?
+                    .to_string(),
+            ),
+            attr("swap_fee", swap_fee),
+            attr("creation_fee", stream.stream_creation_fee.to_string()),
+            attr("creation_fee_denom", stream.stream_creation_denom),
+        ]))
+}
+
+// checks if the stream can be finalized and syncs it to its end
+pub fn check_finalize_stream(now: Timestamp, stream: &mut Stream) -> Result<(), ContractError> {
+    // check if the stream is already finalized
+    if stream.status == Status::Finalized {
+        return Err(ContractError::StreamAlreadyFinalized {});
+    }
+    // check if killswitch is active
+    if stream.is_killswitch_active() {
+        return Err(ContractError::StreamKillswitchActive {});
+    }
+    if now <= stream.end_time {
+        return Err(ContractError::StreamNotEnded {});
+    }
+    if stream.last_updated < stream.end_time {
+        update_stream(now, stream)?;
+    }
+    Ok(())
+}
+
+// calculates the finalize payouts of an ended stream.
+// returns creator revenue, swap fee and the keeper reserve held back from the swap fee
+pub fn compute_finalize_stream(
+    stream: &Stream,
+    keeper_reward_percent: Decimal,
+) -> Result<(Uint128, Uint128, Uint128), ContractError> {
+    //Stream's swap fee collected at fixed rate from accumulated spent_in of positions(ie stream.spent_in)
+    let (in_fee_percent, _) = stream.fee_side.split(stream.stream_exit_fee_percent)?;
+    let max_swap_fee = Decimal::from_ratio(stream.spent_in, Uint128::one())
+        .checked_mul(in_fee_percent)?
+        * Uint128::one();
+    let creator_revenue = stream.spent_in.checked_sub(max_swap_fee)?;
+
+    // With fee tiers the swap fee depends on each position's spend. Fees of positions exited
+    // so far are collected now, the rest stays in the contract until those positions exit.
+    if !stream.stream_exit_fee_tiers.is_empty() {
+        return Ok((creator_revenue, stream.exit_fees_accrued, Uint128::zero()));
+    }
+    // Without fee tiers the whole swap fee is collected now, except the keeper share of the
+    // positions not exited yet, which is held back to reward keepers exiting them.
+    let swap_fee = max_swap_fee.checked_sub(stream.keeper_rewards)?;
+    if stream.shares.is_zero() {
+        return Ok((creator_revenue, swap_fee, Uint128::zero()));
+    }
+    let keeper_reserve = (Decimal::from_ratio(max_swap_fee, Uint128::one())
+        .checked_mul(keeper_reward_percent)?
+        * Uint128::one())
+    .saturating_sub(stream.keeper_rewards)
+    .min(swap_fee);
+    Ok((
+        creator_revenue,
+        swap_fee.checked_sub(keeper_reserve)?,
+        keeper_reserve,
+    ))
+}
+
+pub fn execute_exit_stream(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    stream_id: u64,
+    operator_target: Option<String>,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    let config = CONFIG.load(deps.storage)?;
+    check_exit_stream(deps.storage, env.block.time, stream_id, &mut stream)?;
+
+    let operator_target =
+        maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
+    let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
+    check_access(
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        OperatorPermission::Exit,
+    )?;
+    let recipient = check_recipient(
+        deps.api,
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        recipient,
+    )?;
+
+    let payout = exit_position(
+        deps.storage,
+        &config,
+        stream_id,
+        &mut stream,
+        &mut position,
+        &recipient,
+        Decimal::zero(),
+    )?;
+    STREAMS.save(deps.storage, stream_id, &stream)?;
+    let event: Event = ExitStreamEvent {
+        stream_id,
+        stream: &stream,
+        position: &position,
+        recipient: &recipient,
+        purchased: payout.purchased,
+        refund: payout.refund,
+        swap_fee: payout.swap_fee,
+        swap_fee_out: payout.swap_fee_out,
+        keeper: None,
+    }
+    .into();
+
+    let attributes = vec![
+        attr("action", "exit_stream"),
         attr("stream_id", stream_id.to_string()),
-        attr("treasury", treasury.as_str()),
-        attr("fee_collector", config.fee_collector.to_string()),
-        attr("creators_revenue", creator_revenue),
-        attr("refunded_out_remaining", stream.out_remaining.to_string()),
+        attr("spent", position.spent.checked_sub(payout.swap_fee)?),
+        attr("purchased", payout.purchased),
+        attr("swap_fee_paid", payout.swap_fee),
+        attr("swap_fee_out", payout.swap_fee_out),
         attr(
-            "total_sold",
-            stream
-                .out_supply
-                .checked_sub(stream.out_remaining)?
-                .to_string(),
+            "swap_fee_rebate",
+            payout.refund.checked_sub(position.in_balance)?,
         ),
-        attr("swap_fee", swap_fee),
-        attr("creation_fee", config.stream_creation_fee.to_string()),
-    ]))
+        attr("recipient", recipient.clone()),
+    ];
+    Ok(Response::new()
+        .add_messages(payout.messages)
+        .add_attributes(attributes)
+        .add_event(event))
 }
 
-pub fn execute_exit_stream(
+pub fn execute_distribute_exits(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     stream_id: u64,
-    operator_target: Option<String>,
+    limit: Option<u32>,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
-    let _config = CONFIG.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    check_exit_stream(deps.storage, env.block.time, stream_id, &mut stream)?;
+
+    // exited positions are removed, remaining ones are always at the start
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let positions = POSITIONS
+        .prefix(stream_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    if positions.is_empty() {
+        return Err(ContractError::NoExitsToDistribute {});
+    }
+
+    let mut messages = vec![];
+    let mut events: Vec<Event> = vec![];
+    let mut keeper_reward_in = Uint128::zero();
+    let mut keeper_reward_out = Uint128::zero();
+    for (owner, mut position) in positions.iter().cloned() {
+        let payout = exit_position(
+            deps.storage,
+            &config,
+            stream_id,
+            &mut stream,
+            &mut position,
+            &owner,
+            config.keeper_reward_percent,
+        )?;
+        events.push(
+            ExitStreamEvent {
+                stream_id,
+                stream: &stream,
+                position: &position,
+                recipient: &owner,
+                purchased: payout.purchased,
+                refund: payout.refund,
+                swap_fee: payout.swap_fee,
+                swap_fee_out: payout.swap_fee_out,
+                keeper: Some(&info.sender),
+            }
+            .into(),
+        );
+        messages.extend(payout.messages);
+        keeper_reward_in = keeper_reward_in.checked_add(payout.keeper_reward_in)?;
+        keeper_reward_out = keeper_reward_out.checked_add(payout.keeper_reward_out)?;
+    }
+    STREAMS.save(deps.storage, stream_id, &stream)?;
+
+    let mut keeper_reward = vec![];
+    if !keeper_reward_in.is_zero() {
+        keeper_reward.push(Coin::new(keeper_reward_in.u128(), &stream.in_denom));
+    }
+    if !keeper_reward_out.is_zero() {
+        keeper_reward.push(Coin::new(keeper_reward_out.u128(), &stream.out_denom));
+    }
+    if !keeper_reward.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: keeper_reward,
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", "distribute_exits"),
+            attr("stream_id", stream_id.to_string()),
+            attr("exited_positions", positions.len().to_string()),
+            attr("keeper", info.sender),
+            attr("keeper_reward_in", keeper_reward_in),
+            attr("keeper_reward_out", keeper_reward_out),
+        ])
+        .add_events(events))
+}
+
+struct ExitPayout {
+    messages: Vec<CosmosMsg>,
+    purchased: Uint128,
+    refund: Uint128,
+    swap_fee: Uint128,
+    swap_fee_out: Uint128,
+    keeper_reward_in: Uint128,
+    keeper_reward_out: Uint128,
+}
+
+// exits the position to the recipient and accrues its fees to the fee collectors.
+// keeper_reward_percent of the swap fees is left out as keeper reward, from the keeper reserve
+// if the fee is already collected
+fn exit_position(
+    storage: &mut dyn Storage,
+    config: &Config,
+    stream_id: u64,
+    stream: &mut Stream,
+    position: &mut Position,
+    recipient: &Addr,
+    keeper_reward_percent: Decimal,
+) -> Result<ExitPayout, ContractError> {
+    // update position before exit
+    let (purchased, refund, swap_fee, swap_fee_out) = compute_exit_stream(stream, position)?;
+
+    let mut messages = vec![];
+    if !purchased.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: stream.out_denom.to_string(),
+                amount: purchased,
+            }],
+        }));
+    }
+    if !refund.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: stream.in_denom.clone(),
+                amount: refund,
+            }],
+        }));
+    }
+
+    let keeper_reward = |fee: Uint128| -> StdResult<Uint128> {
+        Ok(
+            Decimal::from_ratio(fee, Uint128::one()).checked_mul(keeper_reward_percent)?
+                * Uint128::one(),
+        )
+    };
+    let keeper_reward_in;
+    // keeper share held back at finalize that is not paid to a keeper goes to the fee collectors
+    let mut released_reserve = Uint128::zero();
+    if stream.stream_exit_fee_tiers.is_empty() {
+        if stream.status != Status::Finalized {
+            // Swap fee is collected with finalize, keeper rewards paid before are deducted from it
+            keeper_reward_in = keeper_reward(swap_fee)?;
+            stream.keeper_rewards = stream.keeper_rewards.checked_add(keeper_reward_in)?;
+        } else {
+            // Swap fee is already collected, the keeper share of it was held back at finalize
+            let share = (Decimal::from_ratio(swap_fee, Uint128::one())
+                .checked_mul(config.keeper_reward_percent)?
+                * Uint128::one())
+            .min(stream.keeper_reserve);
+            keeper_reward_in = keeper_reward(swap_fee)?.min(share);
+            released_reserve = share.checked_sub(keeper_reward_in)?;
+            stream.keeper_reserve = stream.keeper_reserve.checked_sub(share)?;
+        }
+    } else {
+        // Tiered swap fees are known per position only, they are collected with finalize
+        // or accrued directly if the stream is already finalized
+        keeper_reward_in = keeper_reward(swap_fee)?;
+        let fee = swap_fee.checked_sub(keeper_reward_in)?;
+        if stream.status == Status::Finalized {
+            accrue_fees(storage, &config.fee_collectors, &stream.in_denom, fee)?;
+            let mut stats = STATS.load(storage)?;
+            stats.add_fee(Coin::new(fee.u128(), &stream.in_denom));
+            STATS.save(storage, &stats)?;
+        } else {
+            stream.exit_fees_accrued = stream.exit_fees_accrued.checked_add(fee)?;
+        }
+    }
+
+    // Fees in out tokens are taken from the purchased amount, they are not part of out_remaining
+    let keeper_reward_out = keeper_reward(swap_fee_out)?;
+    let fee_out = swap_fee_out.checked_sub(keeper_reward_out)?;
+    if !fee_out.is_zero() {
+        accrue_fees(storage, &config.fee_collectors, &stream.out_denom, fee_out)?;
+        let mut stats = STATS.load(storage)?;
+        stats.add_fee(Coin::new(fee_out.u128(), &stream.out_denom));
+        STATS.save(storage, &stats)?;
+    }
+
+    stream.shares = stream.shares.checked_sub(position.shares)?;
+    // reserve left by rounding or self exits is released once all positions are exited
+    if stream.status == Status::Finalized && stream.shares.is_zero() {
+        released_reserve = released_reserve.checked_add(stream.keeper_reserve)?;
+        stream.keeper_reserve = Uint128::zero();
+    }
+    if !released_reserve.is_zero() {
+        accrue_fees(
+            storage,
+            &config.fee_collectors,
+            &stream.in_denom,
+            released_reserve,
+        )?;
+        let mut stats = STATS.load(storage)?;
+        stats.add_fee(Coin::new(released_reserve.u128(), &stream.in_denom));
+        STATS.save(storage, &stats)?;
+    }
+    POSITIONS.remove(storage, (stream_id, &position.owner));
+    clear_position_operators(storage, stream_id, &position.owner)?;
+    burn_position_token(storage, position)?;
+    remove_position_stats(storage)?;
+
+    Ok(ExitPayout {
+        messages,
+        purchased,
+        refund,
+        swap_fee,
+        swap_fee_out,
+        keeper_reward_in,
+        keeper_reward_out,
+    })
+}
+
+// checks if positions can exit the stream and syncs the stream to its end
+pub fn check_exit_stream(
+    storage: &dyn Storage,
+    now: Timestamp,
+    stream_id: u64,
+    stream: &mut Stream,
+) -> Result<(), ContractError> {
     // check if stream is paused
     if stream.is_killswitch_active() {
         return Err(ContractError::StreamKillswitchActive {});
     }
-    if env.block.time <= stream.end_time {
+    if now <= stream.end_time {
         return Err(ContractError::StreamNotEnded {});
     }
     if stream.last_updated < stream.end_time {
-        update_stream(env.block.time, &mut stream)?;
+        update_stream(now, stream)?;
     }
     let threshold_state = ThresholdState::new();
 
-    threshold_state.error_if_not_reached(stream_id, deps.storage, &stream)?;
-    let operator_target =
-        maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
-    let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    check_access(&info, &position.owner, &position.operator)?;
+    threshold_state.error_if_not_reached(stream_id, storage, stream)?;
+    Ok(())
+}
 
-    // update position before exit
+// syncs the position to the ended stream and calculates its exit payouts.
+// returns purchased out amount after out side fee and claims, refunded in amount, in side swap fee and out side swap fee
+pub fn compute_exit_stream(
+    stream: &Stream,
+    position: &mut Position,
+) -> Result<(Uint128, Uint128, Uint128, Uint128), ContractError> {
     update_position(
         stream.dist_index,
         stream.shares,
         stream.last_updated,
         stream.in_supply,
-        &mut position,
+        position,
     )?;
-    // Swap fee = fixed_rate*position.spent_in this calculation is only for execution reply attributes
-    let swap_fee = Decimal::from_ratio(position.spent, Uint128::one())
-        .checked_mul(stream.stream_exit_fee_percent)?
+    // Swap fee = fixed_rate*position.spent_in
+    let (base_in_percent, _) = stream.fee_side.split(stream.stream_exit_fee_percent)?;
+    let (in_percent, out_percent) = stream
+        .fee_side
+        .split(stream.exit_fee_percent_for(position.spent))?;
+    let max_swap_fee = Decimal::from_ratio(position.spent, Uint128::one())
+        .checked_mul(base_in_percent)?
+        * Uint128::one();
+    let swap_fee = Decimal::from_ratio(position.spent, Uint128::one()).checked_mul(in_percent)?
+        * Uint128::one();
+    // finalize withholds the fee at the base rate, lower tier fees are rebated to the position
+    let rebate = max_swap_fee.checked_sub(swap_fee)?;
+    let swap_fee_out = Decimal::from_ratio(position.purchased, Uint128::one())
+        .checked_mul(out_percent)?
         * Uint128::one();
 
-    let send_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: operator_target.to_string(),
-        amount: vec![Coin {
-            denom: stream.out_denom.to_string(),
-            amount: position.purchased,
-        }],
-    });
-
-    stream.shares = stream.shares.checked_sub(position.shares)?;
-
-    STREAMS.save(deps.storage, stream_id, &stream)?;
-    POSITIONS.remove(deps.storage, (stream_id, &position.owner));
-
-    let attributes = vec![
-        attr("action", "exit_stream"),
-        attr("stream_id", stream_id.to_string()),
-        attr("spent", position.spent.checked_sub(swap_fee)?),
-        attr("purchased", position.purchased),
-        attr("swap_fee_paid", swap_fee),
-    ];
-    if !position.in_balance.is_zero() {
-        let unspent = position.in_balance;
-        let unspent_msg = CosmosMsg::Bank(BankMsg::Send {
-            to_address: operator_target.to_string(),
-            amount: vec![Coin {
-                denom: stream.in_denom,
-                amount: unspent,
-            }],
-        });
-
-        Ok(Response::new()
-            .add_message(send_msg)
-            .add_message(unspent_msg)
-            .add_attributes(attributes))
-    } else {
-        Ok(Response::new()
-            .add_message(send_msg)
-            .add_attributes(attributes))
-    }
+    Ok((
+        position
+            .purchased
+            .checked_sub(swap_fee_out)?
+            .checked_sub(position.claimed)?,
+        position.in_balance.checked_add(rebate)?,
+        swap_fee,
+        swap_fee_out,
+    ))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -1025,9 +2192,14 @@ pub fn execute_update_config(
     min_duration_until_start_time: Option<Uint64>,
     stream_creation_denom: Option<String>,
     stream_creation_fee: Option<Uint128>,
-    fee_collector: Option<String>,
+    stream_creation_fee_options: Option<Vec<Coin>>,
+    fee_collectors: Option<Vec<FeeCollectorMsg>>,
     accepted_in_denom: Option<String>,
     exit_fee_percent: Option<Decimal>,
+    exit_fee_tiers: Option<Vec<ExitFeeTier>>,
+    fee_side: Option<FeeSide>,
+    keeper_reward_percent: Option<Decimal>,
+    position_nfts: Option<bool>,
 ) -> Result<Response, ContractError> {
     let mut cfg = CONFIG.load(deps.storage)?;
 
@@ -1052,12 +2224,32 @@ pub fn execute_update_config(
         min_duration_until_start_time.unwrap_or(cfg.min_seconds_until_start_time);
     cfg.stream_creation_denom = stream_creation_denom.unwrap_or(cfg.stream_creation_denom);
     cfg.stream_creation_fee = stream_creation_fee.unwrap_or(cfg.stream_creation_fee);
+    cfg.stream_creation_fee_options =
+        stream_creation_fee_options.unwrap_or(cfg.stream_creation_fee_options);
+    check_creation_fee_options(&cfg.stream_creation_denom, &cfg.stream_creation_fee_options)?;
     cfg.accepted_in_denom = accepted_in_denom.unwrap_or(cfg.accepted_in_denom);
-    let collector = maybe_addr(deps.api, fee_collector)?.unwrap_or(cfg.fee_collector);
-    cfg.fee_collector = collector;
+    if let Some(fee_collectors) = fee_collectors {
+        cfg.fee_collectors = validate_fee_collectors(deps.api, fee_collectors)?;
+    }
     cfg.exit_fee_percent = exit_fee_percent.unwrap_or(cfg.exit_fee_percent);
+    cfg.exit_fee_tiers = exit_fee_tiers.unwrap_or(cfg.exit_fee_tiers);
+    check_exit_fee_tiers(cfg.exit_fee_percent, &cfg.exit_fee_tiers)?;
+    if let Some(fee_side) = fee_side {
+        check_fee_side(&fee_side)?;
+        cfg.fee_side = fee_side;
+    }
+    // keeper reward is a share of the exit fee
+    if let Some(keeper_reward_percent) = keeper_reward_percent {
+        if keeper_reward_percent > Decimal::one() {
+            return Err(ContractError::InvalidKeeperRewardPercent {});
+        }
+        cfg.keeper_reward_percent = keeper_reward_percent;
+    }
+    // existing positions are not minted, only new ones
+    cfg.position_nfts = position_nfts.unwrap_or(cfg.position_nfts);
 
     CONFIG.save(deps.storage, &cfg)?;
+    let event: Event = UpdateConfigEvent { config: &cfg }.into();
 
     let attributes = vec![
         attr("action", "update_config"),
@@ -1068,27 +2260,143 @@ pub fn execute_update_config(
         ),
         attr("stream_creation_denom", cfg.stream_creation_denom),
         attr("stream_creation_fee", cfg.stream_creation_fee),
-        attr("fee_collector", cfg.fee_collector),
+        attr("fee_collectors", fee_collectors_attr(&cfg.fee_collectors)),
     ];
 
-    Ok(Response::default().add_attributes(attributes))
+    Ok(Response::default()
+        .add_event(event)
+        .add_attributes(attributes))
 }
 
-fn check_access(
-    info: &MessageInfo,
-    position_owner: &Addr,
-    position_operator: &Option<Addr>,
+// accrues the fee to collectors by weight
+fn accrue_fees(
+    storage: &mut dyn Storage,
+    collectors: &[FeeCollector],
+    denom: &str,
+    fee: Uint128,
 ) -> Result<(), ContractError> {
-    if position_owner.as_ref() != info.sender
-        && position_operator
-            .as_ref()
-            .map_or(true, |o| o != &info.sender)
-    {
-        return Err(ContractError::Unauthorized {});
+    for (collector, amount) in split_fee(collectors, fee)? {
+        accrue_fee(storage, &collector, denom, amount)?;
     }
     Ok(())
 }
 
+pub fn execute_claim_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    collector: Option<String>,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let collector = maybe_addr(deps.api, collector)?.unwrap_or_else(|| info.sender.clone());
+    if info.sender != collector && info.sender != config.protocol_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    // only the collector can send its fees elsewhere, the protocol admin claims to the collector
+    let recipient = maybe_addr(deps.api, recipient)?.unwrap_or_else(|| collector.clone());
+    if recipient != collector && info.sender != collector {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let fees = unclaimed_fees(deps.storage, &collector)?;
+    if fees.is_empty() {
+        return Err(ContractError::NoFeesToClaim {});
+    }
+    for fee in fees.iter() {
+        UNCLAIMED_FEES.remove(deps.storage, (&collector, &fee.denom));
+    }
+
+    let event: Event = ClaimFeesEvent {
+        collector: &collector,
+        recipient: &recipient,
+        fees: &fees,
+    }
+    .into();
+    let fees_attr = fees
+        .iter()
+        .map(|fee| fee.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    Ok(Response::new()
+        .add_event(event)
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: fees,
+        })
+        .add_attributes(vec![
+            attr("action", "claim_fees"),
+            attr("collector", collector),
+            attr("recipient", recipient),
+            attr("fees", fees_attr),
+        ]))
+}
+
+// returns unclaimed fees of a collector sorted by denom
+fn unclaimed_fees(storage: &dyn Storage, collector: &Addr) -> StdResult<Vec<Coin>> {
+    UNCLAIMED_FEES
+        .prefix(collector)
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect()
+}
+
+fn fee_collectors_attr(collectors: &[FeeCollector]) -> String {
+    collectors
+        .iter()
+        .map(|c| format!("{}:{}", c.address, c.weight))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// returns the address receiving the position proceeds, the position owner by default.
+// only the owner or an operator granted the redirect permission can set another recipient
+pub(crate) fn check_recipient(
+    api: &dyn Api,
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    info: &MessageInfo,
+    stream_id: u64,
+    position: &Position,
+    recipient: Option<String>,
+) -> Result<Addr, ContractError> {
+    let recipient = match maybe_addr(api, recipient)? {
+        Some(recipient) if recipient != position.owner => recipient,
+        _ => return Ok(position.owner.clone()),
+    };
+    if info.sender != position.owner {
+        let grant =
+            POSITION_OPERATORS.may_load(storage, (stream_id, &position.owner, &info.sender))?;
+        if !grant.is_some_and(|grant| grant.allows(&OperatorPermission::Redirect, block)) {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+    Ok(recipient)
+}
+
+// checks if the sender is the position owner, an operator approved by the owner
+// or an operator granted the permission on the position
+pub(crate) fn check_access(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    info: &MessageInfo,
+    stream_id: u64,
+    position: &Position,
+    permission: OperatorPermission,
+) -> Result<(), ContractError> {
+    if position.owner == info.sender {
+        return Ok(());
+    }
+    if let Some(expires) = ACCOUNT_OPERATORS.may_load(storage, (&position.owner, &info.sender))? {
+        if !expires.is_expired(block) {
+            return Ok(());
+        }
+    }
+    match POSITION_OPERATORS.may_load(storage, (stream_id, &position.owner, &info.sender))? {
+        Some(grant) if grant.allows(&permission, block) => Ok(()),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
     match msg {
@@ -1098,6 +2406,14 @@ pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractE
     }
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        HOOK_REPLY_ID => hooks::reply_hook_failed(msg),
+        id => Err(ContractError::UnknownReplyId(id)),
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     let contract_info = get_contract_version(deps.storage)?;
@@ -1114,9 +2430,116 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, C
         set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
         // Code to facilitate state change goes here
     }
+    // Thresholds are stored by kind
+    migrate_legacy_thresholds(deps.storage)?;
+    // Single fee collector is replaced by weighted fee collectors
+    if let Ok(legacy) = LEGACY_CONFIG.load(deps.storage) {
+        let config = Config {
+            min_stream_seconds: legacy.min_stream_seconds,
+            min_seconds_until_start_time: legacy.min_seconds_until_start_time,
+            accepted_in_denom: legacy.accepted_in_denom,
+            stream_creation_denom: legacy.stream_creation_denom,
+            stream_creation_fee: legacy.stream_creation_fee,
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: legacy.exit_fee_percent,
+            exit_fee_tiers: legacy.exit_fee_tiers,
+            fee_side: FeeSide::In,
+            fee_collectors: vec![FeeCollector {
+                address: legacy.fee_collector,
+                weight: Decimal::one(),
+            }],
+            protocol_admin: legacy.protocol_admin,
+            keeper_reward_percent: Decimal::zero(),
+            position_nfts: false,
+        };
+        CONFIG.save(deps.storage, &config)?;
+    }
+    // Protocol stats are introduced after streams exist, rebuild them from the current state
+    if STATS.may_load(deps.storage)?.is_none() {
+        rebuild_protocol_stats(deps.storage)?;
+    }
+    // Legacy position operators become full-permission operator grants
+    let legacy_operators = POSITIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(_, position)| position.operator.is_some())
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    for ((stream_id, owner), mut position) in legacy_operators {
+        if let Some(operator) = position.operator.take() {
+            POSITION_OPERATORS.save(
+                deps.storage,
+                (stream_id, &owner, &operator),
+                &OperatorGrant::full(),
+            )?;
+        }
+        POSITIONS.save(deps.storage, (stream_id, &owner), &position)?;
+    }
+    // Pre-start commitments are tracked per stream, the whole in supply of a waiting stream is committed
+    let waiting = STREAMS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(_, stream)| stream.status == Status::Waiting)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    for (stream_id, mut stream) in waiting {
+        stream.pre_committed_in = stream.in_supply;
+        STREAMS.save(deps.storage, stream_id, &stream)?;
+    }
+    // Subscribers are counted per stream, legacy streams count the positions holding shares.
+    // Streams already counting subscribers are left as is, exits after the end are not recounted.
+    let mut subscribers: BTreeMap<u64, u64> = BTreeMap::new();
+    for item in POSITIONS.range(deps.storage, None, None, Order::Ascending) {
+        let ((stream_id, _), position) = item?;
+        if !position.shares.is_zero() {
+            *subscribers.entry(stream_id).or_default() += 1;
+        }
+    }
+    for (stream_id, count) in subscribers {
+        let mut stream = STREAMS.load(deps.storage, stream_id)?;
+        if stream.subscribers == 0 {
+            stream.subscribers = count;
+            STREAMS.save(deps.storage, stream_id, &stream)?;
+        }
+    }
     Ok(Response::default())
 }
 
+// Rebuilds protocol stats from existing streams and positions.
+// Exited positions are removed from storage, so their owners are not counted as subscribers.
+pub fn rebuild_protocol_stats(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let mut stats = ProtocolStats::default();
+    SUBSCRIBERS.clear(storage);
+    let streams = STREAMS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (_, stream) in streams {
+        stats.add_stream(&stream.status);
+        if stream.status == Status::Finalized {
+            // streams finalized before stats were introduced hold no keeper reserve
+            let (_, swap_fee, _) = compute_finalize_stream(&stream, Decimal::zero())?;
+            stats.add_in_volume(Coin::new(stream.spent_in.u128(), &stream.in_denom));
+            stats.add_fee(Coin::new(
+                stream.stream_creation_fee.u128(),
+                &stream.stream_creation_denom,
+            ));
+            stats.add_fee(Coin::new(swap_fee.u128(), &stream.in_denom));
+        }
+    }
+    STATS.save(storage, &stats)?;
+
+    let owners = POSITIONS
+        .keys(storage, None, None, Order::Ascending)
+        .map(|key| key.map(|(_, owner)| owner))
+        .collect::<StdResult<Vec<_>>>()?;
+    for owner in owners {
+        add_position_stats(storage, &owner)?;
+    }
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -1126,22 +2549,129 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_json_binary(&query_position(deps, env, stream_id, owner)?)
         }
         QueryMsg::ListStreams { start_after, limit } => {
-            to_json_binary(&list_streams(deps, start_after, limit)?)
+            to_json_binary(&list_streams(deps, env, start_after, limit)?)
         }
         QueryMsg::ListPositions {
             stream_id,
             start_after,
             limit,
         } => to_json_binary(&list_positions(deps, stream_id, start_after, limit)?),
+        QueryMsg::PositionOperators { stream_id, owner } => {
+            to_json_binary(&query_position_operators(deps, stream_id, owner)?)
+        }
+        QueryMsg::AccountOperators { owner } => {
+            to_json_binary(&query_account_operators(deps, owner)?)
+        }
         QueryMsg::AveragePrice { stream_id } => {
             to_json_binary(&query_average_price(deps, env, stream_id)?)
         }
         QueryMsg::LastStreamedPrice { stream_id } => {
             to_json_binary(&query_last_streamed_price(deps, env, stream_id)?)
         }
+        QueryMsg::PreCommitted { stream_id } => {
+            to_json_binary(&query_pre_committed(deps, stream_id)?)
+        }
         QueryMsg::Threshold { stream_id } => {
             to_json_binary(&query_threshold_state(deps, env, stream_id)?)
         }
+        QueryMsg::EstimateSubscription {
+            stream_id,
+            amount,
+            at_time,
+        } => to_json_binary(&query_estimate_subscription(
+            deps, env, stream_id, amount, at_time,
+        )?),
+        QueryMsg::ExitPreview { stream_id, owner } => {
+            to_json_binary(&query_exit_preview(deps, env, stream_id, owner)?)
+        }
+        QueryMsg::FinalizePreview { stream_id } => {
+            to_json_binary(&query_finalize_preview(deps, env, stream_id)?)
+        }
+        QueryMsg::Stats {} => to_json_binary(&query_stats(deps)?),
+        QueryMsg::StreamsByIds { ids } => to_json_binary(&query_streams_by_ids(deps, env, ids)?),
+        QueryMsg::PositionsBatch { pairs } => to_json_binary(&query_positions_batch(deps, pairs)?),
+        QueryMsg::FeeDiscount { address } => to_json_binary(&query_fee_discount(deps, address)?),
+        QueryMsg::HolderFeeDiscounts {} => to_json_binary(&query_holder_fee_discounts(deps)?),
+        QueryMsg::UnclaimedFees { collector } => {
+            to_json_binary(&query_unclaimed_fees(deps, collector)?)
+        }
+        QueryMsg::Hooks { stream_id } => to_json_binary(&hooks::query_hooks(deps, stream_id)?),
+        QueryMsg::OwnerOf {
+            token_id,
+            include_expired,
+        } => to_json_binary(&nft::query_owner_of(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Approval {
+            token_id,
+            spender,
+            include_expired,
+        } => to_json_binary(&nft::query_approval(
+            deps,
+            env,
+            token_id,
+            spender,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Approvals {
+            token_id,
+            include_expired,
+        } => to_json_binary(&nft::query_approvals(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Operator {
+            owner,
+            operator,
+            include_expired,
+        } => to_json_binary(&nft::query_operator(
+            deps,
+            env,
+            owner,
+            operator,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::AllOperators {
+            owner,
+            include_expired,
+            start_after,
+            limit,
+        } => to_json_binary(&nft::query_all_operators(
+            deps,
+            env,
+            owner,
+            include_expired.unwrap_or(false),
+            start_after,
+            limit,
+        )?),
+        QueryMsg::NumTokens {} => to_json_binary(&nft::query_num_tokens(deps)?),
+        QueryMsg::ContractInfo {} => to_json_binary(&nft::query_contract_info()),
+        QueryMsg::NftInfo { token_id } => {
+            to_json_binary(&nft::query_nft_info(deps, env, token_id)?)
+        }
+        QueryMsg::AllNftInfo {
+            token_id,
+            include_expired,
+        } => to_json_binary(&nft::query_all_nft_info(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Tokens {
+            owner,
+            start_after,
+            limit,
+        } => to_json_binary(&nft::query_tokens(deps, owner, start_after, limit)?),
+        QueryMsg::AllTokens { start_after, limit } => {
+            to_json_binary(&nft::query_all_tokens(deps, start_after, limit)?)
+        }
+        QueryMsg::Minter {} => to_json_binary(&nft::query_minter(env)),
     }
 }
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
@@ -1151,16 +2681,27 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         min_seconds_until_start_time: cfg.min_seconds_until_start_time,
         stream_creation_denom: cfg.stream_creation_denom,
         stream_creation_fee: cfg.stream_creation_fee,
+        stream_creation_fee_options: cfg.stream_creation_fee_options,
         exit_fee_percent: cfg.exit_fee_percent,
-        fee_collector: cfg.fee_collector.to_string(),
+        exit_fee_tiers: cfg.exit_fee_tiers,
+        fee_side: cfg.fee_side,
+        keeper_reward_percent: cfg.keeper_reward_percent,
+        position_nfts: cfg.position_nfts,
+        fee_collectors: cfg.fee_collectors,
         protocol_admin: cfg.protocol_admin.to_string(),
         accepted_in_denom: cfg.accepted_in_denom,
     })
 }
 
-pub fn query_stream(deps: Deps, _env: Env, stream_id: u64) -> StdResult<StreamResponse> {
+pub fn query_stream(deps: Deps, env: Env, stream_id: u64) -> StdResult<StreamResponse> {
     let stream = STREAMS.load(deps.storage, stream_id)?;
-    let stream = StreamResponse {
+    Ok(stream_response(stream_id, stream, env.block.time))
+}
+
+// the status is the one at `now`, stored status of waiting streams lags until a transaction
+fn stream_response(stream_id: u64, stream: Stream, now: Timestamp) -> StreamResponse {
+    let status = stream.status_at(now);
+    StreamResponse {
         id: stream_id,
         treasury: stream.treasury.to_string(),
         in_denom: stream.in_denom,
@@ -1174,22 +2715,29 @@ pub fn query_stream(deps: Deps, _env: Env, stream_id: u64) -> StdResult<StreamRe
         in_supply: stream.in_supply,
         shares: stream.shares,
         last_updated: stream.last_updated,
-        status: stream.status,
+        status,
         pause_date: stream.pause_date,
         url: stream.url,
         current_streamed_price: stream.current_streamed_price,
         exit_fee_percent: stream.stream_exit_fee_percent,
+        exit_fee_tiers: stream.stream_exit_fee_tiers,
+        stream_creation_denom: stream.stream_creation_denom,
         stream_creation_fee: stream.stream_creation_fee,
-    };
-    Ok(stream)
+        fee_discount: stream.fee_discount,
+        fee_side: stream.fee_side,
+        subscribers: stream.subscribers,
+    }
 }
 
 // settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+pub(crate) const MAX_LIMIT: u32 = 30;
+pub(crate) const DEFAULT_LIMIT: u32 = 10;
+// maximum number of items in batch queries
+const MAX_BATCH_SIZE: usize = 50;
 
 pub fn list_streams(
     deps: Deps,
+    env: Env,
     start_after: Option<u64>,
     limit: Option<u32>,
 ) -> StdResult<StreamsResponse> {
@@ -1200,28 +2748,7 @@ pub fn list_streams(
         .take(limit)
         .map(|item| {
             let (stream_id, stream) = item?;
-            let stream = StreamResponse {
-                id: stream_id,
-                treasury: stream.treasury.to_string(),
-                in_denom: stream.in_denom,
-                out_denom: stream.out_denom,
-                out_supply: stream.out_supply,
-                start_time: stream.start_time,
-                end_time: stream.end_time,
-                spent_in: stream.spent_in,
-                last_updated: stream.last_updated,
-                dist_index: stream.dist_index,
-                out_remaining: stream.out_remaining,
-                in_supply: stream.in_supply,
-                shares: stream.shares,
-                status: stream.status,
-                pause_date: stream.pause_date,
-                url: stream.url,
-                current_streamed_price: stream.current_streamed_price,
-                exit_fee_percent: stream.stream_exit_fee_percent,
-                stream_creation_fee: stream.stream_creation_fee,
-            };
-            Ok(stream)
+            Ok(stream_response(stream_id, stream, env.block.time))
         })
         .collect();
     let streams = streams?;
@@ -1236,19 +2763,72 @@ pub fn query_position(
 ) -> StdResult<PositionResponse> {
     let owner = deps.api.addr_validate(&owner)?;
     let position = POSITIONS.load(deps.storage, (stream_id, &owner))?;
-    let res = PositionResponse {
+    position_response(deps.storage, stream_id, position)
+}
+
+pub fn query_position_operators(
+    deps: Deps,
+    stream_id: u64,
+    owner: String,
+) -> StdResult<PositionOperatorsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let operators = POSITION_OPERATORS
+        .prefix((stream_id, &owner))
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (operator, grant) = item?;
+            Ok(PositionOperatorResponse {
+                operator: operator.to_string(),
+                permissions: grant.permissions,
+                expires: grant.expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PositionOperatorsResponse { operators })
+}
+
+pub fn query_account_operators(deps: Deps, owner: String) -> StdResult<AccountOperatorsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let operators = ACCOUNT_OPERATORS
+        .prefix(&owner)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (operator, expires) = item?;
+            Ok(AccountOperatorResponse {
+                operator: operator.to_string(),
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AccountOperatorsResponse { operators })
+}
+
+// the operator of the response is the first operator with full control of the position
+fn position_response(
+    storage: &dyn Storage,
+    stream_id: u64,
+    position: Position,
+) -> StdResult<PositionResponse> {
+    let operator = POSITION_OPERATORS
+        .prefix((stream_id, &position.owner))
+        .range(storage, None, None, Order::Ascending)
+        .find(|item| item.as_ref().map_or(true, |(_, grant)| grant.is_full()))
+        .transpose()?
+        .map(|(operator, _)| operator);
+    Ok(PositionResponse {
         stream_id,
-        owner: owner.to_string(),
+        owner: position.owner.to_string(),
         in_balance: position.in_balance,
         purchased: position.purchased,
         index: position.index,
         spent: position.spent,
         shares: position.shares,
-        operator: position.operator,
+        operator,
         last_updated: position.last_updated,
         pending_purchase: position.pending_purchase,
-    };
-    Ok(res)
+        claimed: position.claimed,
+        token_id: position.token_id,
+    })
 }
 
 pub fn list_positions(
@@ -1266,26 +2846,68 @@ pub fn list_positions(
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
-            let (owner, position) = item?;
-            let position = PositionResponse {
-                stream_id,
-                owner: owner.to_string(),
-                index: position.index,
-                last_updated: position.last_updated,
-                purchased: position.purchased,
-                pending_purchase: position.pending_purchase,
-                spent: position.spent,
-                in_balance: position.in_balance,
-                shares: position.shares,
-                operator: position.operator,
-            };
-            Ok(position)
+            let (_, position) = item?;
+            position_response(deps.storage, stream_id, position)
         })
         .collect();
     let positions = positions?;
     Ok(PositionsResponse { positions })
 }
 
+pub fn query_streams_by_ids(
+    deps: Deps,
+    env: Env,
+    ids: Vec<u64>,
+) -> StdResult<StreamsByIdsResponse> {
+    if ids.len() > MAX_BATCH_SIZE {
+        return Err(StdError::generic_err(format!(
+            "Batch size exceeds the limit of {}",
+            MAX_BATCH_SIZE
+        )));
+    }
+    let streams = ids
+        .into_iter()
+        .map(|id| {
+            let stream = STREAMS.may_load(deps.storage, id)?;
+            Ok(StreamResult {
+                id,
+                stream: stream.map(|stream| stream_response(id, stream, env.block.time)),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(StreamsByIdsResponse { streams })
+}
+
+pub fn query_positions_batch(
+    deps: Deps,
+    pairs: Vec<(u64, String)>,
+) -> StdResult<PositionsBatchResponse> {
+    if pairs.len() > MAX_BATCH_SIZE {
+        return Err(StdError::generic_err(format!(
+            "Batch size exceeds the limit of {}",
+            MAX_BATCH_SIZE
+        )));
+    }
+    let positions = pairs
+        .into_iter()
+        .map(|(stream_id, owner)| {
+            // an invalid owner can't hold a position, it doesn't fail the whole batch
+            let position = match deps.api.addr_validate(&owner) {
+                Ok(owner) => POSITIONS.may_load(deps.storage, (stream_id, &owner))?,
+                Err(_) => None,
+            };
+            Ok(PositionResult {
+                stream_id,
+                owner,
+                position: position
+                    .map(|position| position_response(deps.storage, stream_id, position))
+                    .transpose()?,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PositionsBatchResponse { positions })
+}
+
 pub fn query_average_price(
     deps: Deps,
     _env: Env,
@@ -1297,6 +2919,15 @@ pub fn query_average_price(
     Ok(AveragePriceResponse { average_price })
 }
 
+pub fn query_pre_committed(deps: Deps, stream_id: u64) -> StdResult<PreCommittedResponse> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    Ok(PreCommittedResponse {
+        in_denom: stream.in_denom,
+        pre_committed_in: stream.pre_committed_in,
+        start_time: stream.start_time,
+    })
+}
+
 pub fn query_last_streamed_price(
     deps: Deps,
     _env: Env,
@@ -1310,10 +2941,194 @@ pub fn query_last_streamed_price(
 
 pub fn query_threshold_state(
     deps: Deps,
-    _env: Env,
+    env: Env,
     stream_id: u64,
-) -> Result<Option<Uint128>, StdError> {
+) -> Result<Option<ThresholdStatus>, StdError> {
     let threshold_state = ThresholdState::new();
-    let threshold = threshold_state.get_threshold(stream_id, deps.storage)?;
-    Ok(threshold)
+    let threshold = match threshold_state.get_threshold(stream_id, deps.storage)? {
+        Some(threshold) => threshold,
+        None => return Ok(None),
+    };
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    // sync the stream to the current block, stopped streams do not progress
+    let mut projected = stream.clone();
+    if !stream.is_killswitch_active() && stream.status != Status::Finalized {
+        update_stream(env.block.time, &mut stream).map_err(to_std_err)?;
+        // project the stream as if current in_supply keeps streaming until the end
+        projected = stream.clone();
+        update_stream(stream.end_time.max(env.block.time), &mut projected).map_err(to_std_err)?;
+    }
+    Ok(Some(ThresholdStatus {
+        current: threshold.progress(&stream),
+        spent_in: stream.spent_in,
+        percent_reached: threshold.percent_reached(&stream)?,
+        projected: threshold.progress(&projected),
+        projected_spent_in: projected.spent_in,
+        reached: threshold.is_reached(&stream),
+        guaranteed: threshold.is_guaranteed(&stream),
+        threshold,
+    }))
+}
+
+pub fn query_estimate_subscription(
+    deps: Deps,
+    env: Env,
+    stream_id: u64,
+    amount: Uint128,
+    at_time: Option<Timestamp>,
+) -> StdResult<EstimateSubscriptionResponse> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    // the stream can't be synced backwards
+    if at_time.is_some_and(|at_time| at_time < env.block.time.max(stream.last_updated)) {
+        return Err(to_std_err(ContractError::InvalidEstimateTime {}));
+    }
+    let now = at_time.unwrap_or(env.block.time);
+    if stream.is_killswitch_active() {
+        return Err(to_std_err(ContractError::StreamKillswitchActive {}));
+    }
+    if now >= stream.end_time {
+        return Err(to_std_err(ContractError::StreamEnded {}));
+    }
+
+    // sync stream to the subscription time, same as execute_subscribe does
+    update_stream(now, &mut stream).map_err(to_std_err)?;
+    let shares = stream.compute_shares_amount(amount, false);
+    let mut position = Position::new(
+        Addr::unchecked(""),
+        amount,
+        shares,
+        Some(stream.dist_index),
+        now,
+    );
+    stream.in_supply = stream.in_supply.checked_add(amount)?;
+    stream.shares = stream.shares.checked_add(shares)?;
+
+    // project the stream to its end assuming no further subscriptions or withdrawals
+    update_stream(stream.end_time, &mut stream).map_err(to_std_err)?;
+    update_position(
+        stream.dist_index,
+        stream.shares,
+        stream.last_updated,
+        stream.in_supply,
+        &mut position,
+    )
+    .map_err(to_std_err)?;
+
+    let implied_price = if position.purchased.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(position.spent, position.purchased)
+    };
+    let (in_percent, out_percent) = stream
+        .fee_side
+        .split(stream.exit_fee_percent_for(position.spent))?;
+    let exit_fee = Decimal::from_ratio(position.spent, Uint128::one()).checked_mul(in_percent)?
+        * Uint128::one();
+    let exit_fee_out = Decimal::from_ratio(position.purchased, Uint128::one())
+        .checked_mul(out_percent)?
+        * Uint128::one();
+
+    Ok(EstimateSubscriptionResponse {
+        shares,
+        expected_out: position.purchased.checked_sub(exit_fee_out)?,
+        expected_spent: position.spent,
+        implied_price,
+        exit_fee,
+        exit_fee_out,
+    })
+}
+
+pub fn query_exit_preview(
+    deps: Deps,
+    env: Env,
+    stream_id: u64,
+    owner: String,
+) -> StdResult<ExitPreviewResponse> {
+    let stream = STREAMS.load(deps.storage, stream_id)?;
+    let owner = deps.api.addr_validate(&owner)?;
+    let mut position = POSITIONS.load(deps.storage, (stream_id, &owner))?;
+
+    // cancelled streams and ended streams with unmet threshold exit through exit_cancelled
+    let mut cancelled_stream = stream.clone();
+    if killswitch::check_exit_cancelled(
+        deps.storage,
+        env.block.time,
+        stream_id,
+        &mut cancelled_stream,
+    )
+    .is_ok()
+    {
+        // the spent part paying for claimed out tokens goes to the treasury
+        let claimed_spent = position.claimed_spent();
+        return Ok(ExitPreviewResponse {
+            purchased: Uint128::zero(),
+            refund: position
+                .in_balance
+                .checked_add(position.spent)?
+                .checked_sub(claimed_spent)?,
+            treasury_refund: claimed_spent,
+            swap_fee: Uint128::zero(),
+            swap_fee_out: Uint128::zero(),
+            cancelled: true,
+            threshold_not_reached: !stream.is_cancelled(),
+        });
+    }
+
+    let mut stream = stream;
+    check_exit_stream(deps.storage, env.block.time, stream_id, &mut stream).map_err(to_std_err)?;
+    let (purchased, unspent, swap_fee, swap_fee_out) =
+        compute_exit_stream(&stream, &mut position).map_err(to_std_err)?;
+    Ok(ExitPreviewResponse {
+        purchased,
+        refund: unspent,
+        treasury_refund: Uint128::zero(),
+        swap_fee,
+        swap_fee_out,
+        cancelled: false,
+        threshold_not_reached: false,
+    })
+}
+
+pub fn query_finalize_preview(
+    deps: Deps,
+    env: Env,
+    stream_id: u64,
+) -> StdResult<FinalizePreviewResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    check_finalize_stream(env.block.time, &mut stream).map_err(to_std_err)?;
+
+    let threshold_not_reached = ThresholdState::new()
+        .error_if_not_reached(stream_id, deps.storage, &stream)
+        .is_err();
+    let (creator_revenue, swap_fee, _) =
+        compute_finalize_stream(&stream, config.keeper_reward_percent).map_err(to_std_err)?;
+
+    Ok(FinalizePreviewResponse {
+        treasury: stream.treasury.to_string(),
+        creator_revenue,
+        swap_fee,
+        fee_collectors: config.fee_collectors,
+        creation_fee: Coin {
+            denom: stream.stream_creation_denom,
+            amount: stream.stream_creation_fee,
+        },
+        refunded_out_remaining: stream.out_remaining,
+        total_sold: stream.out_supply.checked_sub(stream.out_remaining)?,
+        threshold_not_reached,
+    })
+}
+
+pub fn query_unclaimed_fees(deps: Deps, collector: String) -> StdResult<UnclaimedFeesResponse> {
+    let collector = deps.api.addr_validate(&collector)?;
+    Ok(UnclaimedFeesResponse {
+        fees: unclaimed_fees(deps.storage, &collector)?,
+        collector: collector.to_string(),
+    })
+}
+
+// stats are updated on status changes, a started stream is counted as waiting
+// until the first transaction activates it
+pub fn query_stats(deps: Deps) -> StdResult<ProtocolStats> {
+    STATS.load(deps.storage)
 }
@@ -0,0 +1,121 @@
+use crate::events::{SetFeeDiscountEvent, SetHolderFeeDiscountsEvent};
+use crate::msg::{FeeDiscountResponse, HolderFeeDiscountsResponse};
+use crate::state::{
+    FeeDiscount, HolderFeeDiscount, ADDRESS_FEE_DISCOUNTS, CONFIG, HOLDER_FEE_DISCOUNTS,
+};
+use crate::ContractError;
+use cosmwasm_std::{attr, Addr, Decimal, Deps, DepsMut, Event, MessageInfo, Response, StdResult};
+
+pub fn execute_set_fee_discount(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    discount: Option<FeeDiscount>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.protocol_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let address = deps.api.addr_validate(&address)?;
+
+    let mut attrs = vec![
+        attr("action", "set_fee_discount"),
+        attr("address", address.as_str()),
+    ];
+    let event: Event = SetFeeDiscountEvent {
+        address: &address,
+        discount: discount.as_ref(),
+    }
+    .into();
+    match discount {
+        Some(discount) => {
+            check_fee_discount(&discount)?;
+            ADDRESS_FEE_DISCOUNTS.save(deps.storage, &address, &discount)?;
+            attrs.push(attr(
+                "creation_fee_discount",
+                discount.creation_fee_discount.to_string(),
+            ));
+            attrs.push(attr(
+                "exit_fee_discount",
+                discount.exit_fee_discount.to_string(),
+            ));
+        }
+        None => {
+            ADDRESS_FEE_DISCOUNTS.remove(deps.storage, &address);
+            attrs.push(attr("removed", "true"));
+        }
+    }
+
+    Ok(Response::default().add_event(event).add_attributes(attrs))
+}
+
+pub fn execute_set_holder_fee_discounts(
+    deps: DepsMut,
+    info: MessageInfo,
+    discounts: Vec<HolderFeeDiscount>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.protocol_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    for holder_discount in discounts.iter() {
+        if holder_discount.min_balance.is_zero() {
+            return Err(ContractError::InvalidFeeDiscount {});
+        }
+        check_fee_discount(&holder_discount.discount)?;
+    }
+    HOLDER_FEE_DISCOUNTS.save(deps.storage, &discounts)?;
+    let event: Event = SetHolderFeeDiscountsEvent {
+        discounts: &discounts,
+    }
+    .into();
+
+    Ok(Response::default().add_event(event).add_attributes(vec![
+        attr("action", "set_holder_fee_discounts"),
+        attr("discounts", discounts.len().to_string()),
+    ]))
+}
+
+fn check_fee_discount(discount: &FeeDiscount) -> Result<(), ContractError> {
+    if discount.creation_fee_discount > Decimal::one()
+        || discount.exit_fee_discount > Decimal::one()
+    {
+        return Err(ContractError::InvalidFeeDiscount {});
+    }
+    Ok(())
+}
+
+// returns the discount granted to an address, per-address overrides take precedence over
+// holder discounts which are checked in registry order
+pub fn fee_discount_for(deps: Deps, address: &Addr) -> StdResult<Option<FeeDiscount>> {
+    if let Some(discount) = ADDRESS_FEE_DISCOUNTS.may_load(deps.storage, address)? {
+        return Ok(Some(discount));
+    }
+    for holder_discount in HOLDER_FEE_DISCOUNTS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+    {
+        let balance = deps
+            .querier
+            .query_balance(address, &holder_discount.denom)?;
+        if balance.amount >= holder_discount.min_balance {
+            return Ok(Some(holder_discount.discount));
+        }
+    }
+    Ok(None)
+}
+
+pub fn query_fee_discount(deps: Deps, address: String) -> StdResult<FeeDiscountResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    Ok(FeeDiscountResponse {
+        discount: fee_discount_for(deps, &address)?,
+    })
+}
+
+pub fn query_holder_fee_discounts(deps: Deps) -> StdResult<HolderFeeDiscountsResponse> {
+    Ok(HolderFeeDiscountsResponse {
+        discounts: HOLDER_FEE_DISCOUNTS
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+    })
+}
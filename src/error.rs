@@ -39,6 +39,14 @@ pub enum ContractError {
     #[error("Exit fee must be between 0 and 1")]
     InvalidExitFeePercent {},
 
+    #[error(
+        "Exit fee tiers must be sorted by min_spent and fees can not exceed the previous tier"
+    )]
+    InvalidExitFeeTiers {},
+
+    #[error("Fee collectors must be unique with positive weights summing up to one")]
+    InvalidFeeCollectors {},
+
     #[error("Required denom not found in funds")]
     NoFundsSent {},
 
@@ -60,6 +68,27 @@ pub enum ContractError {
     #[error("Withdraw amount cannot be zero")]
     InvalidWithdrawAmount {},
 
+    #[error("Claim amount exceeds claimable purchased amount: {0}")]
+    ClaimAmountExceedsPurchased(Uint128),
+
+    #[error("Claim amount cannot be zero")]
+    InvalidClaimAmount {},
+
+    #[error("Operator permissions cannot be empty")]
+    InvalidOperatorPermissions {},
+
+    #[error("Operator cannot be the position owner")]
+    InvalidOperator {},
+
+    #[error("Expiration is already expired")]
+    InvalidExpiration {},
+
+    #[error("Position cannot be transferred to its owner")]
+    InvalidTransferRecipient {},
+
+    #[error("Recipient already has a position in the stream")]
+    PositionExists {},
+
     #[error("Invalid funds")]
     InvalidFunds {},
 
@@ -102,6 +131,9 @@ pub enum ContractError {
     #[error("Stream Ended")]
     StreamEnded {},
 
+    #[error("Estimate time is before the current block or the last stream update")]
+    InvalidEstimateTime {},
+
     #[error("Stream not started")]
     StreamNotStarted {},
 
@@ -150,6 +182,39 @@ pub enum ContractError {
     #[error("Invalid stream creation fee")]
     InvalidStreamCreationFee {},
 
+    #[error("Creation fee options must have distinct denoms and non zero amounts")]
+    InvalidStreamCreationFeeOptions {},
+
+    #[error("Fee discounts can not exceed one and holder discounts require a minimum balance")]
+    InvalidFeeDiscount {},
+
+    #[error("Out share of a split fee side must be between zero and one")]
+    InvalidFeeSide {},
+
+    #[error("No fees to claim")]
+    NoFeesToClaim {},
+
+    #[error("No refunds to process")]
+    NoRefundsToProcess {},
+
+    #[error("No exits to distribute")]
+    NoExitsToDistribute {},
+
+    #[error("Invalid keeper reward percent")]
+    InvalidKeeperRewardPercent {},
+
     #[error("Invalid exit fee")]
     InvalidStreamExitFee {},
+
+    #[error("Hook is already registered")]
+    HookAlreadyRegistered {},
+
+    #[error("Hook is not registered")]
+    HookNotRegistered {},
+
+    #[error("Too many hooks registered")]
+    TooManyHooks {},
+
+    #[error("Unknown reply id: {0}")]
+    UnknownReplyId(u64),
 }
@@ -0,0 +1,754 @@
+// Typed events emitted by stream, position, operator and protocol handlers.
+//
+// Every event has the action as its type and a `version` attribute. The attributes of a version
+// never change, new attributes bump the version. Events carry the stream (`stream_*`) and the
+// position (`position_*`) state after the action, so indexers can rebuild them from events only.
+// Amounts are in the smallest unit of their denom.
+use crate::state::{
+    Config, ExitFeeTier, FeeDiscount, FeeSide, HolderFeeDiscount, OperatorGrant, Position, Status,
+    Stream,
+};
+use crate::threshold::Threshold;
+use cosmwasm_std::{Addr, Attribute, Coin, Event, Uint128};
+use cw_utils::Expiration;
+
+pub const EVENT_VERSION: &str = "2";
+
+/// A stream is created.
+pub struct CreateStreamEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub threshold: Option<&'a Threshold>,
+}
+
+/// Tokens are added to a position, `pending` before the stream starts.
+pub struct SubscribeEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub position: &'a Position,
+    pub in_amount: Uint128,
+    /// shares minted for `in_amount`.
+    pub shares: Uint128,
+    pub pending: bool,
+}
+
+/// Unspent tokens are withdrawn from a position, including paused and pending streams.
+pub struct WithdrawEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub position: &'a Position,
+    pub recipient: &'a Addr,
+    pub amount: Uint128,
+    /// shares burned for `amount`.
+    pub shares: Uint128,
+}
+
+/// A position is synced to the stream distribution.
+pub struct UpdatePositionEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub position: &'a Position,
+    pub purchased: Uint128,
+    pub spent: Uint128,
+}
+
+/// A stream distribution is synced.
+pub struct UpdateStreamEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    /// `token_out` distributed since the last update.
+    pub distributed: Uint128,
+}
+
+/// Purchased tokens are claimed during the stream.
+pub struct ClaimPurchasedEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub position: &'a Position,
+    pub recipient: &'a Addr,
+    pub amount: Uint128,
+}
+
+/// A position exits an ended stream, by its owner or a keeper.
+pub struct ExitStreamEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub position: &'a Position,
+    pub recipient: &'a Addr,
+    pub purchased: Uint128,
+    pub refund: Uint128,
+    pub swap_fee: Uint128,
+    pub swap_fee_out: Uint128,
+    pub keeper: Option<&'a Addr>,
+}
+
+/// A position exits a cancelled stream, by its owner or a refund batch.
+pub struct ExitCancelledEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub position: &'a Position,
+    pub recipient: &'a Addr,
+    pub refund: Uint128,
+    /// spent tokens paying for claimed tokens, sent to the treasury.
+    pub treasury_refund: Uint128,
+}
+
+/// A stream is finalized.
+pub struct FinalizeStreamEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub treasury: &'a Addr,
+    pub creator_revenue: Uint128,
+    pub swap_fee: Uint128,
+}
+
+/// A stream is paused, `sudo` if paused by governance.
+pub struct PauseStreamEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub sudo: bool,
+}
+
+/// A paused stream is resumed, `sudo` if resumed by governance.
+pub struct ResumeStreamEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub sudo: bool,
+}
+
+#[derive(Clone, Copy)]
+pub enum CancelReason {
+    ProtocolAdmin,
+    Sudo,
+    Threshold,
+}
+
+/// A stream is cancelled.
+pub struct CancelStreamEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub reason: CancelReason,
+    /// `token_out` refunded to the treasury.
+    pub out_refund: Uint128,
+}
+
+/// A position is transferred, `merged` into the recipient's position if there was one.
+pub struct TransferPositionEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub owner: &'a Addr,
+    /// the recipient's position after the transfer.
+    pub position: &'a Position,
+    pub merged: bool,
+}
+
+/// The threshold of a waiting stream is set, changed or removed by the treasury.
+pub struct UpdateThresholdEvent<'a> {
+    pub stream_id: u64,
+    pub stream: &'a Stream,
+    pub previous: Option<&'a Threshold>,
+    pub threshold: Option<&'a Threshold>,
+}
+
+/// All operators of a position are replaced by `operator` with full control, or removed.
+pub struct UpdateOperatorEvent<'a> {
+    pub stream_id: u64,
+    pub owner: &'a Addr,
+    pub operator: Option<&'a Addr>,
+}
+
+/// An operator is granted scoped permissions on a position.
+pub struct GrantOperatorEvent<'a> {
+    pub stream_id: u64,
+    pub owner: &'a Addr,
+    pub operator: &'a Addr,
+    pub grant: &'a OperatorGrant,
+}
+
+/// The grant of an operator on a position is revoked.
+pub struct RevokeOperatorEvent<'a> {
+    pub stream_id: u64,
+    pub owner: &'a Addr,
+    pub operator: &'a Addr,
+}
+
+/// An operator is approved on all positions of the owner.
+pub struct ApproveOperatorEvent<'a> {
+    pub owner: &'a Addr,
+    pub operator: &'a Addr,
+    pub expires: &'a Expiration,
+}
+
+/// The approval of an operator on all positions of the owner is revoked.
+pub struct RevokeOperatorApprovalEvent<'a> {
+    pub owner: &'a Addr,
+    pub operator: &'a Addr,
+}
+
+/// A spender is approved to transfer a position token.
+pub struct ApproveEvent<'a> {
+    pub stream_id: u64,
+    pub owner: &'a Addr,
+    pub spender: &'a Addr,
+    pub token_id: &'a str,
+    pub expires: &'a Expiration,
+}
+
+/// The approval of a spender on a position token is revoked.
+pub struct RevokeEvent<'a> {
+    pub stream_id: u64,
+    pub owner: &'a Addr,
+    pub spender: &'a Addr,
+    pub token_id: &'a str,
+}
+
+/// A cw721 operator is approved on all tokens of the owner.
+pub struct ApproveAllEvent<'a> {
+    pub owner: &'a Addr,
+    pub operator: &'a Addr,
+    pub expires: &'a Expiration,
+}
+
+/// The approval of a cw721 operator on all tokens of the owner is revoked.
+pub struct RevokeAllEvent<'a> {
+    pub owner: &'a Addr,
+    pub operator: &'a Addr,
+}
+
+/// Accrued fees of a collector are claimed.
+pub struct ClaimFeesEvent<'a> {
+    pub collector: &'a Addr,
+    pub recipient: &'a Addr,
+    pub fees: &'a [Coin],
+}
+
+/// The protocol config is updated, the event carries the config after the update.
+pub struct UpdateConfigEvent<'a> {
+    pub config: &'a Config,
+}
+
+/// The protocol admin is replaced.
+pub struct UpdateProtocolAdminEvent<'a> {
+    pub previous: &'a Addr,
+    pub protocol_admin: &'a Addr,
+}
+
+/// The fee discount of an address is set, or removed if `discount` is none.
+pub struct SetFeeDiscountEvent<'a> {
+    pub address: &'a Addr,
+    pub discount: Option<&'a FeeDiscount>,
+}
+
+/// The holder fee discounts are replaced by `discounts`.
+pub struct SetHolderFeeDiscountsEvent<'a> {
+    pub discounts: &'a [HolderFeeDiscount],
+}
+
+/// A position token is burned, the position stays with its owner.
+pub struct BurnEvent<'a> {
+    pub stream_id: u64,
+    pub owner: &'a Addr,
+    pub token_id: &'a str,
+}
+
+/// A hook is registered, on all streams if `stream_id` is none.
+pub struct AddHookEvent<'a> {
+    pub stream_id: Option<u64>,
+    pub contract: &'a Addr,
+}
+
+/// A hook is removed, from all streams if `stream_id` is none.
+pub struct RemoveHookEvent<'a> {
+    pub stream_id: Option<u64>,
+    pub contract: &'a Addr,
+}
+
+fn versioned_event(ty: &str) -> Event {
+    Event::new(ty).add_attribute("version", EVENT_VERSION)
+}
+
+fn event(ty: &str, stream_id: u64) -> Event {
+    versioned_event(ty).add_attribute("stream_id", stream_id.to_string())
+}
+
+// empty attribute values are rejected by the chain, empty lists and missing values are "none"
+fn list_attr<T: ToString>(items: &[T]) -> String {
+    if items.is_empty() {
+        return "none".to_string();
+    }
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn threshold_attr(threshold: Option<&Threshold>) -> String {
+    threshold.map_or("none".to_string(), |t| t.to_string())
+}
+
+// returns the creation and exit fee discounts
+fn discount_attrs(discount: Option<&FeeDiscount>) -> (String, String) {
+    discount.map_or(("none".to_string(), "none".to_string()), |discount| {
+        (
+            discount.creation_fee_discount.to_string(),
+            discount.exit_fee_discount.to_string(),
+        )
+    })
+}
+
+fn fee_side_attr(fee_side: &FeeSide) -> String {
+    match fee_side {
+        FeeSide::In => "in".to_string(),
+        FeeSide::Out => "out".to_string(),
+        FeeSide::Both { out_share } => format!("both:{}", out_share),
+    }
+}
+
+// tiers are `{min_spent}:{fee_percent}`
+fn exit_fee_tiers_attr(tiers: &[ExitFeeTier]) -> String {
+    let tiers = tiers
+        .iter()
+        .map(|tier| format!("{}:{}", tier.min_spent, tier.fee_percent))
+        .collect::<Vec<_>>();
+    list_attr(&tiers)
+}
+
+fn hook_stream_attr(stream_id: Option<u64>) -> String {
+    stream_id.map_or("all".to_string(), |stream_id| stream_id.to_string())
+}
+
+fn status_attr(status: &Status) -> &'static str {
+    match status {
+        Status::Waiting => "waiting",
+        Status::Active => "active",
+        Status::Finalized => "finalized",
+        Status::Paused => "paused",
+        Status::Cancelled => "cancelled",
+    }
+}
+
+fn stream_attributes(stream: &Stream) -> Vec<Attribute> {
+    vec![
+        Attribute::new("stream_status", status_attr(&stream.status)),
+        Attribute::new("stream_treasury", &stream.treasury),
+        Attribute::new("stream_in_denom", &stream.in_denom),
+        Attribute::new("stream_in_supply", stream.in_supply),
+        Attribute::new("stream_pre_committed_in", stream.pre_committed_in),
+        Attribute::new("stream_spent_in", stream.spent_in),
+        Attribute::new("stream_shares", stream.shares),
+        Attribute::new("stream_out_denom", &stream.out_denom),
+        Attribute::new("stream_out_remaining", stream.out_remaining),
+        Attribute::new("stream_out_claimed", stream.out_claimed),
+        Attribute::new("stream_exit_fees_accrued", stream.exit_fees_accrued),
+        Attribute::new("stream_keeper_rewards", stream.keeper_rewards),
+        Attribute::new("stream_keeper_reserve", stream.keeper_reserve),
+        Attribute::new("stream_subscribers", stream.subscribers.to_string()),
+        Attribute::new("stream_dist_index", stream.dist_index.to_string()),
+        Attribute::new(
+            "stream_current_streamed_price",
+            stream.current_streamed_price.to_string(),
+        ),
+        Attribute::new("stream_last_updated", stream.last_updated.to_string()),
+        Attribute::new("stream_start_time", stream.start_time.to_string()),
+        Attribute::new("stream_end_time", stream.end_time.to_string()),
+        Attribute::new(
+            "stream_pause_date",
+            stream
+                .pause_date
+                .map_or("none".to_string(), |date| date.to_string()),
+        ),
+    ]
+}
+
+fn position_attributes(position: &Position) -> Vec<Attribute> {
+    vec![
+        Attribute::new("owner", &position.owner),
+        Attribute::new("position_in_balance", position.in_balance),
+        Attribute::new("position_shares", position.shares),
+        Attribute::new("position_index", position.index.to_string()),
+        Attribute::new("position_purchased", position.purchased),
+        Attribute::new(
+            "position_pending_purchase",
+            position.pending_purchase.to_string(),
+        ),
+        Attribute::new("position_spent", position.spent),
+        Attribute::new("position_claimed", position.claimed),
+        Attribute::new("position_last_updated", position.last_updated.to_string()),
+    ]
+}
+
+impl From<CreateStreamEvent<'_>> for Event {
+    fn from(e: CreateStreamEvent) -> Self {
+        let (creation_fee_discount, exit_fee_discount) =
+            discount_attrs(e.stream.fee_discount.as_ref());
+        event("create_stream", e.stream_id)
+            .add_attribute("treasury", &e.stream.treasury)
+            .add_attribute("name", &e.stream.name)
+            .add_attribute("url", e.stream.url.clone().unwrap_or("none".to_string()))
+            .add_attribute("out_supply", e.stream.out_supply)
+            .add_attribute("start_time", e.stream.start_time.to_string())
+            .add_attribute("creation_fee_denom", &e.stream.stream_creation_denom)
+            .add_attribute("creation_fee", e.stream.stream_creation_fee)
+            .add_attribute(
+                "exit_fee_percent",
+                e.stream.stream_exit_fee_percent.to_string(),
+            )
+            .add_attribute(
+                "exit_fee_tiers",
+                exit_fee_tiers_attr(&e.stream.stream_exit_fee_tiers),
+            )
+            .add_attribute("fee_side", fee_side_attr(&e.stream.fee_side))
+            .add_attribute("creation_fee_discount", creation_fee_discount)
+            .add_attribute("exit_fee_discount", exit_fee_discount)
+            .add_attribute("threshold", threshold_attr(e.threshold))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<SubscribeEvent<'_>> for Event {
+    fn from(e: SubscribeEvent) -> Self {
+        event("subscribe", e.stream_id)
+            .add_attribute("in_amount", e.in_amount)
+            .add_attribute("shares", e.shares)
+            .add_attribute("pending", e.pending.to_string())
+            .add_attribute(
+                "token_id",
+                e.position.token_id.clone().unwrap_or("none".to_string()),
+            )
+            .add_attributes(position_attributes(e.position))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<WithdrawEvent<'_>> for Event {
+    fn from(e: WithdrawEvent) -> Self {
+        event("withdraw", e.stream_id)
+            .add_attribute("recipient", e.recipient)
+            .add_attribute("amount", e.amount)
+            .add_attribute("shares", e.shares)
+            .add_attributes(position_attributes(e.position))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<UpdatePositionEvent<'_>> for Event {
+    fn from(e: UpdatePositionEvent) -> Self {
+        event("update_position", e.stream_id)
+            .add_attribute("purchased", e.purchased)
+            .add_attribute("spent", e.spent)
+            .add_attributes(position_attributes(e.position))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<UpdateStreamEvent<'_>> for Event {
+    fn from(e: UpdateStreamEvent) -> Self {
+        event("update_stream", e.stream_id)
+            .add_attribute("distributed", e.distributed)
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<ClaimPurchasedEvent<'_>> for Event {
+    fn from(e: ClaimPurchasedEvent) -> Self {
+        event("claim_purchased", e.stream_id)
+            .add_attribute("recipient", e.recipient)
+            .add_attribute("amount", e.amount)
+            .add_attributes(position_attributes(e.position))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<ExitStreamEvent<'_>> for Event {
+    fn from(e: ExitStreamEvent) -> Self {
+        event("exit_stream", e.stream_id)
+            .add_attribute("recipient", e.recipient)
+            .add_attribute("purchased", e.purchased)
+            .add_attribute("refund", e.refund)
+            .add_attribute("swap_fee", e.swap_fee)
+            .add_attribute("swap_fee_out", e.swap_fee_out)
+            .add_attribute("keeper", e.keeper.map_or("none", |keeper| keeper.as_str()))
+            .add_attributes(position_attributes(e.position))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<ExitCancelledEvent<'_>> for Event {
+    fn from(e: ExitCancelledEvent) -> Self {
+        event("exit_cancelled", e.stream_id)
+            .add_attribute("recipient", e.recipient)
+            .add_attribute("refund", e.refund)
+            .add_attribute("treasury_refund", e.treasury_refund)
+            .add_attributes(position_attributes(e.position))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<FinalizeStreamEvent<'_>> for Event {
+    fn from(e: FinalizeStreamEvent) -> Self {
+        event("finalize_stream", e.stream_id)
+            .add_attribute("treasury", e.treasury)
+            .add_attribute("creator_revenue", e.creator_revenue)
+            .add_attribute("swap_fee", e.swap_fee)
+            .add_attribute("creation_fee_denom", &e.stream.stream_creation_denom)
+            .add_attribute("creation_fee", e.stream.stream_creation_fee)
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<PauseStreamEvent<'_>> for Event {
+    fn from(e: PauseStreamEvent) -> Self {
+        event("pause_stream", e.stream_id)
+            .add_attribute("sudo", e.sudo.to_string())
+            .add_attribute(
+                "pause_date",
+                e.stream
+                    .pause_date
+                    .map_or("none".to_string(), |date| date.to_string()),
+            )
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<ResumeStreamEvent<'_>> for Event {
+    fn from(e: ResumeStreamEvent) -> Self {
+        event("resume_stream", e.stream_id)
+            .add_attribute("sudo", e.sudo.to_string())
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<CancelStreamEvent<'_>> for Event {
+    fn from(e: CancelStreamEvent) -> Self {
+        let reason = match e.reason {
+            CancelReason::ProtocolAdmin => "protocol_admin",
+            CancelReason::Sudo => "sudo",
+            CancelReason::Threshold => "threshold",
+        };
+        event("cancel_stream", e.stream_id)
+            .add_attribute("reason", reason)
+            .add_attribute("out_refund", e.out_refund)
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<TransferPositionEvent<'_>> for Event {
+    fn from(e: TransferPositionEvent) -> Self {
+        event("transfer_position", e.stream_id)
+            .add_attribute("previous_owner", e.owner)
+            .add_attribute("merged", e.merged.to_string())
+            .add_attribute(
+                "token_id",
+                e.position.token_id.clone().unwrap_or("none".to_string()),
+            )
+            .add_attributes(position_attributes(e.position))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<UpdateThresholdEvent<'_>> for Event {
+    fn from(e: UpdateThresholdEvent) -> Self {
+        event("update_threshold", e.stream_id)
+            .add_attribute("previous_threshold", threshold_attr(e.previous))
+            .add_attribute("threshold", threshold_attr(e.threshold))
+            .add_attributes(stream_attributes(e.stream))
+    }
+}
+
+impl From<UpdateOperatorEvent<'_>> for Event {
+    fn from(e: UpdateOperatorEvent) -> Self {
+        event("update_operator", e.stream_id)
+            .add_attribute("owner", e.owner)
+            .add_attribute(
+                "operator",
+                e.operator.map_or("none", |operator| operator.as_str()),
+            )
+    }
+}
+
+impl From<GrantOperatorEvent<'_>> for Event {
+    fn from(e: GrantOperatorEvent) -> Self {
+        let permissions = e
+            .grant
+            .permissions
+            .iter()
+            .map(|permission| format!("{:?}", permission).to_lowercase())
+            .collect::<Vec<_>>();
+        event("grant_operator", e.stream_id)
+            .add_attribute("owner", e.owner)
+            .add_attribute("operator", e.operator)
+            .add_attribute("permissions", list_attr(&permissions))
+            .add_attribute("expires", e.grant.expires.to_string())
+    }
+}
+
+impl From<RevokeOperatorEvent<'_>> for Event {
+    fn from(e: RevokeOperatorEvent) -> Self {
+        event("revoke_operator", e.stream_id)
+            .add_attribute("owner", e.owner)
+            .add_attribute("operator", e.operator)
+    }
+}
+
+impl From<ApproveOperatorEvent<'_>> for Event {
+    fn from(e: ApproveOperatorEvent) -> Self {
+        versioned_event("approve_operator")
+            .add_attribute("owner", e.owner)
+            .add_attribute("operator", e.operator)
+            .add_attribute("expires", e.expires.to_string())
+    }
+}
+
+impl From<RevokeOperatorApprovalEvent<'_>> for Event {
+    fn from(e: RevokeOperatorApprovalEvent) -> Self {
+        versioned_event("revoke_operator_approval")
+            .add_attribute("owner", e.owner)
+            .add_attribute("operator", e.operator)
+    }
+}
+
+impl From<ApproveEvent<'_>> for Event {
+    fn from(e: ApproveEvent) -> Self {
+        event("approve", e.stream_id)
+            .add_attribute("owner", e.owner)
+            .add_attribute("spender", e.spender)
+            .add_attribute("token_id", e.token_id)
+            .add_attribute("expires", e.expires.to_string())
+    }
+}
+
+impl From<RevokeEvent<'_>> for Event {
+    fn from(e: RevokeEvent) -> Self {
+        event("revoke", e.stream_id)
+            .add_attribute("owner", e.owner)
+            .add_attribute("spender", e.spender)
+            .add_attribute("token_id", e.token_id)
+    }
+}
+
+impl From<ApproveAllEvent<'_>> for Event {
+    fn from(e: ApproveAllEvent) -> Self {
+        versioned_event("approve_all")
+            .add_attribute("owner", e.owner)
+            .add_attribute("operator", e.operator)
+            .add_attribute("expires", e.expires.to_string())
+    }
+}
+
+impl From<RevokeAllEvent<'_>> for Event {
+    fn from(e: RevokeAllEvent) -> Self {
+        versioned_event("revoke_all")
+            .add_attribute("owner", e.owner)
+            .add_attribute("operator", e.operator)
+    }
+}
+
+impl From<ClaimFeesEvent<'_>> for Event {
+    fn from(e: ClaimFeesEvent) -> Self {
+        versioned_event("claim_fees")
+            .add_attribute("collector", e.collector)
+            .add_attribute("recipient", e.recipient)
+            .add_attribute("fees", list_attr(e.fees))
+    }
+}
+
+impl From<UpdateConfigEvent<'_>> for Event {
+    fn from(e: UpdateConfigEvent) -> Self {
+        let config = e.config;
+        let fee_collectors = config
+            .fee_collectors
+            .iter()
+            .map(|collector| format!("{}:{}", collector.address, collector.weight))
+            .collect::<Vec<_>>();
+        versioned_event("update_config")
+            .add_attribute("min_stream_seconds", config.min_stream_seconds)
+            .add_attribute(
+                "min_seconds_until_start_time",
+                config.min_seconds_until_start_time,
+            )
+            .add_attribute("accepted_in_denom", &config.accepted_in_denom)
+            .add_attribute("stream_creation_denom", &config.stream_creation_denom)
+            .add_attribute("stream_creation_fee", config.stream_creation_fee)
+            .add_attribute(
+                "stream_creation_fee_options",
+                list_attr(&config.stream_creation_fee_options),
+            )
+            .add_attribute("exit_fee_percent", config.exit_fee_percent.to_string())
+            .add_attribute(
+                "exit_fee_tiers",
+                exit_fee_tiers_attr(&config.exit_fee_tiers),
+            )
+            .add_attribute("fee_side", fee_side_attr(&config.fee_side))
+            .add_attribute("fee_collectors", list_attr(&fee_collectors))
+            .add_attribute("protocol_admin", &config.protocol_admin)
+            .add_attribute(
+                "keeper_reward_percent",
+                config.keeper_reward_percent.to_string(),
+            )
+            .add_attribute("position_nfts", config.position_nfts.to_string())
+    }
+}
+
+impl From<AddHookEvent<'_>> for Event {
+    fn from(e: AddHookEvent) -> Self {
+        versioned_event("add_hook")
+            .add_attribute("stream_id", hook_stream_attr(e.stream_id))
+            .add_attribute("contract", e.contract)
+    }
+}
+
+impl From<RemoveHookEvent<'_>> for Event {
+    fn from(e: RemoveHookEvent) -> Self {
+        versioned_event("remove_hook")
+            .add_attribute("stream_id", hook_stream_attr(e.stream_id))
+            .add_attribute("contract", e.contract)
+    }
+}
+
+impl From<UpdateProtocolAdminEvent<'_>> for Event {
+    fn from(e: UpdateProtocolAdminEvent) -> Self {
+        versioned_event("update_protocol_admin")
+            .add_attribute("previous_protocol_admin", e.previous)
+            .add_attribute("protocol_admin", e.protocol_admin)
+    }
+}
+
+impl From<SetFeeDiscountEvent<'_>> for Event {
+    fn from(e: SetFeeDiscountEvent) -> Self {
+        let (creation_fee_discount, exit_fee_discount) = discount_attrs(e.discount);
+        versioned_event("set_fee_discount")
+            .add_attribute("address", e.address)
+            .add_attribute("creation_fee_discount", creation_fee_discount)
+            .add_attribute("exit_fee_discount", exit_fee_discount)
+    }
+}
+
+impl From<SetHolderFeeDiscountsEvent<'_>> for Event {
+    fn from(e: SetHolderFeeDiscountsEvent) -> Self {
+        // discounts are `{denom}:{min_balance}:{creation_fee_discount}:{exit_fee_discount}`
+        let discounts = e
+            .discounts
+            .iter()
+            .map(|d| {
+                format!(
+                    "{}:{}:{}:{}",
+                    d.denom,
+                    d.min_balance,
+                    d.discount.creation_fee_discount,
+                    d.discount.exit_fee_discount
+                )
+            })
+            .collect::<Vec<_>>();
+        versioned_event("set_holder_fee_discounts")
+            .add_attribute("discounts", list_attr(&discounts))
+    }
+}
+
+impl From<BurnEvent<'_>> for Event {
+    fn from(e: BurnEvent) -> Self {
+        event("burn", e.stream_id)
+            .add_attribute("owner", e.owner)
+            .add_attribute("token_id", e.token_id)
+    }
+}
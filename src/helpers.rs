@@ -1,5 +1,7 @@
+use crate::msg::FeeCollectorMsg;
+use crate::state::{ExitFeeTier, FeeCollector, FeeSide};
 use crate::ContractError;
-use cosmwasm_std::{Decimal256, StdError};
+use cosmwasm_std::{Addr, Api, Coin, Decimal, Decimal256, StdError, Uint128};
 use std::str::FromStr;
 
 /// Stream validation related constants
@@ -26,7 +28,7 @@ pub fn get_decimals(value: Decimal256) -> Result<Decimal256, ContractError> {
     }
 }
 
-pub fn check_name_and_url(name: &String, url: &Option<String>) -> Result<(), ContractError> {
+pub fn check_name_and_url(name: &str, url: &Option<String>) -> Result<(), ContractError> {
     if name.len() < MIN_NAME_LENGTH {
         return Err(ContractError::StreamNameTooShort {});
     }
@@ -56,6 +58,108 @@ pub fn check_name_and_url(name: &String, url: &Option<String>) -> Result<(), Con
     Ok(())
 }
 
+// tiers are volume discounts, fees can only decrease as min_spent increases
+pub fn check_exit_fee_tiers(
+    exit_fee_percent: Decimal,
+    tiers: &[ExitFeeTier],
+) -> Result<(), ContractError> {
+    let mut prev_fee = exit_fee_percent;
+    for (i, tier) in tiers.iter().enumerate() {
+        if i > 0 && tier.min_spent <= tiers[i - 1].min_spent {
+            return Err(ContractError::InvalidExitFeeTiers {});
+        }
+        if tier.fee_percent > prev_fee {
+            return Err(ContractError::InvalidExitFeeTiers {});
+        }
+        prev_fee = tier.fee_percent;
+    }
+    Ok(())
+}
+
+pub fn check_fee_side(fee_side: &FeeSide) -> Result<(), ContractError> {
+    if let FeeSide::Both { out_share } = fee_side {
+        if out_share.is_zero() || *out_share >= Decimal::one() {
+            return Err(ContractError::InvalidFeeSide {});
+        }
+    }
+    Ok(())
+}
+
+pub fn check_creation_fee_options(
+    stream_creation_denom: &str,
+    options: &[Coin],
+) -> Result<(), ContractError> {
+    for (i, option) in options.iter().enumerate() {
+        if option.amount.is_zero()
+            || option.denom == stream_creation_denom
+            || options[..i].iter().any(|o| o.denom == option.denom)
+        {
+            return Err(ContractError::InvalidStreamCreationFeeOptions {});
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_fee_collectors(
+    api: &dyn Api,
+    collectors: Vec<FeeCollectorMsg>,
+) -> Result<Vec<FeeCollector>, ContractError> {
+    let mut total_weight = Decimal::zero();
+    let mut validated: Vec<FeeCollector> = Vec::with_capacity(collectors.len());
+    for collector in collectors {
+        let address = api.addr_validate(&collector.address)?;
+        if collector.weight.is_zero() || validated.iter().any(|c| c.address == address) {
+            return Err(ContractError::InvalidFeeCollectors {});
+        }
+        total_weight = total_weight.checked_add(collector.weight)?;
+        validated.push(FeeCollector {
+            address,
+            weight: collector.weight,
+        });
+    }
+    if total_weight != Decimal::one() {
+        return Err(ContractError::InvalidFeeCollectors {});
+    }
+    Ok(validated)
+}
+
+// splits the fee between collectors by weight, rounding leftover goes to the first collector.
+// returns non zero shares of collectors
+pub fn split_fee(
+    collectors: &[FeeCollector],
+    fee: Uint128,
+) -> Result<Vec<(Addr, Uint128)>, ContractError> {
+    let mut shares = collectors
+        .iter()
+        .map(|collector| {
+            let amount = Decimal::from_ratio(fee, Uint128::one()).checked_mul(collector.weight)?
+                * Uint128::one();
+            Ok(amount)
+        })
+        .collect::<Result<Vec<Uint128>, ContractError>>()?;
+    let distributed = shares
+        .iter()
+        .try_fold(Uint128::zero(), |acc, share| acc.checked_add(*share))?;
+    if let Some(first) = shares.first_mut() {
+        *first = first.checked_add(fee.checked_sub(distributed)?)?;
+    }
+
+    Ok(collectors
+        .iter()
+        .zip(shares)
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(collector, amount)| (collector.address.clone(), amount))
+        .collect())
+}
+
 pub fn from_semver(err: semver::Error) -> ContractError {
     ContractError::from(StdError::generic_err(format!("Semver: {}", err)))
 }
+
+// queries reuse execute side calculations, which return contract errors
+pub fn to_std_err(err: ContractError) -> StdError {
+    match err {
+        ContractError::Std(err) => err,
+        err => StdError::generic_err(err.to_string()),
+    }
+}
@@ -0,0 +1,152 @@
+use crate::events::{AddHookEvent, RemoveHookEvent};
+use crate::msg::{HooksResponse, StreamHookEvent, StreamHookMsg};
+use crate::state::{CONFIG, GLOBAL_HOOKS, STREAMS, STREAM_HOOKS};
+use crate::ContractError;
+use cosmwasm_std::{
+    to_json_binary, Addr, Deps, DepsMut, Empty, Event, MessageInfo, Order, Reply, Response,
+    StdResult, Storage, SubMsg, SubMsgResult, WasmMsg,
+};
+
+pub const HOOK_REPLY_ID: u64 = 1;
+// hooks are executed on every lifecycle event, their number is capped to bound gas usage
+const MAX_HOOKS: usize = 10;
+// gas available to each hook callback, a hook running out of gas fails like any other error
+pub const HOOK_GAS_LIMIT: u64 = 500_000;
+
+pub fn execute_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    stream_id: Option<u64>,
+    contract: String,
+) -> Result<Response, ContractError> {
+    check_hook_admin(deps.storage, &info, stream_id)?;
+    let contract = deps.api.addr_validate(&contract)?;
+    let hooks = load_hooks(deps.storage, stream_id)?;
+    if hooks.contains(&contract) {
+        return Err(ContractError::HookAlreadyRegistered {});
+    }
+    if hooks.len() >= MAX_HOOKS {
+        return Err(ContractError::TooManyHooks {});
+    }
+    match stream_id {
+        Some(stream_id) => STREAM_HOOKS.save(deps.storage, (stream_id, &contract), &Empty {})?,
+        None => GLOBAL_HOOKS.save(deps.storage, &contract, &Empty {})?,
+    }
+    let event: Event = AddHookEvent {
+        stream_id,
+        contract: &contract,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "add_hook")
+        .add_attribute("stream_id", stream_id_attr(stream_id))
+        .add_attribute("contract", contract))
+}
+
+pub fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    stream_id: Option<u64>,
+    contract: String,
+) -> Result<Response, ContractError> {
+    check_hook_admin(deps.storage, &info, stream_id)?;
+    let contract = deps.api.addr_validate(&contract)?;
+    if !load_hooks(deps.storage, stream_id)?.contains(&contract) {
+        return Err(ContractError::HookNotRegistered {});
+    }
+    match stream_id {
+        Some(stream_id) => STREAM_HOOKS.remove(deps.storage, (stream_id, &contract)),
+        None => GLOBAL_HOOKS.remove(deps.storage, &contract),
+    }
+    let event: Event = RemoveHookEvent {
+        stream_id,
+        contract: &contract,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "remove_hook")
+        .add_attribute("stream_id", stream_id_attr(stream_id))
+        .add_attribute("contract", contract))
+}
+
+// hooks of all streams are managed by the protocol admin, hooks of a stream by its treasury
+fn check_hook_admin(
+    storage: &dyn Storage,
+    info: &MessageInfo,
+    stream_id: Option<u64>,
+) -> Result<(), ContractError> {
+    let admin = match stream_id {
+        Some(stream_id) => STREAMS.load(storage, stream_id)?.treasury,
+        None => CONFIG.load(storage)?.protocol_admin,
+    };
+    if info.sender != admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn stream_id_attr(stream_id: Option<u64>) -> String {
+    stream_id.map_or("all".to_string(), |stream_id| stream_id.to_string())
+}
+
+fn load_hooks(storage: &dyn Storage, stream_id: Option<u64>) -> StdResult<Vec<Addr>> {
+    match stream_id {
+        Some(stream_id) => STREAM_HOOKS
+            .prefix(stream_id)
+            .keys(storage, None, None, Order::Ascending)
+            .collect(),
+        None => GLOBAL_HOOKS
+            .keys(storage, None, None, Order::Ascending)
+            .collect(),
+    }
+}
+
+// returns callbacks of the event to hooks of all streams and of the stream.
+// callbacks reply on error only and have a gas limit, so a failing hook does not revert the event
+pub fn hook_messages(
+    storage: &dyn Storage,
+    stream_id: u64,
+    event: StreamHookEvent,
+) -> StdResult<Vec<SubMsg>> {
+    let mut hooks = load_hooks(storage, None)?;
+    for hook in load_hooks(storage, Some(stream_id))? {
+        if !hooks.contains(&hook) {
+            hooks.push(hook);
+        }
+    }
+    let msg = to_json_binary(&StreamHookMsg::StreamHook(event))?;
+    Ok(hooks
+        .into_iter()
+        .map(|hook| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: hook.to_string(),
+                    msg: msg.clone(),
+                    funds: vec![],
+                },
+                HOOK_REPLY_ID,
+            )
+            .with_gas_limit(HOOK_GAS_LIMIT)
+        })
+        .collect())
+}
+
+pub fn reply_hook_failed(msg: Reply) -> Result<Response, ContractError> {
+    let mut res = Response::new().add_attribute("action", "hook_failed");
+    if let SubMsgResult::Err(error) = msg.result {
+        res = res.add_attribute("error", error);
+    }
+    Ok(res)
+}
+
+pub fn query_hooks(deps: Deps, stream_id: Option<u64>) -> StdResult<HooksResponse> {
+    let hooks = load_hooks(deps.storage, stream_id)?
+        .into_iter()
+        .map(|hook| hook.to_string())
+        .collect();
+    Ok(HooksResponse { hooks })
+}
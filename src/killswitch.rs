@@ -1,20 +1,33 @@
-use crate::contract::{update_position, update_stream};
-use crate::state::{Status, Stream, CONFIG, POSITIONS, STREAMS};
+use crate::contract::{
+    activate_stream, check_access, check_recipient, update_position, update_stream, DEFAULT_LIMIT,
+    MAX_LIMIT,
+};
+use crate::events::{
+    CancelReason, CancelStreamEvent, ExitCancelledEvent, PauseStreamEvent, ResumeStreamEvent,
+    WithdrawEvent,
+};
+use crate::hooks::hook_messages;
+use crate::msg::StreamHookEvent;
+use crate::state::{
+    burn_position_token, clear_position_operators, remove_position_stats, update_status_stats,
+    OperatorPermission, Status, Stream, CONFIG, POSITIONS, STREAMS,
+};
 use crate::threshold::{ThresholdError, ThresholdState};
 use crate::ContractError;
 use cosmwasm_std::{
-    attr, BankMsg, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdResult, Timestamp,
-    Uint128,
+    attr, BankMsg, Coin, CosmosMsg, DepsMut, Env, Event, MessageInfo, Order, Response, StdResult,
+    Storage, Timestamp, Uint128,
 };
 use cw_utils::maybe_addr;
 
 pub fn execute_withdraw_paused(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     stream_id: u64,
     cap: Option<Uint128>,
     operator_target: Option<String>,
+    recipient: Option<String>,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
     // check if stream is paused
@@ -26,14 +39,23 @@ pub fn execute_withdraw_paused(
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let mut position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    if position.owner != info.sender
-        && position
-            .operator
-            .as_ref()
-            .map_or(true, |o| o != &info.sender)
-    {
-        return Err(ContractError::Unauthorized {});
-    }
+    check_access(
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        OperatorPermission::Withdraw,
+    )?;
+    let recipient = check_recipient(
+        deps.api,
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        recipient,
+    )?;
 
     // on withdraw_paused we don't update_stream
     update_position(
@@ -65,6 +87,10 @@ pub fn execute_withdraw_paused(
     stream.shares = stream.shares.checked_sub(shares_amount)?;
     position.in_balance = position.in_balance.checked_sub(withdraw_amount)?;
     position.shares = position.shares.checked_sub(shares_amount)?;
+    // positions withdrawn in full no longer count as subscribers
+    if position.shares.is_zero() {
+        stream.subscribers = stream.subscribers.saturating_sub(1);
+    }
 
     STREAMS.save(deps.storage, stream_id, &stream)?;
     POSITIONS.save(deps.storage, (stream_id, &position.owner), &position)?;
@@ -74,18 +100,39 @@ pub fn execute_withdraw_paused(
         attr("stream_id", stream_id.to_string()),
         attr("operator_target", operator_target.clone()),
         attr("withdraw_amount", withdraw_amount),
+        attr("recipient", recipient.clone()),
     ];
 
+    let event: Event = WithdrawEvent {
+        stream_id,
+        stream: &stream,
+        position: &position,
+        recipient: &recipient,
+        amount: withdraw_amount,
+        shares: shares_amount,
+    }
+    .into();
+
     // send funds to withdraw address or to the sender
     let res = Response::new()
         .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: operator_target.to_string(),
+            to_address: recipient.to_string(),
             amount: vec![Coin {
                 denom: stream.in_denom,
                 amount: withdraw_amount,
             }],
         }))
-        .add_attributes(attributes);
+        .add_attributes(attributes)
+        .add_event(event)
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Withdraw {
+                stream_id,
+                owner: position.owner.to_string(),
+                amount: withdraw_amount,
+            },
+        )?);
 
     Ok(res)
 }
@@ -96,73 +143,193 @@ pub fn execute_exit_cancelled(
     info: MessageInfo,
     stream_id: u64,
     operator_target: Option<String>,
+    recipient: Option<String>,
 ) -> Result<Response, ContractError> {
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
-
-    // This execution requires the stream to be cancelled or
-    // the stream to be ended and the threshold not reached.
-
-    // check if stream is cancelled
-    if !stream.is_cancelled() {
-        let threshold_state = ThresholdState::new();
-        // Threshold should be set
-        let is_set = threshold_state.check_if_threshold_set(stream_id, deps.storage)?;
-        if !is_set {
-            return Err(ContractError::StreamNotCancelled {});
-        }
-
-        // Stream should not be paused
-        // If stream paused now_block can exceed end_block
-        // Stream being appeared as ended only happens when its paused or cancelled
-        if stream.is_paused() == true {
-            return Err(ContractError::StreamNotCancelled {});
-        }
-        // Stream should be ended
-        if stream.end_time > env.block.time {
-            return Err(ContractError::StreamNotCancelled {});
-        }
-        // Update stream before checking threshold
-        update_stream(env.block.time, &mut stream)?;
-        threshold_state.error_if_reached(stream_id, deps.storage, &stream)?;
-    }
+    check_exit_cancelled(deps.storage, env.block.time, stream_id, &mut stream)?;
 
     let operator_target =
         maybe_addr(deps.api, operator_target)?.unwrap_or_else(|| info.sender.clone());
     let position = POSITIONS.load(deps.storage, (stream_id, &operator_target))?;
-    if position.owner != info.sender
-        && position
-            .operator
-            .as_ref()
-            .map_or(true, |o| o != &info.sender)
-    {
-        return Err(ContractError::Unauthorized {});
-    }
+    check_access(
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        OperatorPermission::Exit,
+    )?;
+    let recipient = check_recipient(
+        deps.api,
+        deps.storage,
+        &env.block,
+        &info,
+        stream_id,
+        &position,
+        recipient,
+    )?;
 
     // no need to update position here, we just need to return total balance
-    let total_balance = position.in_balance + position.spent;
+    // except the spent part paying for claimed out tokens, it goes to the treasury
+    let claimed_spent = position.claimed_spent();
+    let total_balance = (position.in_balance + position.spent).checked_sub(claimed_spent)?;
     POSITIONS.remove(deps.storage, (stream_id, &position.owner));
+    clear_position_operators(deps.storage, stream_id, &position.owner)?;
+    burn_position_token(deps.storage, &position)?;
+    remove_position_stats(deps.storage)?;
 
     let attributes = vec![
-        attr("action", "withdraw_cancelled"),
+        attr("action", "exit_cancelled"),
         attr("stream_id", stream_id.to_string()),
         attr("operator_target", operator_target.clone()),
         attr("total_balance", total_balance),
+        attr("recipient", recipient.clone()),
     ];
+    let event: Event = ExitCancelledEvent {
+        stream_id,
+        stream: &stream,
+        position: &position,
+        recipient: &recipient,
+        refund: total_balance,
+        treasury_refund: claimed_spent,
+    }
+    .into();
 
     // send funds to withdraw address or to the sender
-    let res = Response::new()
-        .add_message(CosmosMsg::Bank(BankMsg::Send {
-            to_address: operator_target.to_string(),
+    let mut messages = vec![CosmosMsg::Bank(BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: vec![Coin {
+            denom: stream.in_denom.clone(),
+            amount: total_balance,
+        }],
+    })];
+    if !claimed_spent.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: stream.treasury.to_string(),
             amount: vec![Coin {
                 denom: stream.in_denom,
-                amount: total_balance,
+                amount: claimed_spent,
             }],
-        }))
-        .add_attributes(attributes);
+        }));
+    }
+    let res = Response::new()
+        .add_messages(messages)
+        .add_attributes(attributes)
+        .add_event(event);
 
     Ok(res)
 }
 
+pub fn execute_process_refunds(
+    deps: DepsMut,
+    env: Env,
+    stream_id: u64,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    check_exit_cancelled(deps.storage, env.block.time, stream_id, &mut stream)?;
+
+    // refunded positions are removed, remaining ones are always at the start
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let positions = POSITIONS
+        .prefix(stream_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    if positions.is_empty() {
+        return Err(ContractError::NoRefundsToProcess {});
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut events: Vec<Event> = vec![];
+    for (owner, position) in positions.iter() {
+        // same as exit cancelled, total balance is returned
+        let claimed_spent = position.claimed_spent();
+        let total_balance = (position.in_balance + position.spent).checked_sub(claimed_spent)?;
+        POSITIONS.remove(deps.storage, (stream_id, owner));
+        clear_position_operators(deps.storage, stream_id, owner)?;
+        burn_position_token(deps.storage, position)?;
+        remove_position_stats(deps.storage)?;
+        events.push(
+            ExitCancelledEvent {
+                stream_id,
+                stream: &stream,
+                position,
+                recipient: owner,
+                refund: total_balance,
+                treasury_refund: claimed_spent,
+            }
+            .into(),
+        );
+        if !total_balance.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: owner.to_string(),
+                amount: vec![Coin {
+                    denom: stream.in_denom.clone(),
+                    amount: total_balance,
+                }],
+            }));
+        }
+        if !claimed_spent.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: stream.treasury.to_string(),
+                amount: vec![Coin {
+                    denom: stream.in_denom.clone(),
+                    amount: claimed_spent,
+                }],
+            }));
+        }
+    }
+
+    let completed = POSITIONS
+        .prefix(stream_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_none();
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "process_refunds")
+        .add_attribute("stream_id", stream_id.to_string())
+        .add_attribute("refunded_positions", positions.len().to_string())
+        .add_attribute("completed", completed.to_string())
+        .add_events(events))
+}
+
+// This execution requires the stream to be cancelled or
+// the stream to be ended and the threshold not reached.
+pub fn check_exit_cancelled(
+    storage: &dyn Storage,
+    now: Timestamp,
+    stream_id: u64,
+    stream: &mut Stream,
+) -> Result<(), ContractError> {
+    // check if stream is cancelled
+    if !stream.is_cancelled() {
+        let threshold_state = ThresholdState::new();
+        // Threshold should be set
+        let is_set = threshold_state.check_if_threshold_set(stream_id, storage)?;
+        if !is_set {
+            return Err(ContractError::StreamNotCancelled {});
+        }
+
+        // Stream should not be paused
+        // If stream paused now_block can exceed end_block
+        // Stream being appeared as ended only happens when its paused or cancelled
+        if stream.is_paused() {
+            return Err(ContractError::StreamNotCancelled {});
+        }
+        // Stream should be ended
+        if stream.end_time > now {
+            return Err(ContractError::StreamNotCancelled {});
+        }
+        // Update stream before checking threshold
+        update_stream(now, stream)?;
+        threshold_state.error_if_reached(stream_id, storage, stream)?;
+    }
+    Ok(())
+}
+
 pub fn execute_pause_stream(
     deps: DepsMut,
     env: Env,
@@ -188,15 +355,31 @@ pub fn execute_pause_stream(
     }
     // update stream before pause
     let mut stream = STREAMS.load(deps.storage, stream_id)?;
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
+    let status = stream.status.clone();
     update_stream(env.block.time, &mut stream)?;
     pause_stream(env.block.time, &mut stream)?;
     STREAMS.save(deps.storage, stream_id, &stream)?;
+    update_status_stats(deps.storage, &status, &stream.status)?;
 
     Ok(Response::default()
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Pause { stream_id },
+        )?)
         .add_attribute("action", "pause_stream")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("is_paused", "true")
-        .add_attribute("pause_date", env.block.time.to_string()))
+        .add_attribute("pause_date", env.block.time.to_string())
+        .add_event(
+            PauseStreamEvent {
+                stream_id,
+                stream: &stream,
+                sudo: false,
+            }
+            .into(),
+        ))
 }
 
 pub fn pause_stream(now: Timestamp, stream: &mut Stream) -> StdResult<()> {
@@ -233,14 +416,34 @@ pub fn execute_resume_stream(
         .last_updated
         .plus_nanos(env.block.time.nanos() - pause_date.nanos());
 
-    stream.status = Status::Active;
+    // the stream is back to waiting, activated if it has started
+    stream.status = Status::Waiting;
+    stream.pause_date = None;
+    update_status_stats(deps.storage, &Status::Paused, &stream.status)?;
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
     STREAMS.save(deps.storage, stream_id, &stream)?;
 
     let attributes = vec![
         attr("action", "resume_stream"),
         attr("stream_id", stream_id.to_string()),
+        attr("new_end_date", stream.end_time.to_string()),
+        attr("status", "active"),
     ];
-    Ok(Response::default().add_attributes(attributes))
+    Ok(Response::default()
+        .add_attributes(attributes)
+        .add_event(
+            ResumeStreamEvent {
+                stream_id,
+                stream: &stream,
+                sudo: false,
+            }
+            .into(),
+        )
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Resume { stream_id },
+        )?))
 }
 
 pub fn execute_cancel_stream(
@@ -262,31 +465,47 @@ pub fn execute_cancel_stream(
     }
     stream.status = Status::Cancelled;
     STREAMS.save(deps.storage, stream_id, &stream)?;
+    update_status_stats(deps.storage, &Status::Paused, &stream.status)?;
+
+    let out_refund = stream.out_supply.checked_sub(stream.out_claimed)?;
+    let event: Event = CancelStreamEvent {
+        stream_id,
+        stream: &stream,
+        reason: CancelReason::ProtocolAdmin,
+        out_refund,
+    }
+    .into();
 
-    //Refund all out tokens to stream creator(treasury)
-    let messages: Vec<CosmosMsg> = vec![
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: stream.treasury.to_string(),
-            amount: vec![Coin {
-                denom: stream.out_denom,
-                amount: stream.out_supply,
-            }],
-        }),
-        //Refund stream creation fee to stream creator
-        CosmosMsg::Bank(BankMsg::Send {
+    //Refund all out tokens not claimed by positions to stream creator(treasury)
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
+        to_address: stream.treasury.to_string(),
+        amount: vec![Coin {
+            denom: stream.out_denom,
+            amount: out_refund,
+        }],
+    })];
+    //Refund stream creation fee to stream creator, nothing to refund if fully discounted
+    if !stream.stream_creation_fee.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
             to_address: stream.treasury.to_string(),
             amount: vec![Coin {
                 denom: stream.stream_creation_denom,
                 amount: stream.stream_creation_fee,
             }],
-        }),
-    ];
+        }));
+    }
 
     Ok(Response::new()
         .add_attribute("action", "cancel_stream")
         .add_messages(messages)
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Cancel { stream_id },
+        )?)
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("status", "cancelled"))
+        .add_attribute("status", "cancelled")
+        .add_event(event))
 }
 
 pub fn execute_cancel_stream_with_threshold(
@@ -328,9 +547,19 @@ pub fn execute_cancel_stream_with_threshold(
     // Threshold should not be reached
     threshold_state.error_if_reached(stream_id, deps.storage, &stream)?;
 
+    let status = stream.status.clone();
     stream.status = Status::Cancelled;
 
     STREAMS.save(deps.storage, stream_id, &stream)?;
+    update_status_stats(deps.storage, &status, &stream.status)?;
+
+    let event: Event = CancelStreamEvent {
+        stream_id,
+        stream: &stream,
+        reason: CancelReason::Threshold,
+        out_refund: stream.out_supply,
+    }
+    .into();
 
     //Refund all out tokens to stream creator(treasury)
     let messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
@@ -342,10 +571,16 @@ pub fn execute_cancel_stream_with_threshold(
     })];
 
     Ok(Response::new()
-        .add_attribute("action", "cancel_stream")
+        .add_attribute("action", "cancel_stream_with_threshold")
         .add_messages(messages)
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Cancel { stream_id },
+        )?)
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("status", "cancelled"))
+        .add_attribute("status", "cancelled")
+        .add_event(event))
 }
 
 pub fn sudo_pause_stream(
@@ -366,15 +601,31 @@ pub fn sudo_pause_stream(
     if stream.is_killswitch_active() {
         return Err(ContractError::StreamKillswitchActive {});
     }
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
+    let status = stream.status.clone();
     update_stream(env.block.time, &mut stream)?;
     pause_stream(env.block.time, &mut stream)?;
     STREAMS.save(deps.storage, stream_id, &stream)?;
+    update_status_stats(deps.storage, &status, &stream.status)?;
 
     Ok(Response::default()
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Pause { stream_id },
+        )?)
         .add_attribute("action", "sudo_pause_stream")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("is_paused", "true")
-        .add_attribute("pause_date", env.block.time.to_string()))
+        .add_attribute("pause_date", env.block.time.to_string())
+        .add_event(
+            PauseStreamEvent {
+                stream_id,
+                stream: &stream,
+                sudo: true,
+            }
+            .into(),
+        ))
 }
 
 pub fn sudo_resume_stream(
@@ -401,15 +652,31 @@ pub fn sudo_resume_stream(
         .last_updated
         .plus_nanos(env.block.time.nanos() - pause_date.nanos());
 
-    stream.status = Status::Active;
+    // the stream is back to waiting, activated if it has started
+    stream.status = Status::Waiting;
     stream.pause_date = None;
+    update_status_stats(deps.storage, &Status::Paused, &stream.status)?;
+    activate_stream(deps.storage, env.block.time, &mut stream)?;
     STREAMS.save(deps.storage, stream_id, &stream)?;
 
     Ok(Response::default()
-        .add_attribute("action", "resume_stream")
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Resume { stream_id },
+        )?)
+        .add_attribute("action", "sudo_resume_stream")
         .add_attribute("stream_id", stream_id.to_string())
         .add_attribute("new_end_date", stream.end_time.to_string())
-        .add_attribute("status", "active"))
+        .add_attribute("status", "active")
+        .add_event(
+            ResumeStreamEvent {
+                stream_id,
+                stream: &stream,
+                sudo: true,
+            }
+            .into(),
+        ))
 }
 
 pub fn sudo_cancel_stream(
@@ -426,29 +693,45 @@ pub fn sudo_cancel_stream(
     }
     stream.status = Status::Cancelled;
     STREAMS.save(deps.storage, stream_id, &stream)?;
+    update_status_stats(deps.storage, &Status::Paused, &stream.status)?;
+
+    let out_refund = stream.out_supply.checked_sub(stream.out_claimed)?;
+    let event: Event = CancelStreamEvent {
+        stream_id,
+        stream: &stream,
+        reason: CancelReason::Sudo,
+        out_refund,
+    }
+    .into();
 
-    //Refund all out tokens to stream creator(treasury)
-    let messages: Vec<CosmosMsg> = vec![
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: stream.treasury.to_string(),
-            amount: vec![Coin {
-                denom: stream.out_denom,
-                amount: stream.out_supply,
-            }],
-        }),
-        //Refund stream creation fee to stream creator
-        CosmosMsg::Bank(BankMsg::Send {
+    //Refund all out tokens not claimed by positions to stream creator(treasury)
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
+        to_address: stream.treasury.to_string(),
+        amount: vec![Coin {
+            denom: stream.out_denom,
+            amount: out_refund,
+        }],
+    })];
+    //Refund stream creation fee to stream creator, nothing to refund if fully discounted
+    if !stream.stream_creation_fee.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
             to_address: stream.treasury.to_string(),
             amount: vec![Coin {
                 denom: stream.stream_creation_denom,
                 amount: stream.stream_creation_fee,
             }],
-        }),
-    ];
+        }));
+    }
 
     Ok(Response::new()
-        .add_attribute("action", "cancel_stream")
+        .add_attribute("action", "sudo_cancel_stream")
         .add_messages(messages)
+        .add_submessages(hook_messages(
+            deps.storage,
+            stream_id,
+            StreamHookEvent::Cancel { stream_id },
+        )?)
         .add_attribute("stream_id", stream_id.to_string())
-        .add_attribute("status", "cancelled"))
+        .add_attribute("status", "cancelled")
+        .add_event(event))
 }
@@ -2,10 +2,14 @@ extern crate core;
 
 pub use crate::error::ContractError;
 pub mod contract;
+mod discount;
 mod error;
+mod events;
 mod helpers;
+mod hooks;
 mod killswitch;
 pub mod msg;
+mod nft;
 pub mod state;
 #[cfg(test)]
 mod tests;
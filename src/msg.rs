@@ -1,6 +1,14 @@
-use crate::state::Status;
+use crate::state::{
+    ExitFeeTier, FeeCollector, FeeDiscount, FeeSide, HolderFeeDiscount, OperatorPermission,
+    ProtocolStats, Status,
+};
+use crate::threshold::Threshold;
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Decimal, Decimal256, Timestamp, Uint128, Uint64};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, Decimal256, StdResult, Timestamp,
+    Uint128, Uint64, WasmMsg,
+};
+use cw_utils::Expiration;
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -12,16 +20,34 @@ pub struct InstantiateMsg {
     pub stream_creation_denom: String,
     /// Stream creation fee amount
     pub stream_creation_fee: Uint128,
+    /// Alternative creation fees, any one of them satisfies the creation fee instead of
+    /// `stream_creation_fee` in `stream_creation_denom`.
+    #[serde(default)]
+    pub stream_creation_fee_options: Vec<Coin>,
     /// in/buy token exit fee in percent
     pub exit_fee_percent: Decimal,
-    /// Address of the fee collector
-    pub fee_collector: String,
+    /// Exit fee tiers lowering `exit_fee_percent` for positions with larger spend.
+    #[serde(default)]
+    pub exit_fee_tiers: Vec<ExitFeeTier>,
+    /// Side of the swap the exit fee is charged on.
+    #[serde(default)]
+    pub fee_side: FeeSide,
+    /// Fee collectors sharing the collected fees by weight.
+    pub fee_collectors: Vec<FeeCollectorMsg>,
     /// protocol admin can pause streams in case of emergency.
     pub protocol_admin: String,
     /// Accepted in_denom to buy out_tokens
     pub accepted_in_denom: String,
 }
 
+#[cw_serde]
+pub struct FeeCollectorMsg {
+    /// Address receiving its share of the fees.
+    pub address: String,
+    /// Share of the fees, weights of all collectors must sum up to one.
+    pub weight: Decimal,
+}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     /// CreateStream creates new token stream. Anyone can create a new stream.
@@ -46,7 +72,7 @@ pub enum ExecuteMsg {
         /// Unix timestamp when the stream ends. Calculations in nano sec precision.
         end_time: Timestamp,
         /// Minimum amount of `spent_in` for a stream to be finalized.
-        threshold: Option<Uint128>,
+        threshold: Option<Threshold>,
     },
     /// Update stream and calculates distribution state.
     UpdateStream {
@@ -56,11 +82,41 @@ pub enum ExecuteMsg {
     UpdateProtocolAdmin {
         new_protocol_admin: String,
     },
-    /// UpdateOperator updates the operator of the position.
+    /// UpdateOperator replaces all operators of the position with `new_operator` granted every
+    /// permission without expiration, or removes them if `None`.
     UpdateOperator {
         stream_id: u64,
         new_operator: Option<String>,
     },
+    /// Grants `operator` scoped permissions on the sender's position until `expires`,
+    /// replacing any previous grant of the operator.
+    GrantOperator {
+        stream_id: u64,
+        operator: String,
+        permissions: Vec<OperatorPermission>,
+        expires: Option<Expiration>,
+    },
+    /// Revokes the grant of `operator` on the sender's position.
+    RevokeOperator {
+        stream_id: u64,
+        operator: String,
+    },
+    /// Approves `operator` with full control over all current and future positions of the
+    /// sender until `expires`.
+    ApproveOperator {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    /// Revokes the account level approval of `operator`.
+    RevokeOperatorApproval {
+        operator: String,
+    },
+    /// Transfers the sender's position to `recipient`, merging it into the recipient's
+    /// position if there is one. Operators of the transferred position are removed.
+    TransferPosition {
+        stream_id: u64,
+        recipient: String,
+    },
     /// Subscribe to a token stream. Any use at any time before the stream end can join
     /// the stream by sending `token_in` to the Stream through the Subscribe msg.
     /// During the stream, user `token_in` will be automatically charged every
@@ -69,7 +125,7 @@ pub enum ExecuteMsg {
         stream_id: u64,
         /// operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
-        /// operator can subscribe/withdraw/update position.
+        /// operator is granted every permission on a new position.
         operator: Option<String>,
     },
     /// Withdraw unspent tokens in balance.
@@ -78,6 +134,17 @@ pub enum ExecuteMsg {
         cap: Option<Uint128>,
         /// operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
+        /// recipient of the withdrawn tokens, defaults to the position owner.
+        recipient: Option<String>,
+    },
+    /// ClaimPurchased sends out tokens purchased so far during an active stream, up to `cap`.
+    /// Claimed tokens are deducted from the exit payout. Not available until the stream threshold,
+    /// if any, can no longer be missed.
+    ClaimPurchased {
+        stream_id: u64,
+        cap: Option<Uint128>,
+        /// operator_target is the address of operator targets to execute on behalf of the user.
+        operator_target: Option<String>,
     },
     /// UpdatePosition updates the position of the user.
     /// syncs position index to the current state of the stream.
@@ -100,6 +167,8 @@ pub enum ExecuteMsg {
         stream_id: u64,
         /// operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
+        /// recipient of the purchased and unspent tokens, defaults to the position owner.
+        recipient: Option<String>,
     },
     //
     // Killswitch features
@@ -114,25 +183,54 @@ pub enum ExecuteMsg {
         cap: Option<Uint128>,
         // operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
+        /// recipient of the withdrawn tokens, defaults to the position owner.
+        recipient: Option<String>,
     },
     /// ExitCancelled returns the whole balance user put in the stream, both spent and unspent.
     ExitCancelled {
         stream_id: u64,
         /// operator_target is the address of operator targets to execute on behalf of the user.
         operator_target: Option<String>,
+        /// recipient of the refunded tokens, defaults to the position owner.
+        recipient: Option<String>,
     },
     CancelStreamWithThreshold {
         stream_id: u64,
     },
+    /// Refunds up to `limit` positions of a cancelled stream or an ended stream with an unmet
+    /// threshold, same as `ExitCancelled` for each owner. Permissionless, refunded positions are
+    /// removed so it can be called repeatedly until every position is refunded.
+    ProcessRefunds {
+        stream_id: u64,
+        limit: Option<u32>,
+    },
+    /// Exits up to `limit` positions of an ended stream, sending purchased and unspent tokens to
+    /// the owners. Permissionless, the sender gets `keeper_reward_percent` of the exit fees of
+    /// processed positions not yet accrued to fee collectors.
+    DistributeExits {
+        stream_id: u64,
+        limit: Option<u32>,
+    },
+    /// Sets, changes or removes the stream threshold. Callable by the treasury while the stream
+    /// is waiting, once started the threshold can only be lowered.
+    UpdateThreshold {
+        stream_id: u64,
+        threshold: Option<Threshold>,
+    },
 
     UpdateConfig {
         min_stream_duration: Option<Uint64>,
         min_duration_until_start_time: Option<Uint64>,
         stream_creation_denom: Option<String>,
         stream_creation_fee: Option<Uint128>,
-        fee_collector: Option<String>,
+        stream_creation_fee_options: Option<Vec<Coin>>,
+        fee_collectors: Option<Vec<FeeCollectorMsg>>,
         accepted_in_denom: Option<String>,
         exit_fee_percent: Option<Decimal>,
+        exit_fee_tiers: Option<Vec<ExitFeeTier>>,
+        fee_side: Option<FeeSide>,
+        keeper_reward_percent: Option<Decimal>,
+        position_nfts: Option<bool>,
     },
     ResumeStream {
         stream_id: u64,
@@ -140,6 +238,96 @@ pub enum ExecuteMsg {
     CancelStream {
         stream_id: u64,
     },
+    /// Sets or removes (`None`) the fee discount of an address. Protocol admin only.
+    SetFeeDiscount {
+        address: String,
+        discount: Option<FeeDiscount>,
+    },
+    /// Replaces the fee discounts granted to holders of a denom. Protocol admin only.
+    SetHolderFeeDiscounts {
+        discounts: Vec<HolderFeeDiscount>,
+    },
+    /// Claims fees accrued to `collector` (defaults to the sender) and sends them to `recipient`
+    /// (defaults to the collector). Callable by the collector or the protocol admin, only the
+    /// collector can set a `recipient` other than itself.
+    ClaimFees {
+        collector: Option<String>,
+        recipient: Option<String>,
+    },
+    /// Registers `contract` to receive lifecycle callbacks of the stream, or of all streams if
+    /// `stream_id` is `None`. Treasury of the stream or protocol admin only.
+    AddHook {
+        stream_id: Option<u64>,
+        contract: String,
+    },
+    /// Removes a hook registered with `AddHook`.
+    RemoveHook {
+        stream_id: Option<u64>,
+        contract: String,
+    },
+    //
+    // cw721 interface of position tokens
+    //
+    /// Transfers the position of the token to `recipient`, same as `TransferPosition`.
+    /// Callable by the owner, an approved spender or an approved operator.
+    TransferNft {
+        recipient: String,
+        token_id: String,
+    },
+    /// Transfers the position of the token to `contract` and calls its `ReceiveNft` hook.
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    /// Allows `spender` to transfer the token until `expires`.
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    /// Removes the approval of `spender` on the token.
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+    /// Allows `operator` to transfer, approve and burn all tokens of the sender until `expires`.
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    /// Removes the approval of `operator` on all tokens of the sender.
+    RevokeAll {
+        operator: String,
+    },
+    /// Burns the token, the position is kept and managed without it.
+    Burn {
+        token_id: String,
+    },
+}
+
+/// Hook called on the receiving contract of `SendNft`.
+#[cw_serde]
+pub struct Cw721ReceiveMsg {
+    pub sender: String,
+    pub token_id: String,
+    pub msg: Binary,
+}
+
+impl Cw721ReceiveMsg {
+    pub fn into_cosmos_msg(self, contract_addr: String) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr,
+            msg: to_json_binary(&ReceiverExecuteMsg::ReceiveNft(self))?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+#[cw_serde]
+enum ReceiverExecuteMsg {
+    ReceiveNft(Cw721ReceiveMsg),
 }
 
 #[cw_serde]
@@ -167,14 +355,267 @@ pub enum QueryMsg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Returns operators granted scoped permissions on a position.
+    #[returns(PositionOperatorsResponse)]
+    PositionOperators { stream_id: u64, owner: String },
+    /// Returns operators approved on all positions of an owner.
+    #[returns(AccountOperatorsResponse)]
+    AccountOperators { owner: String },
     /// Returns average price of a stream sale.
     #[returns(AveragePriceResponse)]
     AveragePrice { stream_id: u64 },
     /// Returns currently streaming price of a sale.
     #[returns(LatestStreamedPriceResponse)]
     LastStreamedPrice { stream_id: u64 },
-    #[returns(Uint128)]
+    /// Returns the amount of `token_in` committed to a stream before its start.
+    #[returns(PreCommittedResponse)]
+    PreCommitted { stream_id: u64 },
+    /// Returns the stream threshold and the current progress towards it, `None` if no threshold is set.
+    #[returns(Option<ThresholdStatus>)]
     Threshold { stream_id: u64 },
+    /// Returns an estimate of a subscription of `amount` made at `at_time` (defaults to the
+    /// current block time), assuming no further subscriptions or withdrawals until the stream ends.
+    #[returns(EstimateSubscriptionResponse)]
+    EstimateSubscription {
+        stream_id: u64,
+        amount: Uint128,
+        at_time: Option<Timestamp>,
+    },
+    /// Returns the payouts a position would receive by exiting the stream at the current block.
+    #[returns(ExitPreviewResponse)]
+    ExitPreview { stream_id: u64, owner: String },
+    /// Returns the payouts the treasury would trigger by finalizing the stream at the current block.
+    #[returns(FinalizePreviewResponse)]
+    FinalizePreview { stream_id: u64 },
+    /// Returns protocol wide statistics.
+    #[returns(ProtocolStats)]
+    Stats {},
+    /// Returns streams by ids in the given order, missing streams are returned as `None`.
+    #[returns(StreamsByIdsResponse)]
+    StreamsByIds { ids: Vec<u64> },
+    /// Returns positions by `(stream_id, owner)` pairs in the given order, missing positions
+    /// are returned as `None`.
+    #[returns(PositionsBatchResponse)]
+    PositionsBatch { pairs: Vec<(u64, String)> },
+    /// Returns the fee discount a stream created by `address` would currently get.
+    #[returns(FeeDiscountResponse)]
+    FeeDiscount { address: String },
+    /// Returns the fee discounts granted to holders of a denom.
+    #[returns(HolderFeeDiscountsResponse)]
+    HolderFeeDiscounts {},
+    /// Returns fees accrued to a collector and not claimed yet.
+    #[returns(UnclaimedFeesResponse)]
+    UnclaimedFees { collector: String },
+    /// Returns hooks of the stream, or hooks of all streams if `stream_id` is `None`.
+    #[returns(HooksResponse)]
+    Hooks { stream_id: Option<u64> },
+    //
+    // cw721 interface of position tokens
+    //
+    /// Returns the owner of the token and its approvals.
+    #[returns(OwnerOfResponse)]
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    /// Returns the approval of `spender` on the token.
+    #[returns(ApprovalResponse)]
+    Approval {
+        token_id: String,
+        spender: String,
+        include_expired: Option<bool>,
+    },
+    /// Returns all approvals of the token.
+    #[returns(ApprovalsResponse)]
+    Approvals {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    /// Returns the approval of `operator` on all tokens of `owner`.
+    #[returns(OperatorResponse)]
+    Operator {
+        owner: String,
+        operator: String,
+        include_expired: Option<bool>,
+    },
+    /// Returns operators approved on all tokens of `owner`.
+    #[returns(OperatorsResponse)]
+    AllOperators {
+        owner: String,
+        include_expired: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the number of position tokens.
+    #[returns(NumTokensResponse)]
+    NumTokens {},
+    /// Returns the collection name and symbol.
+    #[returns(ContractInfoResponse)]
+    ContractInfo {},
+    /// Returns the token metadata, the extension is the position.
+    #[returns(NftInfoResponse)]
+    NftInfo { token_id: String },
+    /// Returns the owner and metadata of the token.
+    #[returns(AllNftInfoResponse)]
+    AllNftInfo {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    /// Returns tokens of `owner` paginated by `start_after` and `limit`.
+    #[returns(TokensResponse)]
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns all tokens paginated by `start_after` and `limit`.
+    #[returns(TokensResponse)]
+    AllTokens {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the minter of the tokens, this contract.
+    #[returns(MinterResponse)]
+    Minter {},
+}
+
+#[cw_serde]
+pub struct Approval {
+    pub spender: String,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct OwnerOfResponse {
+    pub owner: String,
+    pub approvals: Vec<Approval>,
+}
+
+#[cw_serde]
+pub struct ApprovalResponse {
+    pub approval: Approval,
+}
+
+#[cw_serde]
+pub struct ApprovalsResponse {
+    pub approvals: Vec<Approval>,
+}
+
+#[cw_serde]
+pub struct OperatorResponse {
+    pub approval: Approval,
+}
+
+#[cw_serde]
+pub struct OperatorsResponse {
+    pub operators: Vec<Approval>,
+}
+
+#[cw_serde]
+pub struct NumTokensResponse {
+    pub count: u64,
+}
+
+#[cw_serde]
+pub struct ContractInfoResponse {
+    pub name: String,
+    pub symbol: String,
+}
+
+#[cw_serde]
+pub struct NftInfoResponse {
+    pub token_uri: Option<String>,
+    pub extension: PositionResponse,
+}
+
+#[cw_serde]
+pub struct AllNftInfoResponse {
+    pub access: OwnerOfResponse,
+    pub info: NftInfoResponse,
+}
+
+#[cw_serde]
+pub struct TokensResponse {
+    pub tokens: Vec<String>,
+}
+
+#[cw_serde]
+pub struct MinterResponse {
+    pub minter: Option<String>,
+}
+
+#[cw_serde]
+pub struct HooksResponse {
+    pub hooks: Vec<String>,
+}
+
+/// Callback executed on hook contracts, receivers implement `StreamHook(StreamHookEvent)`.
+#[cw_serde]
+pub enum StreamHookMsg {
+    StreamHook(StreamHookEvent),
+}
+
+#[cw_serde]
+pub enum StreamHookEvent {
+    Create {
+        stream_id: u64,
+    },
+    Subscribe {
+        stream_id: u64,
+        owner: String,
+        in_amount: Uint128,
+    },
+    Withdraw {
+        stream_id: u64,
+        owner: String,
+        amount: Uint128,
+    },
+    Finalize {
+        stream_id: u64,
+    },
+    Pause {
+        stream_id: u64,
+    },
+    Resume {
+        stream_id: u64,
+    },
+    Cancel {
+        stream_id: u64,
+    },
+}
+
+#[cw_serde]
+pub struct UnclaimedFeesResponse {
+    pub collector: String,
+    pub fees: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct FeeDiscountResponse {
+    pub discount: Option<FeeDiscount>,
+}
+
+#[cw_serde]
+pub struct HolderFeeDiscountsResponse {
+    pub discounts: Vec<HolderFeeDiscount>,
+}
+
+#[cw_serde]
+pub struct ThresholdStatus {
+    pub threshold: Threshold,
+    /// Current value of the stream measured in the same kind as `threshold`.
+    pub current: Threshold,
+    /// Current amount of `token_in` spent.
+    pub spent_in: Uint128,
+    /// Ratio of `current` to `threshold`, one meaning reached. Can exceed one.
+    pub percent_reached: Decimal,
+    /// Value of the stream at `end_time` if the current `in_supply` keeps streaming.
+    pub projected: Threshold,
+    /// Amount of `token_in` spent at `end_time` if the current `in_supply` keeps streaming.
+    pub projected_spent_in: Uint128,
+    pub reached: bool,
+    /// True if the threshold can no longer be missed.
+    pub guaranteed: bool,
 }
 
 #[cw_serde]
@@ -189,12 +630,22 @@ pub struct ConfigResponse {
     pub stream_creation_denom: String,
     /// Creation fee amount.
     pub stream_creation_fee: Uint128,
+    /// Alternative creation fees accepted instead of `stream_creation_fee`.
+    pub stream_creation_fee_options: Vec<Coin>,
     /// This percentage represents the fee that will be collected from the investors.
     pub exit_fee_percent: Decimal,
-    /// Address of the fee collector.
-    pub fee_collector: String,
+    /// Exit fee tiers lowering `exit_fee_percent` for positions with larger spend.
+    pub exit_fee_tiers: Vec<ExitFeeTier>,
+    /// Side of the swap the exit fee is charged on.
+    pub fee_side: FeeSide,
+    /// Fee collectors sharing the collected fees by weight.
+    pub fee_collectors: Vec<FeeCollector>,
     /// Address of the protocol admin.
     pub protocol_admin: String,
+    /// Share of the exit fees paid to keepers running `DistributeExits`.
+    pub keeper_reward_percent: Decimal,
+    /// Whether new positions are minted as cw721 tokens.
+    pub position_nfts: bool,
 }
 
 #[cw_serde]
@@ -234,8 +685,18 @@ pub struct StreamResponse {
     pub pause_date: Option<Timestamp>,
     /// Exit fee percent.
     pub exit_fee_percent: Decimal,
+    /// Exit fee tiers.
+    pub exit_fee_tiers: Vec<ExitFeeTier>,
+    /// Denom the creation fee is paid in.
+    pub stream_creation_denom: String,
     /// Creation fee amount.
     pub stream_creation_fee: Uint128,
+    /// Fee discount granted to the creator, already applied to the fees.
+    pub fee_discount: Option<FeeDiscount>,
+    /// Side of the swap the exit fee is charged on.
+    pub fee_side: FeeSide,
+    /// Number of positions holding shares.
+    pub subscribers: u64,
 }
 
 #[cw_serde]
@@ -260,8 +721,35 @@ pub struct PositionResponse {
     pub pending_purchase: Decimal256,
     // total amount of `token_in` spent tokens at latest calculation
     pub spent: Uint128,
-    // operator can update position
+    // operator with full control of the position, set on subscription or with `UpdateOperator`
     pub operator: Option<Addr>,
+    // total amount of `token_out` claimed before exit
+    pub claimed: Uint128,
+    // cw721 token representing the position, if minted
+    pub token_id: Option<String>,
+}
+
+#[cw_serde]
+pub struct PositionOperatorResponse {
+    pub operator: String,
+    pub permissions: Vec<OperatorPermission>,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct PositionOperatorsResponse {
+    pub operators: Vec<PositionOperatorResponse>,
+}
+
+#[cw_serde]
+pub struct AccountOperatorResponse {
+    pub operator: String,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct AccountOperatorsResponse {
+    pub operators: Vec<AccountOperatorResponse>,
 }
 
 #[cw_serde]
@@ -269,16 +757,108 @@ pub struct PositionsResponse {
     pub positions: Vec<PositionResponse>,
 }
 
+#[cw_serde]
+pub struct StreamResult {
+    pub id: u64,
+    /// `None` if the stream does not exist.
+    pub stream: Option<StreamResponse>,
+}
+
+#[cw_serde]
+pub struct StreamsByIdsResponse {
+    pub streams: Vec<StreamResult>,
+}
+
+#[cw_serde]
+pub struct PositionResult {
+    pub stream_id: u64,
+    pub owner: String,
+    /// `None` if the position does not exist or the owner is not a valid address.
+    pub position: Option<PositionResponse>,
+}
+
+#[cw_serde]
+pub struct PositionsBatchResponse {
+    pub positions: Vec<PositionResult>,
+}
+
 #[cw_serde]
 pub struct AveragePriceResponse {
     pub average_price: Decimal,
 }
 
+#[cw_serde]
+pub struct PreCommittedResponse {
+    pub in_denom: String,
+    /// Total amount of `token_in` committed before `start_time`, net of withdrawals before start.
+    /// It is kept as is once the stream starts.
+    pub pre_committed_in: Uint128,
+    pub start_time: Timestamp,
+}
+
 #[cw_serde]
 pub struct LatestStreamedPriceResponse {
     pub current_streamed_price: Decimal,
 }
 
+#[cw_serde]
+pub struct EstimateSubscriptionResponse {
+    /// Shares that would be minted for the subscription.
+    pub shares: Uint128,
+    /// Projected amount of `token_out` purchased by the end of the stream.
+    pub expected_out: Uint128,
+    /// Projected amount of `token_in` spent by the end of the stream.
+    pub expected_spent: Uint128,
+    /// Implied average price of the subscription, `expected_spent / expected_out`.
+    pub implied_price: Decimal,
+    /// Exit fee charged on `expected_spent` under the stream's exit fee percent.
+    pub exit_fee: Uint128,
+    /// Exit fee charged on `expected_out` when the stream charges fees in `token_out`,
+    /// `expected_out` does not include it.
+    pub exit_fee_out: Uint128,
+}
+
+#[cw_serde]
+pub struct ExitPreviewResponse {
+    /// Amount of `token_out` sent to the position owner.
+    pub purchased: Uint128,
+    /// Amount of `token_in` refunded to the position owner, including the unspent balance and
+    /// the rebate of a lower exit fee tier.
+    pub refund: Uint128,
+    /// Amount of spent `token_in` sent to the treasury on cancelled exits, paying for the
+    /// `token_out` the position claimed.
+    pub treasury_refund: Uint128,
+    /// Swap fee charged on the spent `token_in` of the position.
+    pub swap_fee: Uint128,
+    /// Swap fee charged on the purchased `token_out` of the position, `purchased` does not include it.
+    pub swap_fee_out: Uint128,
+    /// True if the position exits through `ExitCancelled`, refunding spent and unspent `token_in`.
+    pub cancelled: bool,
+    /// True if the stream ended without reaching its threshold.
+    pub threshold_not_reached: bool,
+}
+
+#[cw_serde]
+pub struct FinalizePreviewResponse {
+    /// Address of the treasury receiving the creator revenue.
+    pub treasury: String,
+    /// Amount of `token_in` sent to the treasury.
+    pub creator_revenue: Uint128,
+    /// Swap fee in `token_in` sent to the fee collector.
+    pub swap_fee: Uint128,
+    /// Fee collectors sharing the swap fee and the creation fee by weight.
+    pub fee_collectors: Vec<FeeCollector>,
+    /// Creation fee paid at stream creation, sent to the fee collector.
+    pub creation_fee: Coin,
+    /// Amount of unsold `token_out` refunded to the treasury.
+    pub refunded_out_remaining: Uint128,
+    /// Total amount of `token_out` sold.
+    pub total_sold: Uint128,
+    /// True if the stream threshold is not reached, in which case finalize fails and the
+    /// treasury should cancel the stream with `CancelStreamWithThreshold`.
+    pub threshold_not_reached: bool,
+}
+
 #[cw_serde]
 pub enum SudoMsg {
     PauseStream { stream_id: u64 },
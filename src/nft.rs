@@ -0,0 +1,492 @@
+use crate::contract::{query_position, transfer_position, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::events::{
+    ApproveAllEvent, ApproveEvent, BurnEvent, RevokeAllEvent, RevokeEvent, TransferPositionEvent,
+};
+use crate::msg::{
+    AllNftInfoResponse, Approval, ApprovalResponse, ApprovalsResponse, ContractInfoResponse,
+    Cw721ReceiveMsg, MinterResponse, NftInfoResponse, NumTokensResponse, OperatorResponse,
+    OperatorsResponse, OwnerOfResponse, TokensResponse,
+};
+use crate::state::{
+    burn_position_token, PositionToken, TokenApproval, NFT_OPERATORS, OWNER_TOKENS, POSITIONS,
+    POSITION_TOKENS, TOKEN_COUNT,
+};
+use crate::ContractError;
+use cosmwasm_std::{
+    Addr, Binary, BlockInfo, Deps, DepsMut, Env, Event, MessageInfo, Order, Response, StdError,
+    StdResult, Storage,
+};
+use cw_storage_plus::Bound;
+use cw_utils::{maybe_addr, Expiration};
+
+const COLLECTION_NAME: &str = "StreamSwap Positions";
+const COLLECTION_SYMBOL: &str = "SSP";
+
+pub fn execute_transfer_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let token = check_can_send(deps.storage, &env.block, &info.sender, &token_id)?;
+    check_nft_recipient(deps.storage, &token, &recipient)?;
+    let (stream, position, merged) = transfer_position(
+        deps.storage,
+        env.block.time,
+        token.stream_id,
+        &token.owner,
+        &recipient,
+    )?;
+    let event: Event = TransferPositionEvent {
+        stream_id: token.stream_id,
+        stream: &stream,
+        owner: &token.owner,
+        position: &position,
+        merged,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "transfer_nft")
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("token_id", token_id)
+        .add_attribute("stream_id", token.stream_id.to_string()))
+}
+
+pub fn execute_send_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+    token_id: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    let contract = deps.api.addr_validate(&contract)?;
+    let token = check_can_send(deps.storage, &env.block, &info.sender, &token_id)?;
+    check_nft_recipient(deps.storage, &token, &contract)?;
+    let (stream, position, merged) = transfer_position(
+        deps.storage,
+        env.block.time,
+        token.stream_id,
+        &token.owner,
+        &contract,
+    )?;
+    let event: Event = TransferPositionEvent {
+        stream_id: token.stream_id,
+        stream: &stream,
+        owner: &token.owner,
+        position: &position,
+        merged,
+    }
+    .into();
+
+    let receive = Cw721ReceiveMsg {
+        sender: info.sender.to_string(),
+        token_id: token_id.clone(),
+        msg,
+    };
+    Ok(Response::new()
+        .add_message(receive.into_cosmos_msg(contract.to_string())?)
+        .add_event(event)
+        .add_attribute("action", "send_nft")
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", contract)
+        .add_attribute("token_id", token_id)
+        .add_attribute("stream_id", token.stream_id.to_string()))
+}
+
+pub fn execute_approve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    token_id: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let spender = deps.api.addr_validate(&spender)?;
+    let mut token = check_can_approve(deps.storage, &env.block, &info.sender, &token_id)?;
+    if spender == token.owner {
+        return Err(ContractError::InvalidOperator {});
+    }
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+    token
+        .approvals
+        .retain(|approval| approval.spender != spender);
+    token.approvals.push(TokenApproval {
+        spender: spender.clone(),
+        expires,
+    });
+    POSITION_TOKENS.save(deps.storage, &token_id, &token)?;
+    let event: Event = ApproveEvent {
+        stream_id: token.stream_id,
+        owner: &token.owner,
+        spender: &spender,
+        token_id: &token_id,
+        expires: &expires,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "approve")
+        .add_attribute("sender", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("token_id", token_id)
+        .add_attribute("expires", expires.to_string()))
+}
+
+pub fn execute_revoke(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let spender = deps.api.addr_validate(&spender)?;
+    let mut token = check_can_approve(deps.storage, &env.block, &info.sender, &token_id)?;
+    token
+        .approvals
+        .retain(|approval| approval.spender != spender);
+    POSITION_TOKENS.save(deps.storage, &token_id, &token)?;
+    let event: Event = RevokeEvent {
+        stream_id: token.stream_id,
+        owner: &token.owner,
+        spender: &spender,
+        token_id: &token_id,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "revoke")
+        .add_attribute("sender", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("token_id", token_id))
+}
+
+// approves an operator on all tokens of the sender, it gives no access to the positions
+pub fn execute_approve_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    if operator == info.sender {
+        return Err(ContractError::InvalidOperator {});
+    }
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+    NFT_OPERATORS.save(deps.storage, (&info.sender, &operator), &expires)?;
+    let event: Event = ApproveAllEvent {
+        owner: &info.sender,
+        operator: &operator,
+        expires: &expires,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "approve_all")
+        .add_attribute("sender", info.sender)
+        .add_attribute("operator", operator)
+        .add_attribute("expires", expires.to_string()))
+}
+
+pub fn execute_revoke_all(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: String,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    if !NFT_OPERATORS.has(deps.storage, (&info.sender, &operator)) {
+        return Err(ContractError::Unauthorized {});
+    }
+    NFT_OPERATORS.remove(deps.storage, (&info.sender, &operator));
+    let event: Event = RevokeAllEvent {
+        owner: &info.sender,
+        operator: &operator,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "revoke_all")
+        .add_attribute("sender", info.sender)
+        .add_attribute("operator", operator))
+}
+
+// burns the token only, the position stays with its owner
+pub fn execute_burn(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let token = check_can_send(deps.storage, &env.block, &info.sender, &token_id)?;
+    let mut position = POSITIONS.load(deps.storage, (token.stream_id, &token.owner))?;
+    burn_position_token(deps.storage, &position)?;
+    position.token_id = None;
+    POSITIONS.save(deps.storage, (token.stream_id, &token.owner), &position)?;
+    let event: Event = BurnEvent {
+        stream_id: token.stream_id,
+        owner: &token.owner,
+        token_id: &token_id,
+    }
+    .into();
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "burn")
+        .add_attribute("sender", info.sender)
+        .add_attribute("token_id", token_id)
+        .add_attribute("stream_id", token.stream_id.to_string()))
+}
+
+// tokens are moved as is, merging into an existing position would burn the sent token
+fn check_nft_recipient(
+    storage: &dyn Storage,
+    token: &PositionToken,
+    recipient: &Addr,
+) -> Result<(), ContractError> {
+    if POSITIONS.has(storage, (token.stream_id, recipient)) {
+        return Err(ContractError::PositionExists {});
+    }
+    Ok(())
+}
+
+// checks if the sender is the token owner or a cw721 operator approved by the owner
+fn check_can_approve(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    sender: &Addr,
+    token_id: &str,
+) -> Result<PositionToken, ContractError> {
+    let token = POSITION_TOKENS.load(storage, token_id)?;
+    if token.owner == sender {
+        return Ok(token);
+    }
+    match NFT_OPERATORS.may_load(storage, (&token.owner, sender))? {
+        Some(expires) if !expires.is_expired(block) => Ok(token),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+// checks if the sender can transfer the token, approved spenders included
+fn check_can_send(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    sender: &Addr,
+    token_id: &str,
+) -> Result<PositionToken, ContractError> {
+    let token = POSITION_TOKENS.load(storage, token_id)?;
+    if token
+        .approvals
+        .iter()
+        .any(|approval| approval.spender == sender && !approval.expires.is_expired(block))
+    {
+        return Ok(token);
+    }
+    check_can_approve(storage, block, sender, token_id)
+}
+
+fn humanize_approvals(
+    block: &BlockInfo,
+    token: &PositionToken,
+    include_expired: bool,
+) -> Vec<Approval> {
+    token
+        .approvals
+        .iter()
+        .filter(|approval| include_expired || !approval.expires.is_expired(block))
+        .map(|approval| Approval {
+            spender: approval.spender.to_string(),
+            expires: approval.expires,
+        })
+        .collect()
+}
+
+pub fn query_owner_of(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    include_expired: bool,
+) -> StdResult<OwnerOfResponse> {
+    let token = POSITION_TOKENS.load(deps.storage, &token_id)?;
+    Ok(OwnerOfResponse {
+        owner: token.owner.to_string(),
+        approvals: humanize_approvals(&env.block, &token, include_expired),
+    })
+}
+
+pub fn query_approval(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    spender: String,
+    include_expired: bool,
+) -> StdResult<ApprovalResponse> {
+    let token = POSITION_TOKENS.load(deps.storage, &token_id)?;
+    humanize_approvals(&env.block, &token, include_expired)
+        .into_iter()
+        .find(|approval| approval.spender == spender)
+        .map(|approval| ApprovalResponse { approval })
+        .ok_or_else(|| StdError::not_found("Approval"))
+}
+
+pub fn query_approvals(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    include_expired: bool,
+) -> StdResult<ApprovalsResponse> {
+    let token = POSITION_TOKENS.load(deps.storage, &token_id)?;
+    Ok(ApprovalsResponse {
+        approvals: humanize_approvals(&env.block, &token, include_expired),
+    })
+}
+
+pub fn query_operator(
+    deps: Deps,
+    env: Env,
+    owner: String,
+    operator: String,
+    include_expired: bool,
+) -> StdResult<OperatorResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let operator = deps.api.addr_validate(&operator)?;
+    match NFT_OPERATORS.may_load(deps.storage, (&owner, &operator))? {
+        Some(expires) if include_expired || !expires.is_expired(&env.block) => {
+            Ok(OperatorResponse {
+                approval: Approval {
+                    spender: operator.to_string(),
+                    expires,
+                },
+            })
+        }
+        _ => Err(StdError::not_found("Approval")),
+    }
+}
+
+pub fn query_all_operators(
+    deps: Deps,
+    env: Env,
+    owner: String,
+    include_expired: bool,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OperatorsResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let start_after = maybe_addr(deps.api, start_after)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let operators = NFT_OPERATORS
+        .prefix(&owner)
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, expires)| {
+                include_expired || !expires.is_expired(&env.block)
+            })
+        })
+        .take(limit)
+        .map(|item| {
+            let (operator, expires) = item?;
+            Ok(Approval {
+                spender: operator.to_string(),
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(OperatorsResponse { operators })
+}
+
+pub fn query_num_tokens(deps: Deps) -> StdResult<NumTokensResponse> {
+    let count = TOKEN_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    Ok(NumTokensResponse { count })
+}
+
+pub fn query_contract_info() -> ContractInfoResponse {
+    ContractInfoResponse {
+        name: COLLECTION_NAME.to_string(),
+        symbol: COLLECTION_SYMBOL.to_string(),
+    }
+}
+
+pub fn query_nft_info(deps: Deps, env: Env, token_id: String) -> StdResult<NftInfoResponse> {
+    let token = POSITION_TOKENS.load(deps.storage, &token_id)?;
+    Ok(NftInfoResponse {
+        token_uri: None,
+        extension: query_position(deps, env, token.stream_id, token.owner.to_string())?,
+    })
+}
+
+pub fn query_all_nft_info(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    include_expired: bool,
+) -> StdResult<AllNftInfoResponse> {
+    Ok(AllNftInfoResponse {
+        access: query_owner_of(deps, env.clone(), token_id.clone(), include_expired)?,
+        info: query_nft_info(deps, env, token_id)?,
+    })
+}
+
+pub fn query_tokens(
+    deps: Deps,
+    owner: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let tokens = OWNER_TOKENS
+        .prefix(&owner)
+        .keys(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(TokensResponse { tokens })
+}
+
+pub fn query_all_tokens(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let tokens = POSITION_TOKENS
+        .keys(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(TokensResponse { tokens })
+}
+
+pub fn query_minter(env: Env) -> MinterResponse {
+    MinterResponse {
+        minter: Some(env.contract.address.to_string()),
+    }
+}
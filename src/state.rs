@@ -1,7 +1,11 @@
 use crate::ContractError;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Decimal, Decimal256, Storage, Timestamp, Uint128, Uint64};
+use cosmwasm_std::{
+    Addr, BlockInfo, Coin, Decimal, Decimal256, Empty, Order, StdResult, Storage, Timestamp,
+    Uint128, Uint64,
+};
 use cw_storage_plus::{Item, Map};
+use cw_utils::Expiration;
 use std::ops::Mul;
 
 #[cw_serde]
@@ -16,16 +20,139 @@ pub struct Config {
     pub stream_creation_denom: String,
     /// Stream creation fee amount
     pub stream_creation_fee: Uint128,
+    /// Alternative creation fees, any one of them satisfies the creation fee instead of
+    /// `stream_creation_fee` in `stream_creation_denom`.
+    #[serde(default)]
+    pub stream_creation_fee_options: Vec<Coin>,
     /// in/buy token exit fee in percent
     pub exit_fee_percent: Decimal,
-    /// Address of the fee collector
-    pub fee_collector: Addr,
+    /// Exit fee tiers lowering `exit_fee_percent` for positions with larger spend.
+    #[serde(default)]
+    pub exit_fee_tiers: Vec<ExitFeeTier>,
+    /// Side of the swap the exit fee is charged on.
+    #[serde(default)]
+    pub fee_side: FeeSide,
+    /// Fee collectors sharing the collected fees by weight.
+    pub fee_collectors: Vec<FeeCollector>,
     /// protocol admin can pause streams in case of emergency.
     pub protocol_admin: Addr,
+    /// Share of the exit fees of positions exited by `DistributeExits` paid to the keeper.
+    #[serde(default)]
+    pub keeper_reward_percent: Decimal,
+    /// Mints a cw721 token for every new position.
+    #[serde(default)]
+    pub position_nfts: bool,
+}
+
+impl Config {
+    // returns every accepted creation fee, the default one first
+    pub fn creation_fee_options(&self) -> Vec<Coin> {
+        let mut options = vec![Coin {
+            denom: self.stream_creation_denom.clone(),
+            amount: self.stream_creation_fee,
+        }];
+        options.extend(self.stream_creation_fee_options.iter().cloned());
+        options
+    }
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+#[cw_serde]
+pub struct FeeCollector {
+    /// Address receiving its share of the fees.
+    pub address: Addr,
+    /// Share of the fees, weights of all collectors sum up to one.
+    pub weight: Decimal,
+}
+
+#[cw_serde]
+pub struct FeeDiscount {
+    /// Share of the creation fee waived, between zero and one.
+    pub creation_fee_discount: Decimal,
+    /// Share of the exit fee waived, between zero and one.
+    pub exit_fee_discount: Decimal,
+}
+
+impl FeeDiscount {
+    // returns the creation fee amount left to pay
+    pub fn creation_fee(&self, amount: Uint128) -> StdResult<Uint128> {
+        let waived = Decimal::from_ratio(amount, Uint128::one())
+            .checked_mul(self.creation_fee_discount)?
+            * Uint128::one();
+        Ok(amount.checked_sub(waived)?)
+    }
+
+    // returns the exit fee percent left to pay
+    pub fn exit_fee_percent(&self, percent: Decimal) -> StdResult<Decimal> {
+        Ok(percent.checked_mul(Decimal::one().checked_sub(self.exit_fee_discount)?)?)
+    }
+}
+
+#[cw_serde]
+pub struct HolderFeeDiscount {
+    /// Denom the creator has to hold.
+    pub denom: String,
+    /// Minimum balance of `denom` to be granted the discount.
+    pub min_balance: Uint128,
+    pub discount: FeeDiscount,
+}
+
+/// Fee discounts granted to specific addresses, taking precedence over holder discounts.
+pub const ADDRESS_FEE_DISCOUNTS: Map<&Addr, FeeDiscount> = Map::new("address_fee_discounts");
+/// Fee discounts granted to holders of a denom, the first matching one applies.
+pub const HOLDER_FEE_DISCOUNTS: Item<Vec<HolderFeeDiscount>> = Item::new("holder_fee_discounts");
+
+/// Config layout with a single fee collector, used to migrate existing deployments.
+#[cw_serde]
+pub struct LegacyConfig {
+    pub min_stream_seconds: Uint64,
+    pub min_seconds_until_start_time: Uint64,
+    pub accepted_in_denom: String,
+    pub stream_creation_denom: String,
+    pub stream_creation_fee: Uint128,
+    pub exit_fee_percent: Decimal,
+    #[serde(default)]
+    pub exit_fee_tiers: Vec<ExitFeeTier>,
+    pub fee_collector: Addr,
+    pub protocol_admin: Addr,
+}
+
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+
+#[cw_serde]
+pub struct ExitFeeTier {
+    /// Minimum amount of `token_in` spent by a position for the tier to apply.
+    pub min_spent: Uint128,
+    /// Exit fee percent charged to positions in the tier.
+    pub fee_percent: Decimal,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum FeeSide {
+    /// Exit fee is charged on the spent `token_in`.
+    #[default]
+    In,
+    /// Exit fee is charged on the purchased `token_out` when positions exit.
+    Out,
+    /// `out_share` of the exit fee is charged on the purchased `token_out`, the rest on the spent `token_in`.
+    Both { out_share: Decimal },
+}
+
+impl FeeSide {
+    // splits an exit fee percent between the sides.
+    // returns the percent charged on token_in and the percent charged on token_out
+    pub fn split(&self, percent: Decimal) -> StdResult<(Decimal, Decimal)> {
+        let out_percent = match self {
+            FeeSide::In => Decimal::zero(),
+            FeeSide::Out => percent,
+            FeeSide::Both { out_share } => percent.checked_mul(*out_share)?,
+        };
+        Ok((percent.checked_sub(out_percent)?, out_percent))
+    }
+}
+
 #[cw_serde]
 pub struct Stream {
     /// Name of the stream.
@@ -68,6 +195,35 @@ pub struct Stream {
     pub stream_creation_fee: Uint128,
     /// Stream swap fee in percent. Saved under here to avoid any changes in config to efect existing streams.
     pub stream_exit_fee_percent: Decimal,
+    /// Stream swap fee tiers. Saved under here to avoid any changes in config to efect existing streams.
+    #[serde(default)]
+    pub stream_exit_fee_tiers: Vec<ExitFeeTier>,
+    /// Swap fees of positions exited before finalize when fee tiers are set, collected at finalize.
+    #[serde(default)]
+    pub exit_fees_accrued: Uint128,
+    /// Swap fees paid to keepers by `DistributeExits` before finalize, deducted from the finalize fee.
+    #[serde(default)]
+    pub keeper_rewards: Uint128,
+    /// Keeper share of the swap fee held back at finalize when fee tiers are not set, paid to
+    /// keepers exiting the remaining positions.
+    #[serde(default)]
+    pub keeper_reserve: Uint128,
+    /// Total amount of `token_out` claimed by positions with `ClaimPurchased`.
+    #[serde(default)]
+    pub out_claimed: Uint128,
+    /// Fee discount granted to the creator, already applied to the creation and exit fees above.
+    #[serde(default)]
+    pub fee_discount: Option<FeeDiscount>,
+    /// Side of the swap the exit fee is charged on. Saved under here to avoid any changes in config to efect existing streams.
+    #[serde(default)]
+    pub fee_side: FeeSide,
+    /// Number of positions holding shares. Exits after the end don't decrease it, so a threshold
+    /// reached at the end stays reached.
+    #[serde(default)]
+    pub subscribers: u64,
+    /// Total amount of `token_in` committed before `start_time`, net of withdrawals before start.
+    #[serde(default)]
+    pub pre_committed_in: Uint128,
 }
 
 #[cw_serde]
@@ -94,6 +250,7 @@ impl Stream {
         stream_creation_denom: String,
         stream_creation_fee: Uint128,
         stream_exit_fee_percent: Decimal,
+        stream_exit_fee_tiers: Vec<ExitFeeTier>,
     ) -> Self {
         Stream {
             name,
@@ -116,6 +273,15 @@ impl Stream {
             stream_creation_denom,
             stream_creation_fee,
             stream_exit_fee_percent,
+            stream_exit_fee_tiers,
+            exit_fees_accrued: Uint128::zero(),
+            keeper_rewards: Uint128::zero(),
+            keeper_reserve: Uint128::zero(),
+            out_claimed: Uint128::zero(),
+            fee_discount: None,
+            fee_side: FeeSide::In,
+            subscribers: 0,
+            pre_committed_in: Uint128::zero(),
         }
     }
 
@@ -133,6 +299,15 @@ impl Stream {
         shares
     }
 
+    // exit fee percent charged to a position, positions with larger spend can fall into lower tiers
+    pub fn exit_fee_percent_for(&self, spent: Uint128) -> Decimal {
+        self.stream_exit_fee_tiers
+            .iter()
+            .rev()
+            .find(|tier| spent >= tier.min_spent)
+            .map_or(self.stream_exit_fee_percent, |tier| tier.fee_percent)
+    }
+
     pub fn is_paused(&self) -> bool {
         self.status == Status::Paused
     }
@@ -144,6 +319,16 @@ impl Stream {
     pub fn is_killswitch_active(&self) -> bool {
         self.status == Status::Cancelled || self.status == Status::Paused
     }
+
+    // returns the status at `now`, a waiting stream is active from its start time
+    // even if no transaction has activated it yet
+    pub fn status_at(&self, now: Timestamp) -> Status {
+        if self.status == Status::Waiting && now >= self.start_time {
+            Status::Active
+        } else {
+            self.status.clone()
+        }
+    }
 }
 type StreamId = u64;
 pub const STREAMS: Map<StreamId, Stream> = Map::new("stream");
@@ -170,8 +355,14 @@ pub struct Position {
     pub pending_purchase: Decimal256,
     // total amount of `token_in` spent tokens at latest calculation
     pub spent: Uint128,
-    // operator can update position
+    // legacy operator with full control, migrated to a full-permission operator grant
     pub operator: Option<Addr>,
+    // total amount of `token_out` claimed before exit
+    #[serde(default)]
+    pub claimed: Uint128,
+    // cw721 token representing the position, if minted
+    #[serde(default)]
+    pub token_id: Option<String>,
 }
 
 impl Position {
@@ -181,7 +372,6 @@ impl Position {
         shares: Uint128,
         index: Option<Decimal256>,
         last_updated: Timestamp,
-        operator: Option<Addr>,
     ) -> Self {
         Position {
             owner,
@@ -192,10 +382,293 @@ impl Position {
             purchased: Uint128::zero(),
             pending_purchase: Decimal256::zero(),
             spent: Uint128::zero(),
-            operator,
+            operator: None,
+            claimed: Uint128::zero(),
+            token_id: None,
+        }
+    }
+
+    // returns the part of spent in tokens paying for claimed out tokens, it is not refunded on cancel
+    pub fn claimed_spent(&self) -> Uint128 {
+        if self.purchased.is_zero() {
+            Uint128::zero()
+        } else {
+            self.spent.multiply_ratio(self.claimed, self.purchased)
         }
     }
 }
 
 // Position (stream_id, owner_addr) -> Position
 pub const POSITIONS: Map<(StreamId, &Addr), Position> = Map::new("positions");
+
+#[cw_serde]
+pub enum OperatorPermission {
+    /// Add tokens to the position.
+    Subscribe,
+    /// Withdraw unspent tokens, including paused streams.
+    Withdraw,
+    /// Exit the position, claim purchased tokens or exit a cancelled stream.
+    Exit,
+    /// Sync the position to the stream.
+    Update,
+    /// Send withdrawn or exited tokens to a recipient other than the owner.
+    Redirect,
+}
+
+#[cw_serde]
+pub struct OperatorGrant {
+    /// Actions the operator can execute on behalf of the position owner.
+    pub permissions: Vec<OperatorPermission>,
+    /// The grant is not valid once expired.
+    pub expires: Expiration,
+}
+
+impl OperatorGrant {
+    // grant of every permission that never expires, given by `UpdateOperator`
+    pub fn full() -> Self {
+        OperatorGrant {
+            permissions: vec![
+                OperatorPermission::Subscribe,
+                OperatorPermission::Withdraw,
+                OperatorPermission::Exit,
+                OperatorPermission::Update,
+                OperatorPermission::Redirect,
+            ],
+            expires: Expiration::Never {},
+        }
+    }
+
+    // grants every permission without expiration, like the legacy position operator
+    pub fn is_full(&self) -> bool {
+        self.expires == Expiration::Never {}
+            && OperatorGrant::full()
+                .permissions
+                .iter()
+                .all(|permission| self.permissions.contains(permission))
+    }
+
+    pub fn allows(&self, permission: &OperatorPermission, block: &BlockInfo) -> bool {
+        self.permissions.contains(permission) && !self.expires.is_expired(block)
+    }
+}
+
+// (stream_id, owner, operator) -> scoped operator grant on the owner's position
+pub const POSITION_OPERATORS: Map<(StreamId, &Addr, &Addr), OperatorGrant> =
+    Map::new("position_operators");
+
+// (owner, operator) -> expiration of an operator approved on all positions of the owner
+pub const ACCOUNT_OPERATORS: Map<(&Addr, &Addr), Expiration> = Map::new("account_operators");
+
+// removes operator grants of a position, called when the position is removed
+pub fn clear_position_operators(
+    store: &mut dyn Storage,
+    stream_id: StreamId,
+    owner: &Addr,
+) -> StdResult<()> {
+    let operators = POSITION_OPERATORS
+        .prefix((stream_id, owner))
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for operator in operators {
+        POSITION_OPERATORS.remove(store, (stream_id, owner, &operator));
+    }
+    Ok(())
+}
+
+#[cw_serde]
+pub struct PositionToken {
+    pub stream_id: StreamId,
+    /// owner of the token, always the owner of the position.
+    pub owner: Addr,
+    /// Spenders approved to transfer the token, cleared on transfer.
+    pub approvals: Vec<TokenApproval>,
+}
+
+#[cw_serde]
+pub struct TokenApproval {
+    pub spender: Addr,
+    pub expires: Expiration,
+}
+
+// token_id -> cw721 token wrapping a position
+pub const POSITION_TOKENS: Map<&str, PositionToken> = Map::new("position_tokens");
+
+// (owner, token_id) -> tokens held by the owner
+pub const OWNER_TOKENS: Map<(&Addr, &str), Empty> = Map::new("owner_tokens");
+
+// (owner, operator) -> expiration of a cw721 operator approved on all tokens of the owner
+pub const NFT_OPERATORS: Map<(&Addr, &Addr), Expiration> = Map::new("nft_operators");
+
+// number of position tokens in circulation
+pub const TOKEN_COUNT: Item<u64> = Item::new("token_count");
+
+const TOKEN_NONCE: Item<u64> = Item::new("token_nonce");
+
+// mints a token for the position, the token id is `{stream_id}/{nonce}`
+pub fn mint_position_token(
+    store: &mut dyn Storage,
+    stream_id: StreamId,
+    position: &mut Position,
+) -> StdResult<String> {
+    let nonce = TOKEN_NONCE.may_load(store)?.unwrap_or_default() + 1;
+    TOKEN_NONCE.save(store, &nonce)?;
+    let token_id = format!("{}/{}", stream_id, nonce);
+    let token = PositionToken {
+        stream_id,
+        owner: position.owner.clone(),
+        approvals: vec![],
+    };
+    POSITION_TOKENS.save(store, &token_id, &token)?;
+    OWNER_TOKENS.save(store, (&position.owner, &token_id), &Empty {})?;
+    let count = TOKEN_COUNT.may_load(store)?.unwrap_or_default();
+    TOKEN_COUNT.save(store, &(count + 1))?;
+    position.token_id = Some(token_id.clone());
+    Ok(token_id)
+}
+
+// burns the token of the position if any, called when the position is removed
+pub fn burn_position_token(store: &mut dyn Storage, position: &Position) -> StdResult<()> {
+    if let Some(token_id) = &position.token_id {
+        POSITION_TOKENS.remove(store, token_id);
+        OWNER_TOKENS.remove(store, (&position.owner, token_id));
+        let count = TOKEN_COUNT.may_load(store)?.unwrap_or_default();
+        TOKEN_COUNT.save(store, &count.saturating_sub(1))?;
+    }
+    Ok(())
+}
+
+// moves the token to the new owner, clearing its approvals
+pub fn transfer_position_token(
+    store: &mut dyn Storage,
+    token_id: &str,
+    owner: &Addr,
+) -> StdResult<()> {
+    let mut token = POSITION_TOKENS.load(store, token_id)?;
+    OWNER_TOKENS.remove(store, (&token.owner, token_id));
+    OWNER_TOKENS.save(store, (owner, token_id), &Empty {})?;
+    token.owner = owner.clone();
+    token.approvals = vec![];
+    POSITION_TOKENS.save(store, token_id, &token)
+}
+
+// contracts receiving lifecycle callbacks of all streams
+pub const GLOBAL_HOOKS: Map<&Addr, Empty> = Map::new("global_hooks");
+
+// (stream_id, contract) -> contracts receiving lifecycle callbacks of the stream
+pub const STREAM_HOOKS: Map<(StreamId, &Addr), Empty> = Map::new("stream_hooks");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ProtocolStats {
+    /// total number of streams created.
+    pub total_streams: u64,
+    /// number of streams waiting for start date or first subscription.
+    pub waiting_streams: u64,
+    /// number of active streams.
+    pub active_streams: u64,
+    /// number of paused streams.
+    pub paused_streams: u64,
+    /// number of finalized streams.
+    pub finalized_streams: u64,
+    /// number of cancelled streams.
+    pub cancelled_streams: u64,
+    /// total `token_in` spent on finalized streams, per denom.
+    pub in_volume: Vec<Coin>,
+    /// total fees sent to the fee collector, per denom.
+    pub fees_collected: Vec<Coin>,
+    /// number of distinct addresses that subscribed to any stream.
+    pub unique_subscribers: u64,
+    /// number of positions that are not exited yet.
+    pub open_positions: u64,
+}
+
+impl ProtocolStats {
+    fn status_count(&mut self, status: &Status) -> &mut u64 {
+        match status {
+            Status::Waiting => &mut self.waiting_streams,
+            Status::Active => &mut self.active_streams,
+            Status::Paused => &mut self.paused_streams,
+            Status::Finalized => &mut self.finalized_streams,
+            Status::Cancelled => &mut self.cancelled_streams,
+        }
+    }
+
+    pub fn add_stream(&mut self, status: &Status) {
+        self.total_streams += 1;
+        *self.status_count(status) += 1;
+    }
+
+    pub fn move_stream(&mut self, from: &Status, to: &Status) {
+        if from == to {
+            return;
+        }
+        let count = self.status_count(from);
+        *count = count.saturating_sub(1);
+        *self.status_count(to) += 1;
+    }
+
+    pub fn add_in_volume(&mut self, coin: Coin) {
+        add_coin(&mut self.in_volume, coin);
+    }
+
+    pub fn add_fee(&mut self, coin: Coin) {
+        add_coin(&mut self.fees_collected, coin);
+    }
+}
+
+fn add_coin(coins: &mut Vec<Coin>, coin: Coin) {
+    if coin.amount.is_zero() {
+        return;
+    }
+    match coins.iter_mut().find(|c| c.denom == coin.denom) {
+        Some(c) => c.amount += coin.amount,
+        None => coins.push(coin),
+    }
+}
+
+pub const STATS: Item<ProtocolStats> = Item::new("stats");
+// Addresses that subscribed to any stream, used to count unique subscribers
+pub const SUBSCRIBERS: Map<&Addr, Empty> = Map::new("subscribers");
+
+// moves the stream between status counters of the protocol stats
+pub fn update_status_stats(store: &mut dyn Storage, from: &Status, to: &Status) -> StdResult<()> {
+    if from == to {
+        return Ok(());
+    }
+    let mut stats = STATS.load(store)?;
+    stats.move_stream(from, to);
+    STATS.save(store, &stats)
+}
+
+// records a new position, counting its owner if it has never subscribed before
+pub fn add_position_stats(store: &mut dyn Storage, owner: &Addr) -> StdResult<()> {
+    let mut stats = STATS.load(store)?;
+    stats.open_positions += 1;
+    if !SUBSCRIBERS.has(store, owner) {
+        SUBSCRIBERS.save(store, owner, &Empty {})?;
+        stats.unique_subscribers += 1;
+    }
+    STATS.save(store, &stats)
+}
+
+/// Fees accrued to collectors per denom, pulled by `ClaimFees`.
+pub const UNCLAIMED_FEES: Map<(&Addr, &str), Uint128> = Map::new("unclaimed_fees");
+
+pub fn accrue_fee(
+    store: &mut dyn Storage,
+    collector: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    UNCLAIMED_FEES.update(store, (collector, denom), |fees| -> StdResult<_> {
+        Ok(fees.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+// records an exited position
+pub fn remove_position_stats(store: &mut dyn Storage) -> StdResult<()> {
+    let mut stats = STATS.load(store)?;
+    stats.open_positions = stats.open_positions.saturating_sub(1);
+    STATS.save(store, &stats)
+}
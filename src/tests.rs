@@ -4,23 +4,57 @@ mod test_module {
     use crate::contract::{
         execute_create_stream, execute_exit_stream, execute_finalize_stream,
         execute_update_operator, execute_update_position, execute_update_stream, instantiate,
-        query_average_price, query_config, query_last_streamed_price, query_position, query_stream,
+        list_streams, migrate, query_account_operators, query_average_price, query_config,
+        query_estimate_subscription, query_exit_preview, query_finalize_preview,
+        query_last_streamed_price, query_position, query_position_operators, query_positions_batch,
+        query_pre_committed, query_stats, query_stream, query_streams_by_ids,
+        query_threshold_state, query_unclaimed_fees, reply,
+    };
+    use crate::discount::query_fee_discount;
+    use crate::events::EVENT_VERSION;
+    use crate::hooks::{query_hooks, HOOK_GAS_LIMIT, HOOK_REPLY_ID};
+    use crate::killswitch::{
+        execute_cancel_stream, execute_pause_stream, execute_withdraw_paused, sudo_resume_stream,
     };
-    use crate::killswitch::{execute_pause_stream, execute_withdraw_paused, sudo_resume_stream};
     use crate::msg::ExecuteMsg::UpdateProtocolAdmin;
-    use crate::state::{Status, Stream};
-    use crate::threshold::ThresholdError;
+    use crate::msg::{
+        AccountOperatorResponse, FeeCollectorMsg, FinalizePreviewResponse,
+        PositionOperatorResponse, PositionResponse, StreamHookEvent, StreamHookMsg,
+    };
+    use crate::nft::{
+        query_all_operators, query_all_tokens, query_nft_info, query_num_tokens, query_owner_of,
+        query_tokens,
+    };
+    use crate::state::{
+        ExitFeeTier, FeeCollector, FeeDiscount, FeeSide, HolderFeeDiscount, OperatorGrant,
+        OperatorPermission, ProtocolStats, Status, Stream, POSITIONS, STATS, STREAMS,
+    };
+    use crate::threshold::{Threshold, ThresholdError};
     use crate::ContractError;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
     use cosmwasm_std::StdError::{self};
     use cosmwasm_std::{
-        attr, coin, Addr, BankMsg, Coin, CosmosMsg, Decimal, Decimal256, Response, SubMsg,
-        Timestamp, Uint128, Uint64,
+        attr, coin, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256,
+        DepsMut, Empty, Event, Reply, Response, StdResult, SubMsg, SubMsgResult, Timestamp,
+        Uint128, Uint64, WasmMsg,
     };
-    use cw_utils::PaymentError;
+    use cw_utils::{Expiration, PaymentError};
     use std::ops::Sub;
     use std::str::FromStr;
 
+    fn single_collector(address: &str) -> Vec<FeeCollectorMsg> {
+        vec![FeeCollectorMsg {
+            address: address.to_string(),
+            weight: Decimal::one(),
+        }]
+    }
+
+    fn unclaimed_fees(deps: cosmwasm_std::Deps, collector: &str) -> Vec<Coin> {
+        query_unclaimed_fees(deps, collector.to_string())
+            .unwrap()
+            .fees
+    }
+
     #[test]
     fn test_compute_shares_amount() {
         let mut stream = Stream::new(
@@ -36,6 +70,7 @@ mod test_module {
             "fee".to_string(),
             Uint128::from(100u128),
             Decimal::percent(10),
+            vec![],
         );
 
         // add new shares
@@ -69,8 +104,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(1000),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(101),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -84,8 +122,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(1000),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::zero(),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -98,8 +139,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(1000),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -301,7 +345,7 @@ mod test_module {
             out_supply,
             start_time,
             end_time,
-            Some(Uint128::new(0)),
+            Some(Threshold::MinSpentIn(Uint128::new(0))),
         )
         .unwrap_err();
         assert_eq!(
@@ -681,8 +725,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(1000),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -750,7 +797,10 @@ mod test_module {
         let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
         assert_eq!(res, PaymentError::MissingDenom("in".to_string()).into());
 
+        // the failed subscriptions leave the stream stored as waiting, queries report it started
         let stream = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(stream.status, Status::Active);
+        let stream = STREAMS.load(&deps.storage, 1).unwrap();
         assert_eq!(stream.status, Status::Waiting);
 
         // first subscribe
@@ -824,8 +874,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(1000),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -1001,8 +1054,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(1000),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -1082,6 +1138,7 @@ mod test_module {
             stream_id: 1,
             cap: Some(Uint128::new(500_000)),
             operator_target: None,
+            recipient: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(res.attributes[0].value, "withdraw_pending");
@@ -1115,6 +1172,7 @@ mod test_module {
             stream_id: 1,
             cap: Some(Uint128::new(400_000)),
             operator_target: None,
+            recipient: None,
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(res.attributes[0].value, "withdraw");
@@ -1152,8 +1210,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(1),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -1236,13 +1297,14 @@ mod test_module {
             execute_update_position(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
                 .unwrap();
         assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "update_position")
-                .add_attribute("stream_id", "1")
-                .add_attribute("operator_target", "creator1")
-                .add_attribute("purchased", "0")
-                .add_attribute("spent", "0")
+            res.attributes,
+            vec![
+                attr("action", "update_position"),
+                attr("stream_id", "1"),
+                attr("operator_target", "creator1"),
+                attr("purchased", "0"),
+                attr("spent", "0"),
+            ]
         );
 
         // random cannot update
@@ -1262,6 +1324,7 @@ mod test_module {
             stream_id: 1,
             cap: None,
             operator_target: Some("creator1".to_string()),
+            recipient: None,
         };
         assert_eq!(res, ContractError::Unauthorized {});
 
@@ -1279,8 +1342,61 @@ mod test_module {
             Some("operator1".to_string()),
         )
         .unwrap();
-        let position = query_position(deps.as_ref(), env, stream_id, owner).unwrap();
-        assert_eq!(position.operator.unwrap().as_str(), "operator1".to_string());
+        assert_eq!(
+            query_position_operators(deps.as_ref(), stream_id, owner)
+                .unwrap()
+                .operators,
+            vec![PositionOperatorResponse {
+                operator: "operator1".to_string(),
+                permissions: OperatorGrant::full().permissions,
+                expires: Expiration::Never {},
+            }]
+        );
+        // the full-permission operator is still reported on the position
+        let position = query_position(
+            deps.as_ref(),
+            env.clone(),
+            stream_id,
+            "creator1".to_string(),
+        )
+        .unwrap();
+        assert_eq!(position.operator, Some(Addr::unchecked("operator1")));
+
+        // removing the operator removes its grant
+        let info = mock_info("creator1", &[]);
+        execute_update_operator(deps.as_mut(), mock_env(), info, 1, None).unwrap();
+        assert!(
+            query_position_operators(deps.as_ref(), stream_id, "creator1".to_string())
+                .unwrap()
+                .operators
+                .is_empty()
+        );
+        let position = query_position(
+            deps.as_ref(),
+            env.clone(),
+            stream_id,
+            "creator1".to_string(),
+        )
+        .unwrap();
+        assert_eq!(position.operator, None);
+
+        // a legacy operator is migrated to a full-permission grant
+        let creator1 = Addr::unchecked("creator1");
+        let mut position = POSITIONS.load(&deps.storage, (1, &creator1)).unwrap();
+        position.operator = Some(Addr::unchecked("operator1"));
+        POSITIONS
+            .save(deps.as_mut().storage, (1, &creator1), &position)
+            .unwrap();
+        migrate(deps.as_mut(), mock_env(), crate::msg::MigrateMsg {}).unwrap();
+        let position = POSITIONS.load(&deps.storage, (1, &creator1)).unwrap();
+        assert_eq!(position.operator, None);
+        assert_eq!(
+            query_position_operators(deps.as_ref(), stream_id, "creator1".to_string())
+                .unwrap()
+                .operators[0]
+                .permissions,
+            OperatorGrant::full().permissions
+        );
 
         //operator can increase subscription on behalf of owner
         let info = mock_info("operator1", &[Coin::new(1_000_000, "in")]);
@@ -1293,13 +1409,14 @@ mod test_module {
         };
         let res = execute(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "subscribe")
-                .add_attribute("stream_id", "1")
-                .add_attribute("owner", "creator1")
-                .add_attribute("in_supply", "2000000")
-                .add_attribute("in_amount", "1000000")
+            res.attributes,
+            vec![
+                attr("action", "subscribe"),
+                attr("stream_id", "1"),
+                attr("owner", "creator1"),
+                attr("in_supply", "2000000"),
+                attr("in_amount", "1000000"),
+            ]
         );
 
         // random cannot update operator
@@ -1328,13 +1445,14 @@ mod test_module {
             execute_update_position(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
                 .unwrap();
         assert_eq!(
-            res,
-            Response::new()
-                .add_attribute("action", "update_position")
-                .add_attribute("stream_id", "1")
-                .add_attribute("operator_target", "creator1")
-                .add_attribute("purchased", "0")
-                .add_attribute("spent", "0")
+            res.attributes,
+            vec![
+                attr("action", "update_position"),
+                attr("stream_id", "1"),
+                attr("operator_target", "creator1"),
+                attr("purchased", "0"),
+                attr("spent", "0"),
+            ]
         );
 
         // operator can withdraw
@@ -1345,6 +1463,7 @@ mod test_module {
             stream_id: 1,
             cap: Some(5u128.into()),
             operator_target: Some("creator1".to_string()),
+            recipient: None,
         };
 
         // random cannot exit
@@ -1352,8 +1471,15 @@ mod test_module {
         let mut env = mock_env();
         env.block.time = end.plus_seconds(100);
         execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
-            .unwrap_err();
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env,
+            info,
+            1,
+            Some("creator1".to_string()),
+            None,
+        )
+        .unwrap_err();
         assert_eq!(res, ContractError::Unauthorized {});
 
         let mut env = mock_env();
@@ -1364,9 +1490,16 @@ mod test_module {
         let info = mock_info("operator1", &[]);
         let mut env = mock_env();
         env.block.time = end.plus_seconds(100);
-        let res =
-            execute_exit_stream(deps.as_mut(), env, info, 1, Some("creator1".to_string())).unwrap();
-        match res.messages.get(0).unwrap().msg.clone() {
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env,
+            info,
+            1,
+            Some("creator1".to_string()),
+            None,
+        )
+        .unwrap();
+        match res.messages.first().unwrap().msg.clone() {
             CosmosMsg::Bank(BankMsg::Send {
                 to_address,
                 amount: _,
@@ -1378,48 +1511,42 @@ mod test_module {
     }
 
     #[test]
-    fn test_update_stream() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_operator_permissions() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
         let out_supply = Uint128::new(1_000_000);
-        let out_denom = "out_denom";
 
-        // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
-            min_seconds_until_start_time: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        // create stream
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(1);
+        env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
             "creator",
-            &[
-                Coin::new(out_supply.u128(), out_denom),
-                Coin::new(100, "fee"),
-            ],
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
         );
         execute_create_stream(
             deps.as_mut(),
             env,
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
             Some("https://sample.url".to_string()),
             "in".to_string(),
-            out_denom.to_string(),
+            "out".to_string(),
             out_supply,
             start,
             end,
@@ -1427,90 +1554,336 @@ mod test_module {
         )
         .unwrap();
 
-        //update stream without subscription this means no new  distribution so returned index should be 0
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(100);
-        let res = execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let info = mock_info("owner", &[Coin::new(1_000_000, "in")]);
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // only position owners grant operators
+        let grant = |operator: &str, permissions: Vec<OperatorPermission>, expires| {
+            crate::msg::ExecuteMsg::GrantOperator {
+                stream_id: 1,
+                operator: operator.to_string(),
+                permissions,
+                expires,
+            }
+        };
+        let msg = grant("bot", vec![OperatorPermission::Withdraw], None);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("random", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = grant("bot", vec![], None);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidOperatorPermissions {});
+        let msg = grant(
+            "bot",
+            vec![OperatorPermission::Withdraw],
+            Some(Expiration::AtTime(start)),
+        );
+        let err = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidExpiration {});
+
+        // withdraw only bot and an update only bot expiring soon
+        let msg = grant("bot", vec![OperatorPermission::Withdraw], None);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+        assert!(res.attributes.contains(&attr("permissions", "withdraw")));
+        let msg = grant(
+            "updater",
+            vec![OperatorPermission::Update],
+            Some(Expiration::AtTime(start.plus_seconds(100))),
+        );
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
         assert_eq!(
-            res,
-            Response::default()
-                .add_attribute("action", "update_stream")
-                .add_attribute("stream_id", "1")
-                .add_attribute("new_distribution_amount", "0")
-                .add_attribute("dist_index", "0")
+            query_position_operators(deps.as_ref(), 1, "owner".to_string())
+                .unwrap()
+                .operators,
+            vec![
+                PositionOperatorResponse {
+                    operator: "bot".to_string(),
+                    permissions: vec![OperatorPermission::Withdraw],
+                    expires: Expiration::Never {},
+                },
+                PositionOperatorResponse {
+                    operator: "updater".to_string(),
+                    permissions: vec![OperatorPermission::Update],
+                    expires: Expiration::AtTime(start.plus_seconds(100)),
+                },
+            ]
         );
-        //first subscription
-        //On first subscription index is not incresed because no distrubution prior to that(Execute_subscibe also includes update_stream)
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(100);
-        let info = mock_info("creator1", &[Coin::new(1_000_000, "in")]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+
+        // bot can withdraw but not update or subscribe
+        let msg = crate::msg::ExecuteMsg::Withdraw {
             stream_id: 1,
-            operator_target: Some("creator1".to_string()),
+            cap: Some(Uint128::new(100)),
+            operator_target: Some("owner".to_string()),
+            recipient: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("bot", &[]), msg).unwrap();
+        let msg = crate::msg::ExecuteMsg::UpdatePosition {
+            stream_id: 1,
+            operator_target: Some("owner".to_string()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let subscribe = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: Some("owner".to_string()),
             operator: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg);
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[Coin::new(100, "in")]),
+            subscribe,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
 
-        //Query stream
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(200);
-        let res = query_stream(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(res.dist_index, Decimal256::zero());
+        // updater until its grant expires
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("updater", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        env.block.time = start.plus_seconds(100);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("updater", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // revoked bot loses access
+        let msg = crate::msg::ExecuteMsg::RevokeOperator {
+            stream_id: 1,
+            operator: "bot".to_string(),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(Uint128::new(100)),
+            operator_target: Some("owner".to_string()),
+            recipient: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("bot", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // grants are removed with the position
+        env.block.time = end.plus_seconds(1);
+        execute_exit_stream(deps.as_mut(), env, mock_info("owner", &[]), 1, None, None).unwrap();
+        assert_eq!(
+            query_position_operators(deps.as_ref(), 1, "owner".to_string())
+                .unwrap()
+                .operators,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_account_operators() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        //Update stream again, this time with subscriber
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(300);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        env.block.time = Timestamp::from_seconds(0);
+        for _ in 0..2 {
+            let info = mock_info(
+                "creator",
+                &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                "out".to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+            )
+            .unwrap();
+        }
 
-        //Query stream
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(300);
-        let res = query_stream(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(res.dist_index, Decimal256::from_str("0.00005").unwrap())
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::ApproveOperator {
+            operator: "owner".to_string(),
+            expires: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidOperator {});
+        let msg = crate::msg::ExecuteMsg::ApproveOperator {
+            operator: "bot".to_string(),
+            expires: Some(Expiration::AtTime(start.plus_seconds(1_000))),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(
+            query_account_operators(deps.as_ref(), "owner".to_string())
+                .unwrap()
+                .operators,
+            vec![AccountOperatorResponse {
+                operator: "bot".to_string(),
+                expires: Expiration::AtTime(start.plus_seconds(1_000)),
+            }]
+        );
+
+        // positions opened after the approval are managed by the bot as well
+        for stream_id in [1, 2] {
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id,
+                operator_target: None,
+                operator: None,
+            };
+            let info = mock_info("owner", &[Coin::new(1_000_000, "in")]);
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(Uint128::new(100)),
+            operator_target: Some("owner".to_string()),
+            recipient: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("bot", &[]), msg).unwrap();
+
+        // paused streams too
+        execute_pause_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            2,
+        )
+        .unwrap();
+        execute_withdraw_paused(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            2,
+            Some(Uint128::new(100)),
+            Some("owner".to_string()),
+            None,
+        )
+        .unwrap();
+
+        // approval expires
+        env.block.time = start.plus_seconds(1_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(Uint128::new(100)),
+            operator_target: Some("owner".to_string()),
+            recipient: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // and can be revoked
+        let approve = crate::msg::ExecuteMsg::ApproveOperator {
+            operator: "bot".to_string(),
+            expires: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), approve).unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        let revoke = crate::msg::ExecuteMsg::RevokeOperatorApproval {
+            operator: "bot".to_string(),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            revoke.clone(),
+        )
+        .unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), revoke).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env, mock_info("bot", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
     }
+
     #[test]
-    fn test_update_position() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_withdraw_recipient() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
         let out_supply = Uint128::new(1_000_000);
-        let out_denom = "out_denom";
 
-        // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
-            min_seconds_until_start_time: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        // create stream
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(1);
+        env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
             "creator",
-            &[
-                Coin::new(out_supply.u128(), out_denom),
-                Coin::new(100, "fee"),
-            ],
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
         );
         execute_create_stream(
             deps.as_mut(),
             env,
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
             Some("https://sample.url".to_string()),
             "in".to_string(),
-            out_denom.to_string(),
+            "out".to_string(),
             out_supply,
             start,
             end,
@@ -1518,108 +1891,142 @@ mod test_module {
         )
         .unwrap();
 
-        // first subscription
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(100);
-        let info = mock_info("creator1", &[Coin::new(1_000_000, "in")]);
+        env.block.time = start;
         let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
             operator_target: None,
             operator: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg);
+        let info = mock_info("owner", &[Coin::new(1_000_000, "in")]);
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-        // non owner operator cannot update position
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        let info = mock_info("random", &[]);
-        let err =
-            execute_update_position(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
-                .unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
-
-        // update position
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        let info = mock_info("creator1", &[]);
-        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+        let withdraw = |recipient: Option<&str>| crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(Uint128::new(100)),
+            operator_target: Some("owner".to_string()),
+            recipient: recipient.map(|r| r.to_string()),
+        };
 
-        let position =
-            query_position(deps.as_ref(), env.clone(), 1, "creator1".to_string()).unwrap();
-        assert_eq!(
-            position.index,
-            Decimal256::from_str("0.749993000000000000").unwrap()
-        );
-        assert_eq!(position.purchased, Uint128::new(749_993));
-        assert_eq!(position.spent, Uint128::new(749_993));
-        assert_eq!(position.in_balance, Uint128::new(250_007));
-        let stream = query_stream(deps.as_ref(), env, 1).unwrap();
+        // owner withdraws to another address
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            withdraw(Some("alice")),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("recipient", "alice")));
         assert_eq!(
-            stream.dist_index,
-            Decimal256::from_str("0.749993000000000000").unwrap()
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: vec![Coin::new(100, "in")]
+            })
         );
 
-        // can update position after stream ends
-        let mut env = mock_env();
-        env.block.time = end.plus_seconds(1);
-        let info = mock_info("creator1", &[]);
-        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
-        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
-        assert_eq!(stream.dist_index, Decimal256::from_str("1").unwrap());
-        assert_eq!(stream.in_supply, Uint128::zero());
-        let position = query_position(deps.as_ref(), env, 1, "creator1".to_string()).unwrap();
-        assert_eq!(position.index, Decimal256::from_str("1").unwrap());
-        assert_eq!(position.spent, Uint128::new(1_000_000));
-        assert_eq!(position.in_balance, Uint128::zero());
+        // withdraw operators can only send funds to the owner
+        let msg = crate::msg::ExecuteMsg::GrantOperator {
+            stream_id: 1,
+            operator: "bot".to_string(),
+            permissions: vec![OperatorPermission::Withdraw],
+            expires: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            withdraw(Some("bot")),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            withdraw(Some("owner")),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("recipient", "owner")));
 
-        assert_eq!(stream.out_supply, Uint128::new(1_000_000));
-        assert_eq!(position.purchased, stream.out_supply);
+        // unless granted the redirect permission
+        let msg = crate::msg::ExecuteMsg::GrantOperator {
+            stream_id: 1,
+            operator: "bot".to_string(),
+            permissions: vec![OperatorPermission::Withdraw, OperatorPermission::Redirect],
+            expires: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            withdraw(Some("bot")),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("recipient", "bot")));
+
+        // exit to another address
+        env.block.time = end.plus_seconds(1);
+        let msg = crate::msg::ExecuteMsg::ExitStream {
+            stream_id: 1,
+            operator_target: Some("owner".to_string()),
+            recipient: Some("alice".to_string()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), env, mock_info("owner", &[]), msg).unwrap();
+        assert!(res.attributes.contains(&attr("recipient", "alice")));
+        assert!(res.messages.iter().all(|m| match &m.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => to_address == "alice",
+            _ => true,
+        }));
     }
 
-    // this is for testing the leftover amount with bigger values
     #[test]
-    fn test_rounding_leftover() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_transfer_position() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
-        let out_supply = Uint128::new(1_000_000_000_000);
-        let out_denom = "out_denom";
+        let out_supply = Uint128::new(1_000_000);
 
-        // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
-            min_seconds_until_start_time: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        // create stream
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(1);
+        env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
             "creator",
-            &[
-                Coin::new(out_supply.u128(), out_denom),
-                Coin::new(100, "fee"),
-            ],
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
         );
         execute_create_stream(
             deps.as_mut(),
             env,
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
             Some("https://sample.url".to_string()),
             "in".to_string(),
-            out_denom.to_string(),
+            "out".to_string(),
             out_supply,
             start,
             end,
@@ -1627,162 +2034,170 @@ mod test_module {
         )
         .unwrap();
 
-        // first subscription
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(100);
-        let info = mock_info("creator1", &[Coin::new(1_000_000_000, "in")]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+        env.block.time = start;
+        for subscriber in ["alice", "bob"] {
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: Some("bot".to_string()),
+            };
+            let info = mock_info(subscriber, &[Coin::new(1_000_000, "in")]);
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let msg = crate::msg::ExecuteMsg::GrantOperator {
             stream_id: 1,
-            operator_target: None,
-            operator: None,
+            operator: "bot".to_string(),
+            permissions: vec![OperatorPermission::Withdraw],
+            expires: None,
         };
-        execute(deps.as_mut(), env, info, msg).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg).unwrap();
 
-        // second subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(100_000);
-        let info = mock_info("creator2", &[Coin::new(3_000_000_000, "in")]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+        let transfer = |recipient: &str| crate::msg::ExecuteMsg::TransferPosition {
             stream_id: 1,
-            operator_target: None,
-            operator: None,
+            recipient: recipient.to_string(),
         };
-        execute(deps.as_mut(), env, info, msg).unwrap();
-
-        // update position creator1
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        let info = mock_info("creator1", &[]);
-        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
-
-        let position =
-            query_position(deps.as_ref(), env.clone(), 1, "creator1".to_string()).unwrap();
-        assert_eq!(
-            position.index,
-            Decimal256::from_str("202.813614449380587585").unwrap()
-        );
-        assert_eq!(position.purchased, Uint128::new(202_813_614_449));
-        assert_eq!(position.spent, Uint128::new(749_993_750));
-        assert_eq!(position.in_balance, Uint128::new(250_006_250));
-        let stream = query_stream(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(
-            stream.dist_index,
-            Decimal256::from_str("202.813614449380587585").unwrap()
-        );
-
-        // update position creator2
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_575_000);
-        let info = mock_info("creator2", &[]);
-        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[]),
+            transfer("alice"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidTransferRecipient {});
+        // operators cannot transfer positions
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            transfer("carol"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
 
-        let position =
-            query_position(deps.as_ref(), env.clone(), 1, "creator2".to_string()).unwrap();
-        assert_eq!(
-            position.index,
-            Decimal256::from_str("238.074595237060799266").unwrap()
+        // alice transfers her position to carol in the middle of the stream
+        env.block.time = start.plus_seconds(2_000_000);
+        let alice = query_position(deps.as_ref(), env.clone(), 1, "alice".to_string()).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[]),
+            transfer("carol"),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("merged", "false")));
+        assert!(query_position(deps.as_ref(), env.clone(), 1, "alice".to_string()).is_err());
+        assert!(
+            query_position_operators(deps.as_ref(), 1, "alice".to_string())
+                .unwrap()
+                .operators
+                .is_empty()
         );
-        assert_eq!(position.purchased, Uint128::new(655672748445));
-        assert_eq!(position.spent, Uint128::new(2673076923));
-        assert_eq!(position.in_balance, Uint128::new(326923077));
-        let stream = query_stream(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(
-            stream.dist_index,
-            Decimal256::from_str("238.074595237060799266").unwrap()
+        let carol = query_position(deps.as_ref(), env.clone(), 1, "carol".to_string()).unwrap();
+        assert_eq!(carol.owner, "carol");
+        assert!(
+            query_position_operators(deps.as_ref(), 1, "carol".to_string())
+                .unwrap()
+                .operators
+                .is_empty()
         );
+        assert_eq!(carol.shares, alice.shares);
+        assert_eq!(carol.in_balance, Uint128::new(500_000));
+        assert_eq!(carol.purchased, Uint128::new(250_000));
+        assert_eq!(query_stats(deps.as_ref()).unwrap().open_positions, 2);
 
-        // update position after stream ends
-        let mut env = mock_env();
-        env.block.time = end.plus_seconds(1);
-        let info = mock_info("creator1", &[]);
-        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
-        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
-        assert_eq!(
-            stream.dist_index,
-            Decimal256::from_str("264.137059297637397644").unwrap()
-        );
-        assert_eq!(stream.in_supply, Uint128::zero());
-        let position1 = query_position(deps.as_ref(), env, 1, "creator1".to_string()).unwrap();
+        // carol transfers the position to bob, merging it into his position
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("carol", &[]),
+            transfer("bob"),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("merged", "true")));
+        let bob = query_position(deps.as_ref(), env.clone(), 1, "bob".to_string()).unwrap();
+        assert_eq!(bob.owner, "bob");
         assert_eq!(
-            position1.index,
-            Decimal256::from_str("264.137059297637397644").unwrap()
+            query_position_operators(deps.as_ref(), 1, "bob".to_string())
+                .unwrap()
+                .operators,
+            vec![PositionOperatorResponse {
+                operator: "bot".to_string(),
+                permissions: OperatorGrant::full().permissions,
+                expires: Expiration::Never {},
+            }]
         );
-        assert_eq!(position1.spent, Uint128::new(1_000_000_000));
-        assert_eq!(position1.in_balance, Uint128::zero());
+        assert_eq!(bob.in_balance, Uint128::new(1_000_000));
+        assert_eq!(bob.spent, Uint128::new(1_000_000));
+        assert_eq!(bob.purchased, Uint128::new(500_000));
+        assert_eq!(query_stats(deps.as_ref()).unwrap().open_positions, 1);
 
-        // update position after stream ends
-        let mut env = mock_env();
+        // bob exits with the whole supply
         env.block.time = end.plus_seconds(1);
-        let info = mock_info("creator2", &[]);
-        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
-        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
-        assert_eq!(
-            stream.dist_index,
-            Decimal256::from_str("264.137059297637397644").unwrap()
-        );
-        assert_eq!(stream.in_supply, Uint128::zero());
-        let position2 = query_position(deps.as_ref(), env, 1, "creator2".to_string()).unwrap();
-        assert_eq!(
-            position2.index,
-            Decimal256::from_str("264.137059297637397644").unwrap()
-        );
-        assert_eq!(position2.spent, Uint128::new(3_000_000_000));
-        assert_eq!(position2.in_balance, Uint128::zero());
-
-        assert_eq!(stream.out_remaining, Uint128::zero());
-        assert_eq!(
-            position1
-                .purchased
-                .checked_add(position2.purchased)
-                .unwrap(),
-            // 1 difference due to rounding
-            stream.out_supply.sub(Uint128::new(1u128))
-        );
+        let res =
+            execute_exit_stream(deps.as_mut(), env, mock_info("bob", &[]), 1, None, None).unwrap();
+        assert!(res.attributes.contains(&attr("purchased", out_supply)));
     }
 
     #[test]
-    fn test_withdraw() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_position_nfts() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
-        let out_supply = Uint128::new(1_000_000_000_000);
-        let out_denom = "out_denom";
+        let out_supply = Uint128::new(1_000_000);
 
-        // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: Some(true),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert!(query_config(deps.as_ref()).unwrap().position_nfts);
 
-        // create stream
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
-            "creator1",
-            &[
-                Coin::new(out_supply.u128(), out_denom),
-                Coin::new(100, "fee"),
-            ],
+            "creator",
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
         );
         execute_create_stream(
             deps.as_mut(),
             env,
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
             Some("https://sample.url".to_string()),
             "in".to_string(),
-            out_denom.to_string(),
+            "out".to_string(),
             out_supply,
             start,
             end,
@@ -1790,102 +2205,3207 @@ mod test_module {
         )
         .unwrap();
 
-        // first subscription
+        // new positions are minted, top ups are not
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(0);
-        let funds = Coin::new(2_000_000_000_000, "in");
-        let info = mock_info("creator1", &[funds.clone()]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
-            stream_id: 1,
-            operator_target: None,
-            operator: None,
+        env.block.time = start;
+        let subscribe = |deps: DepsMut, subscriber: &str| {
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+            };
+            let info = mock_info(subscriber, &[Coin::new(1_000_000, "in")]);
+            execute(deps, env.clone(), info, msg).unwrap()
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-
-        // withdraw with cap
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(5000);
-        let info = mock_info("creator1", &[]);
-        // withdraw amount zero
-        let cap = Uint128::zero();
+        let res = subscribe(deps.as_mut(), "alice");
+        assert!(res.attributes.contains(&attr("token_id", "1/1")));
+        let res = subscribe(deps.as_mut(), "alice");
+        assert!(!res.attributes.iter().any(|attr| attr.key == "token_id"));
+        subscribe(deps.as_mut(), "bob");
+        subscribe(deps.as_mut(), "dave");
+        assert_eq!(query_num_tokens(deps.as_ref()).unwrap().count, 3);
+        assert_eq!(
+            query_tokens(deps.as_ref(), "alice".to_string(), None, None)
+                .unwrap()
+                .tokens,
+            vec!["1/1".to_string()]
+        );
+        assert_eq!(
+            query_all_tokens(deps.as_ref(), Some("1/1".to_string()), None)
+                .unwrap()
+                .tokens,
+            vec!["1/2".to_string(), "1/3".to_string()]
+        );
+        let info = query_nft_info(deps.as_ref(), env.clone(), "1/1".to_string()).unwrap();
+        assert_eq!(info.extension.owner, "alice");
+        assert_eq!(info.extension.in_balance, Uint128::new(2_000_000));
+        assert_eq!(info.extension.token_id, Some("1/1".to_string()));
+
+        // approved spenders can transfer the token once
+        let msg = crate::msg::ExecuteMsg::Approve {
+            spender: "bot".to_string(),
+            token_id: "1/1".to_string(),
+            expires: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bob", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg).unwrap();
+        let owner = query_owner_of(deps.as_ref(), env.clone(), "1/1".to_string(), false).unwrap();
+        assert_eq!(owner.approvals[0].spender, "bot");
+        let transfer = crate::msg::ExecuteMsg::TransferNft {
+            recipient: "carol".to_string(),
+            token_id: "1/1".to_string(),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bot", &[]),
+            transfer.clone(),
+        )
+        .unwrap();
+        let owner = query_owner_of(deps.as_ref(), env.clone(), "1/1".to_string(), false).unwrap();
+        assert_eq!(owner.owner, "carol");
+        assert!(owner.approvals.is_empty());
+        assert!(query_position(deps.as_ref(), env.clone(), 1, "alice".to_string()).is_err());
+        assert_eq!(
+            query_position(deps.as_ref(), env.clone(), 1, "carol".to_string())
+                .unwrap()
+                .token_id,
+            Some("1/1".to_string())
+        );
+        let err = execute(deps.as_mut(), env.clone(), mock_info("bot", &[]), transfer).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // send to a contract calls its receive hook
+        let msg = crate::msg::ExecuteMsg::SendNft {
+            contract: "vault".to_string(),
+            token_id: "1/1".to_string(),
+            msg: Binary::from(b"{}"),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("carol", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            crate::msg::Cw721ReceiveMsg {
+                sender: "carol".to_string(),
+                token_id: "1/1".to_string(),
+                msg: Binary::from(b"{}"),
+            }
+            .into_cosmos_msg("vault".to_string())
+            .unwrap()
+        );
+
+        // tokens cannot be moved to an owner that already has a position
+        let msg = crate::msg::ExecuteMsg::TransferNft {
+            recipient: "bob".to_string(),
+            token_id: "1/1".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("vault", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::PositionExists {});
+        let msg = crate::msg::ExecuteMsg::SendNft {
+            contract: "vault".to_string(),
+            token_id: "1/2".to_string(),
+            msg: Binary::from(b"{}"),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::PositionExists {});
+        let owner = query_owner_of(deps.as_ref(), env.clone(), "1/2".to_string(), false).unwrap();
+        assert_eq!(owner.owner, "bob");
+        assert_eq!(query_num_tokens(deps.as_ref()).unwrap().count, 3);
+
+        // account operators manage positions but cannot move their tokens
+        let msg = crate::msg::ExecuteMsg::ApproveOperator {
+            operator: "manager".to_string(),
+            expires: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("dave", &[]), msg).unwrap();
+        let transfer = crate::msg::ExecuteMsg::TransferNft {
+            recipient: "erin".to_string(),
+            token_id: "1/3".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("manager", &[]),
+            transfer.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = crate::msg::ExecuteMsg::RevokeAll {
+            operator: "manager".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("dave", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // cw721 operators are listed apart from account operators
+        let msg = crate::msg::ExecuteMsg::ApproveAll {
+            operator: "market".to_string(),
+            expires: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("dave", &[]), msg).unwrap();
+        let operators = query_all_operators(
+            deps.as_ref(),
+            env.clone(),
+            "dave".to_string(),
+            false,
+            None,
+            None,
+        )
+        .unwrap()
+        .operators;
+        assert_eq!(operators.len(), 1);
+        assert_eq!(operators[0].spender, "market");
         let msg = crate::msg::ExecuteMsg::Withdraw {
             stream_id: 1,
-            cap: Some(cap),
+            cap: None,
+            operator_target: Some("dave".to_string()),
+            recipient: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("market", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // burning keeps the position
+        let msg = crate::msg::ExecuteMsg::Burn {
+            token_id: "1/3".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("dave", &[]), msg).unwrap();
+        let position = query_position(deps.as_ref(), env.clone(), 1, "dave".to_string()).unwrap();
+        assert_eq!(position.token_id, None);
+        assert_eq!(position.in_balance, Uint128::new(1_000_000));
+
+        // exit burns the token
+        env.block.time = end.plus_seconds(1);
+        execute_exit_stream(deps.as_mut(), env, mock_info("bob", &[]), 1, None, None).unwrap();
+        assert_eq!(query_num_tokens(deps.as_ref()).unwrap().count, 1);
+        assert!(query_tokens(deps.as_ref(), "bob".to_string(), None, None)
+            .unwrap()
+            .tokens
+            .is_empty());
+    }
+
+    #[test]
+    fn test_hooks() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let hook = |contract: &str, event: StreamHookEvent| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: contract.to_string(),
+                    msg: to_json_binary(&StreamHookMsg::StreamHook(event)).unwrap(),
+                    funds: vec![],
+                },
+                HOOK_REPLY_ID,
+            )
+            .with_gas_limit(HOOK_GAS_LIMIT)
+        };
+
+        // protocol admin registers hooks of all streams
+        let add_hook = |stream_id: Option<u64>, contract: &str| crate::msg::ExecuteMsg::AddHook {
+            stream_id,
+            contract: contract.to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            add_hook(None, "indexer"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            add_hook(None, "indexer"),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            add_hook(None, "indexer"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::HookAlreadyRegistered {});
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator",
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
+        );
+        let res = execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            "out".to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![hook("indexer", StreamHookEvent::Create { stream_id: 1 })]
+        );
+
+        // treasury registers hooks of its stream
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            add_hook(Some(1), "vault"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasury", &[]),
+            add_hook(Some(1), "vault"),
+        )
+        .unwrap();
+        assert_eq!(
+            query_hooks(deps.as_ref(), Some(1)).unwrap().hooks,
+            vec!["vault".to_string()]
+        );
+
+        let mut env = mock_env();
+        env.block.time = start;
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let info = mock_info("alice", &[Coin::new(1_000, "in")]);
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let event = StreamHookEvent::Subscribe {
+            stream_id: 1,
+            owner: "alice".to_string(),
+            in_amount: Uint128::new(1_000),
+        };
+        assert_eq!(
+            res.messages,
+            vec![hook("indexer", event.clone()), hook("vault", event)]
+        );
+
+        // failing hooks are reported without reverting the flow
+        let res = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: HOOK_REPLY_ID,
+                result: SubMsgResult::Err("hook error".to_string()),
+            },
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("error", "hook error")));
+        let err = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: 99,
+                result: SubMsgResult::Err("hook error".to_string()),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnknownReplyId(99));
+
+        // removed hooks are not called
+        let remove_hook = crate::msg::ExecuteMsg::RemoveHook {
+            stream_id: None,
+            contract: "indexer".to_string(),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            remove_hook.clone(),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            remove_hook,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::HookNotRegistered {});
+        assert!(query_hooks(deps.as_ref(), None).unwrap().hooks.is_empty());
+
+        let res =
+            execute_pause_stream(deps.as_mut(), env, mock_info("protocol_admin", &[]), 1).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![hook("vault", StreamHookEvent::Pause { stream_id: 1 })]
+        );
+    }
+
+    #[test]
+    fn test_failing_hook() {
+        use cw_multi_test::{App, ContractWrapper, Executor};
+
+        let creator = Addr::unchecked("creator");
+        let alice = Addr::unchecked("alice");
+        let mut app = App::new(|router, _, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &creator,
+                    vec![Coin::new(1_000_000, "out"), Coin::new(100, "fee")],
+                )
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &alice, vec![Coin::new(1_000, "in")])
+                .unwrap();
+        });
+        let streamswap_code = app.store_code(Box::new(
+            ContractWrapper::new(execute, instantiate, crate::contract::query).with_reply(reply),
+        ));
+        let hook_code = app.store_code(Box::new(ContractWrapper::new(
+            |_, _, _, _: Empty| -> StdResult<Response> { Err(StdError::generic_err("hook error")) },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::new()) },
+            |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+        )));
+
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        let streamswap = app
+            .instantiate_contract(streamswap_code, creator.clone(), &msg, &[], "ss", None)
+            .unwrap();
+        let hook = app
+            .instantiate_contract(hook_code, creator.clone(), &Empty {}, &[], "hook", None)
+            .unwrap();
+        let msg = crate::msg::ExecuteMsg::AddHook {
+            stream_id: None,
+            contract: hook.to_string(),
+        };
+        app.execute_contract(
+            Addr::unchecked("protocol_admin"),
+            streamswap.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+
+        // the hook fails on create and subscribe, both actions are still executed
+        let start = app.block_info().time.plus_seconds(100);
+        let msg = crate::msg::ExecuteMsg::CreateStream {
+            treasury: "treasury".to_string(),
+            name: "test".to_string(),
+            url: Some("https://sample.url".to_string()),
+            in_denom: "in".to_string(),
+            out_denom: "out".to_string(),
+            out_supply: Uint128::new(1_000_000),
+            start_time: start,
+            end_time: start.plus_seconds(1_000_000),
+            threshold: None,
+        };
+        let funds = [Coin::new(1_000_000, "out"), Coin::new(100, "fee")];
+        let res = app
+            .execute_contract(creator, streamswap.clone(), &msg, &funds)
+            .unwrap();
+        assert!(res.has_event(&Event::new("wasm").add_attribute("action", "hook_failed")));
+
+        app.update_block(|block| block.time = start);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let res = app
+            .execute_contract(
+                alice.clone(),
+                streamswap.clone(),
+                &msg,
+                &[Coin::new(1_000, "in")],
+            )
+            .unwrap();
+        assert!(res.has_event(&Event::new("wasm").add_attribute("action", "hook_failed")));
+        let position: PositionResponse = app
+            .wrap()
+            .query_wasm_smart(
+                &streamswap,
+                &crate::msg::QueryMsg::Position {
+                    stream_id: 1,
+                    owner: alice.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(position.in_balance, Uint128::new(1_000));
+        assert!(app.wrap().query_all_balances(&alice).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_event_schemas() {
+        use crate::msg::ExecuteMsg;
+
+        const STREAM_KEYS: &[&str] = &[
+            "stream_status",
+            "stream_treasury",
+            "stream_in_denom",
+            "stream_in_supply",
+            "stream_pre_committed_in",
+            "stream_spent_in",
+            "stream_shares",
+            "stream_out_denom",
+            "stream_out_remaining",
+            "stream_out_claimed",
+            "stream_exit_fees_accrued",
+            "stream_keeper_rewards",
+            "stream_keeper_reserve",
+            "stream_subscribers",
+            "stream_dist_index",
+            "stream_current_streamed_price",
+            "stream_last_updated",
+            "stream_start_time",
+            "stream_end_time",
+            "stream_pause_date",
+        ];
+        const POSITION_KEYS: &[&str] = &[
+            "owner",
+            "position_in_balance",
+            "position_shares",
+            "position_index",
+            "position_purchased",
+            "position_pending_purchase",
+            "position_spent",
+            "position_claimed",
+            "position_last_updated",
+        ];
+
+        // asserts the number of events of the type and their attribute keys in the current version
+        fn assert_events(res: &Response, ty: &str, keys: &[&str], position: bool, count: usize) {
+            let mut expected = vec!["version", "stream_id"];
+            expected.extend(keys);
+            if position {
+                expected.extend(POSITION_KEYS);
+            }
+            expected.extend(STREAM_KEYS);
+            let events: Vec<_> = res.events.iter().filter(|e| e.ty == ty).collect();
+            assert_eq!(events.len(), count, "{ty} events");
+            for event in events {
+                let event_keys: Vec<_> = event.attributes.iter().map(|a| a.key.as_str()).collect();
+                assert_eq!(event_keys, expected, "{ty} event");
+                assert_eq!(event.attributes[0].value, EVENT_VERSION);
+            }
+        }
+        // asserts the attribute keys of the single event of the type, for events without stream state
+        fn assert_event_keys(res: &Response, ty: &str, keys: &[&str]) {
+            let events: Vec<_> = res.events.iter().filter(|e| e.ty == ty).collect();
+            assert_eq!(events.len(), 1, "{ty} events");
+            let mut expected = vec!["version"];
+            expected.extend(keys);
+            let event_keys: Vec<_> = events[0]
+                .attributes
+                .iter()
+                .map(|a| a.key.as_str())
+                .collect();
+            assert_eq!(event_keys, expected, "{ty} event");
+            assert_eq!(events[0].attributes[0].value, EVENT_VERSION);
+        }
+        fn event_attr(res: &Response, ty: &str, key: &str) -> String {
+            let event = res.events.iter().find(|e| e.ty == ty).unwrap();
+            let attr = event.attributes.iter().find(|a| a.key == key).unwrap();
+            attr.value.clone()
+        }
+        fn exec(
+            deps: DepsMut,
+            sender: &str,
+            funds: &[Coin],
+            time: u64,
+            msg: ExecuteMsg,
+        ) -> Response {
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(time);
+            execute(deps, env, mock_info(sender, funds), msg).unwrap()
+        }
+        fn sudo_at(deps: DepsMut, time: u64, msg: crate::msg::SudoMsg) -> Response {
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(time);
+            crate::contract::sudo(deps, env, msg).unwrap()
+        }
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: Some(true),
+        };
+        let res = exec(deps.as_mut(), "protocol_admin", &[], 0, msg);
+        let config_keys = &[
+            "min_stream_seconds",
+            "min_seconds_until_start_time",
+            "accepted_in_denom",
+            "stream_creation_denom",
+            "stream_creation_fee",
+            "stream_creation_fee_options",
+            "exit_fee_percent",
+            "exit_fee_tiers",
+            "fee_side",
+            "fee_collectors",
+            "protocol_admin",
+            "keeper_reward_percent",
+            "position_nfts",
+        ];
+        assert_event_keys(&res, "update_config", config_keys);
+        assert_eq!(event_attr(&res, "update_config", "position_nfts"), "true");
+        assert_eq!(
+            event_attr(&res, "update_config", "stream_creation_fee_options"),
+            "none"
+        );
+
+        let create_keys = &[
+            "treasury",
+            "name",
+            "url",
+            "out_supply",
+            "start_time",
+            "creation_fee_denom",
+            "creation_fee",
+            "exit_fee_percent",
+            "exit_fee_tiers",
+            "fee_side",
+            "creation_fee_discount",
+            "exit_fee_discount",
+            "threshold",
+        ];
+        let funds = [Coin::new(1_000_000, "out"), Coin::new(100, "fee")];
+        // stream 3 misses its threshold, the others are cancelled or finalized
+        for threshold in [
+            None,
+            None,
+            Some(Threshold::MinSpentIn(Uint128::new(1_000_000_000))),
+            None,
+        ] {
+            let msg = ExecuteMsg::CreateStream {
+                treasury: "treasury".to_string(),
+                name: "test".to_string(),
+                url: None,
+                in_denom: "in".to_string(),
+                out_denom: "out".to_string(),
+                out_supply: Uint128::new(1_000_000),
+                start_time: Timestamp::from_seconds(1_000_000),
+                end_time: Timestamp::from_seconds(5_000_000),
+                threshold: threshold.clone(),
+            };
+            let res = exec(deps.as_mut(), "creator", &funds, 0, msg);
+            assert_events(&res, "create_stream", create_keys, false, 1);
+            assert_eq!(
+                event_attr(&res, "create_stream", "threshold"),
+                threshold.map_or("none".to_string(), |t| t.to_string())
+            );
+            assert_eq!(event_attr(&res, "create_stream", "url"), "none");
+            assert_eq!(event_attr(&res, "create_stream", "fee_side"), "in");
+            assert_eq!(event_attr(&res, "create_stream", "exit_fee_tiers"), "none");
+        }
+        let msg = ExecuteMsg::UpdateThreshold {
+            stream_id: 4,
+            threshold: Some(Threshold::MinSpentIn(Uint128::new(1))),
+        };
+        let res = exec(deps.as_mut(), "treasury", &[], 0, msg);
+        let threshold_keys = &["previous_threshold", "threshold"];
+        assert_events(&res, "update_threshold", threshold_keys, false, 1);
+        assert_eq!(
+            event_attr(&res, "update_threshold", "previous_threshold"),
+            "none"
+        );
+        let msg = ExecuteMsg::UpdateThreshold {
+            stream_id: 4,
+            threshold: None,
+        };
+        exec(deps.as_mut(), "treasury", &[], 0, msg);
+
+        let subscribe = |stream_id| ExecuteMsg::Subscribe {
+            stream_id,
+            operator_target: None,
+            operator: None,
+        };
+        let withdraw = |stream_id, cap: u128| ExecuteMsg::Withdraw {
+            stream_id,
+            cap: Some(Uint128::new(cap)),
+            operator_target: None,
+            recipient: None,
+        };
+        let subscribe_keys = &["in_amount", "shares", "pending", "token_id"];
+        let withdraw_keys = &["recipient", "amount", "shares"];
+        let in_funds = [Coin::new(1_000, "in")];
+
+        // pending subscription and withdrawal
+        let res = exec(deps.as_mut(), "alice", &in_funds, 500_000, subscribe(1));
+        assert_events(&res, "subscribe", subscribe_keys, true, 1);
+        assert_eq!(event_attr(&res, "subscribe", "pending"), "true");
+        assert_eq!(event_attr(&res, "subscribe", "stream_status"), "waiting");
+        let res = exec(deps.as_mut(), "alice", &[], 500_000, withdraw(1, 100));
+        assert_events(&res, "withdraw", withdraw_keys, true, 1);
+        assert_eq!(event_attr(&res, "withdraw", "position_in_balance"), "900");
+
+        let res = exec(deps.as_mut(), "bob", &in_funds, 1_000_000, subscribe(1));
+        assert_events(&res, "subscribe", subscribe_keys, true, 1);
+        assert_eq!(event_attr(&res, "subscribe", "pending"), "false");
+        assert_eq!(event_attr(&res, "subscribe", "stream_status"), "active");
+        for stream_id in 2..=4 {
+            exec(
+                deps.as_mut(),
+                "alice",
+                &in_funds,
+                1_500_000,
+                subscribe(stream_id),
+            );
+            exec(
+                deps.as_mut(),
+                "bob",
+                &in_funds,
+                1_500_000,
+                subscribe(stream_id),
+            );
+        }
+
+        let res = exec(
+            deps.as_mut(),
+            "anyone",
+            &[],
+            2_000_000,
+            ExecuteMsg::UpdateStream { stream_id: 1 },
+        );
+        assert_events(&res, "update_stream", &["distributed"], false, 1);
+        let msg = ExecuteMsg::UpdatePosition {
+            stream_id: 1,
+            operator_target: None,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 2_000_000, msg);
+        assert_events(&res, "update_position", &["purchased", "spent"], true, 1);
+        let msg = ExecuteMsg::ClaimPurchased {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 2_000_000, msg);
+        assert_events(&res, "claim_purchased", &["recipient", "amount"], true, 1);
+        let res = exec(deps.as_mut(), "bob", &[], 2_000_000, withdraw(1, 100));
+        assert_events(&res, "withdraw", withdraw_keys, true, 1);
+
+        // transfers by message and by position token
+        let transfer_keys = &["previous_owner", "merged", "token_id"];
+        let msg = ExecuteMsg::TransferPosition {
+            stream_id: 1,
+            recipient: "carol".to_string(),
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 2_000_000, msg);
+        assert_events(&res, "transfer_position", transfer_keys, true, 1);
+        assert_eq!(event_attr(&res, "transfer_position", "owner"), "carol");
+        let token_id = query_position(deps.as_ref(), mock_env(), 1, "bob".to_string())
+            .unwrap()
+            .token_id
+            .unwrap();
+        let msg = ExecuteMsg::TransferNft {
+            recipient: "dave".to_string(),
+            token_id,
+        };
+        let res = exec(deps.as_mut(), "bob", &[], 2_000_000, msg);
+        assert_events(&res, "transfer_position", transfer_keys, true, 1);
+        assert_eq!(
+            event_attr(&res, "transfer_position", "previous_owner"),
+            "bob"
+        );
+
+        // pause and resume by the protocol admin and by governance
+        let res = exec(
+            deps.as_mut(),
+            "protocol_admin",
+            &[],
+            2_500_000,
+            ExecuteMsg::PauseStream { stream_id: 1 },
+        );
+        assert_events(&res, "pause_stream", &["sudo", "pause_date"], false, 1);
+        assert_eq!(event_attr(&res, "pause_stream", "sudo"), "false");
+        let msg = ExecuteMsg::WithdrawPaused {
+            stream_id: 1,
+            cap: Some(Uint128::new(100)),
+            operator_target: None,
+            recipient: None,
+        };
+        let res = exec(deps.as_mut(), "dave", &[], 2_600_000, msg);
+        assert_events(&res, "withdraw", withdraw_keys, true, 1);
+        let res = exec(
+            deps.as_mut(),
+            "protocol_admin",
+            &[],
+            3_000_000,
+            ExecuteMsg::ResumeStream { stream_id: 1 },
+        );
+        assert_events(&res, "resume_stream", &["sudo"], false, 1);
+        assert_eq!(
+            event_attr(&res, "resume_stream", "stream_end_time"),
+            "5500000.000000000"
+        );
+        let res = sudo_at(
+            deps.as_mut(),
+            3_100_000,
+            crate::msg::SudoMsg::PauseStream { stream_id: 1 },
+        );
+        assert_events(&res, "pause_stream", &["sudo", "pause_date"], false, 1);
+        assert_eq!(event_attr(&res, "pause_stream", "sudo"), "true");
+        let res = sudo_at(
+            deps.as_mut(),
+            3_200_000,
+            crate::msg::SudoMsg::ResumeStream { stream_id: 1 },
+        );
+        assert_events(&res, "resume_stream", &["sudo"], false, 1);
+        assert_eq!(res.attributes[0], attr("action", "sudo_resume_stream"));
+
+        // finalize and exits after the postponed end
+        let msg = ExecuteMsg::FinalizeStream {
+            stream_id: 1,
+            new_treasury: None,
+        };
+        let res = exec(deps.as_mut(), "treasury", &[], 6_000_000, msg);
+        let finalize_keys = &[
+            "treasury",
+            "creator_revenue",
+            "swap_fee",
+            "creation_fee_denom",
+            "creation_fee",
+        ];
+        assert_events(&res, "finalize_stream", finalize_keys, false, 1);
+        assert_eq!(
+            event_attr(&res, "finalize_stream", "stream_status"),
+            "finalized"
+        );
+        let exit_keys = &[
+            "recipient",
+            "purchased",
+            "refund",
+            "swap_fee",
+            "swap_fee_out",
+            "keeper",
+        ];
+        let msg = ExecuteMsg::ExitStream {
+            stream_id: 1,
+            operator_target: None,
+            recipient: None,
+        };
+        let res = exec(deps.as_mut(), "carol", &[], 6_000_000, msg);
+        assert_events(&res, "exit_stream", exit_keys, true, 1);
+        assert_eq!(event_attr(&res, "exit_stream", "keeper"), "none");
+        let msg = ExecuteMsg::DistributeExits {
+            stream_id: 1,
+            limit: None,
+        };
+        let res = exec(deps.as_mut(), "keeper", &[], 6_000_000, msg);
+        assert_events(&res, "exit_stream", exit_keys, true, 1);
+        assert_eq!(event_attr(&res, "exit_stream", "keeper"), "keeper");
+        assert_eq!(event_attr(&res, "exit_stream", "stream_shares"), "0");
+
+        // cancel paths and refunds
+        let cancel_keys = &["reason", "out_refund"];
+        let exit_cancelled_keys = &["recipient", "refund", "treasury_refund"];
+        exec(
+            deps.as_mut(),
+            "protocol_admin",
+            &[],
+            2_000_000,
+            ExecuteMsg::PauseStream { stream_id: 2 },
+        );
+        let res = exec(
+            deps.as_mut(),
+            "protocol_admin",
+            &[],
+            2_000_000,
+            ExecuteMsg::CancelStream { stream_id: 2 },
+        );
+        assert_events(&res, "cancel_stream", cancel_keys, false, 1);
+        assert_eq!(
+            event_attr(&res, "cancel_stream", "reason"),
+            "protocol_admin"
+        );
+        let msg = ExecuteMsg::ExitCancelled {
+            stream_id: 2,
+            operator_target: None,
+            recipient: None,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 2_000_000, msg);
+        assert_events(&res, "exit_cancelled", exit_cancelled_keys, true, 1);
+        assert_eq!(res.attributes[0], attr("action", "exit_cancelled"));
+        let msg = ExecuteMsg::ProcessRefunds {
+            stream_id: 2,
+            limit: None,
+        };
+        let res = exec(deps.as_mut(), "anyone", &[], 2_000_000, msg);
+        assert_events(&res, "exit_cancelled", exit_cancelled_keys, true, 1);
+        assert_eq!(event_attr(&res, "exit_cancelled", "refund"), "1000");
+
+        let msg = ExecuteMsg::CancelStreamWithThreshold { stream_id: 3 };
+        let res = exec(deps.as_mut(), "treasury", &[], 5_000_000, msg);
+        assert_events(&res, "cancel_stream", cancel_keys, false, 1);
+        assert_eq!(event_attr(&res, "cancel_stream", "reason"), "threshold");
+        assert_eq!(
+            res.attributes[0],
+            attr("action", "cancel_stream_with_threshold")
+        );
+
+        sudo_at(
+            deps.as_mut(),
+            2_000_000,
+            crate::msg::SudoMsg::PauseStream { stream_id: 4 },
+        );
+        let res = sudo_at(
+            deps.as_mut(),
+            2_000_000,
+            crate::msg::SudoMsg::CancelStream { stream_id: 4 },
+        );
+        assert_events(&res, "cancel_stream", cancel_keys, false, 1);
+        assert_eq!(event_attr(&res, "cancel_stream", "reason"), "sudo");
+        assert_eq!(
+            event_attr(&res, "cancel_stream", "stream_status"),
+            "cancelled"
+        );
+        assert_eq!(res.attributes[0], attr("action", "sudo_cancel_stream"));
+
+        // operators of a position and of all positions of an owner
+        let msg = ExecuteMsg::UpdateOperator {
+            stream_id: 3,
+            new_operator: Some("bot".to_string()),
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(&res, "update_operator", &["stream_id", "owner", "operator"]);
+        let msg = ExecuteMsg::GrantOperator {
+            stream_id: 3,
+            operator: "updater".to_string(),
+            permissions: vec![OperatorPermission::Update, OperatorPermission::Exit],
+            expires: None,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        let grant_keys = &["stream_id", "owner", "operator", "permissions", "expires"];
+        assert_event_keys(&res, "grant_operator", grant_keys);
+        assert_eq!(
+            event_attr(&res, "grant_operator", "permissions"),
+            "update,exit"
+        );
+        let msg = ExecuteMsg::RevokeOperator {
+            stream_id: 3,
+            operator: "updater".to_string(),
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(&res, "revoke_operator", &["stream_id", "owner", "operator"]);
+        let msg = ExecuteMsg::ApproveOperator {
+            operator: "manager".to_string(),
+            expires: None,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(&res, "approve_operator", &["owner", "operator", "expires"]);
+        let msg = ExecuteMsg::RevokeOperatorApproval {
+            operator: "manager".to_string(),
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(&res, "revoke_operator_approval", &["owner", "operator"]);
+
+        // cw721 approvals
+        let token_id = query_position(deps.as_ref(), mock_env(), 3, "alice".to_string())
+            .unwrap()
+            .token_id
+            .unwrap();
+        let msg = ExecuteMsg::Approve {
+            spender: "market".to_string(),
+            token_id: token_id.clone(),
+            expires: None,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        let approve_keys = &["stream_id", "owner", "spender", "token_id", "expires"];
+        assert_event_keys(&res, "approve", approve_keys);
+        assert_eq!(event_attr(&res, "approve", "stream_id"), "3");
+        let msg = ExecuteMsg::Revoke {
+            spender: "market".to_string(),
+            token_id,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(
+            &res,
+            "revoke",
+            &["stream_id", "owner", "spender", "token_id"],
+        );
+        let msg = ExecuteMsg::ApproveAll {
+            operator: "market".to_string(),
+            expires: None,
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(&res, "approve_all", &["owner", "operator", "expires"]);
+        let msg = ExecuteMsg::RevokeAll {
+            operator: "market".to_string(),
+        };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(&res, "revoke_all", &["owner", "operator"]);
+        let token_id = query_position(deps.as_ref(), mock_env(), 3, "alice".to_string())
+            .unwrap()
+            .token_id
+            .unwrap();
+        let msg = ExecuteMsg::Burn { token_id };
+        let res = exec(deps.as_mut(), "alice", &[], 5_000_000, msg);
+        assert_event_keys(&res, "burn", &["stream_id", "owner", "token_id"]);
+
+        // fee discounts
+        let discount = FeeDiscount {
+            creation_fee_discount: Decimal::percent(50),
+            exit_fee_discount: Decimal::percent(20),
+        };
+        let discount_keys = &["address", "creation_fee_discount", "exit_fee_discount"];
+        let msg = ExecuteMsg::SetFeeDiscount {
+            address: "partner".to_string(),
+            discount: Some(discount.clone()),
+        };
+        let res = exec(deps.as_mut(), "protocol_admin", &[], 5_000_000, msg);
+        assert_event_keys(&res, "set_fee_discount", discount_keys);
+        assert_eq!(
+            event_attr(&res, "set_fee_discount", "exit_fee_discount"),
+            "0.2"
+        );
+        let msg = ExecuteMsg::SetFeeDiscount {
+            address: "partner".to_string(),
+            discount: None,
+        };
+        let res = exec(deps.as_mut(), "protocol_admin", &[], 5_000_000, msg);
+        assert_event_keys(&res, "set_fee_discount", discount_keys);
+        assert_eq!(
+            event_attr(&res, "set_fee_discount", "creation_fee_discount"),
+            "none"
+        );
+        let msg = ExecuteMsg::SetHolderFeeDiscounts {
+            discounts: vec![HolderFeeDiscount {
+                denom: "gov".to_string(),
+                min_balance: Uint128::new(1_000),
+                discount,
+            }],
+        };
+        let res = exec(deps.as_mut(), "protocol_admin", &[], 5_000_000, msg);
+        assert_event_keys(&res, "set_holder_fee_discounts", &["discounts"]);
+        assert_eq!(
+            event_attr(&res, "set_holder_fee_discounts", "discounts"),
+            "gov:1000:0.5:0.2"
+        );
+
+        // hooks and fees
+        let msg = ExecuteMsg::AddHook {
+            stream_id: None,
+            contract: "indexer".to_string(),
+        };
+        let res = exec(deps.as_mut(), "protocol_admin", &[], 6_000_000, msg);
+        assert_event_keys(&res, "add_hook", &["stream_id", "contract"]);
+        assert_eq!(event_attr(&res, "add_hook", "stream_id"), "all");
+        let msg = ExecuteMsg::RemoveHook {
+            stream_id: None,
+            contract: "indexer".to_string(),
+        };
+        let res = exec(deps.as_mut(), "protocol_admin", &[], 6_000_000, msg);
+        assert_event_keys(&res, "remove_hook", &["stream_id", "contract"]);
+        let msg = ExecuteMsg::ClaimFees {
+            collector: None,
+            recipient: None,
+        };
+        let res = exec(deps.as_mut(), "collector", &[], 6_000_000, msg);
+        assert_event_keys(&res, "claim_fees", &["collector", "recipient", "fees"]);
+        let msg = ExecuteMsg::UpdateProtocolAdmin {
+            new_protocol_admin: "new_admin".to_string(),
+        };
+        let res = exec(deps.as_mut(), "protocol_admin", &[], 6_000_000, msg);
+        let admin_keys = &["previous_protocol_admin", "protocol_admin"];
+        assert_event_keys(&res, "update_protocol_admin", admin_keys);
+        assert_eq!(
+            event_attr(&res, "update_protocol_admin", "protocol_admin"),
+            "new_admin"
+        );
+    }
+
+    #[test]
+    fn test_pre_start_commitments() {
+        use crate::msg::ExecuteMsg;
+
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator",
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            None,
+            "in".to_string(),
+            "out".to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // deposits before start are commitments and keep the stream waiting
+        let mut env = mock_env();
+        env.block.time = start.minus_seconds(100);
+        let subscribe = ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        for (subscriber, amount) in [("alice", 1_000), ("bob", 500)] {
+            let info = mock_info(subscriber, &[Coin::new(amount, "in")]);
+            execute(deps.as_mut(), env.clone(), info, subscribe.clone()).unwrap();
+        }
+        let committed = query_pre_committed(deps.as_ref(), 1).unwrap();
+        assert_eq!(committed.pre_committed_in, Uint128::new(1_500));
+        assert_eq!(committed.in_denom, "in");
+        assert_eq!(committed.start_time, start);
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(stream.status, Status::Waiting);
+
+        // commitments can be withdrawn in full until start
+        let msg = ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "bob".to_string(),
+                amount: vec![Coin::new(500, "in")],
+            })
+        );
+        let committed = query_pre_committed(deps.as_ref(), 1).unwrap();
+        assert_eq!(committed.pre_committed_in, Uint128::new(1_000));
+
+        // syncing before start does not activate the stream
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("anyone", &[]),
+            ExecuteMsg::UpdateStream { stream_id: 1 },
+        )
+        .unwrap();
+        let stream = STREAMS.load(&deps.storage, 1).unwrap();
+        assert_eq!(stream.status, Status::Waiting);
+
+        // without any action after start, finalize activates and finalizes the stream
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let msg = ExecuteMsg::FinalizeStream {
+            stream_id: 1,
+            new_treasury: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("treasury", &[]), msg).unwrap();
+        let stream = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(stream.status, Status::Finalized);
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.waiting_streams, 0);
+        assert_eq!(stats.finalized_streams, 1);
+
+        // commitments are kept as is once the stream starts
+        let committed = query_pre_committed(deps.as_ref(), 1).unwrap();
+        assert_eq!(committed.pre_committed_in, Uint128::new(1_000));
+    }
+
+    #[test]
+    fn test_update_stream() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(1000),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(1);
+        let info = mock_info(
+            "creator",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        //update stream without subscription this means no new  distribution so returned index should be 0
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(100);
+        let res = execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "update_stream"),
+                attr("stream_id", "1"),
+                attr("new_distribution_amount", "0"),
+                attr("dist_index", "0"),
+            ]
+        );
+        //first subscription
+        //On first subscription index is not incresed because no distrubution prior to that(Execute_subscibe also includes update_stream)
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(100);
+        let info = mock_info("creator1", &[Coin::new(1_000_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: Some("creator1".to_string()),
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg);
+
+        //Query stream
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(200);
+        let res = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(res.dist_index, Decimal256::zero());
+
+        //Update stream again, this time with subscriber
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(300);
+        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+
+        //Query stream
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(300);
+        let res = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(res.dist_index, Decimal256::from_str("0.00005").unwrap())
+    }
+    #[test]
+    fn test_update_position() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(1000),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(1);
+        let info = mock_info(
+            "creator",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(100);
+        let info = mock_info("creator1", &[Coin::new(1_000_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg);
+
+        // non owner operator cannot update position
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        let info = mock_info("random", &[]);
+        let err =
+            execute_update_position(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
+                .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // update position
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        let info = mock_info("creator1", &[]);
+        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "creator1".to_string()).unwrap();
+        assert_eq!(
+            position.index,
+            Decimal256::from_str("0.749993000000000000").unwrap()
+        );
+        assert_eq!(position.purchased, Uint128::new(749_993));
+        assert_eq!(position.spent, Uint128::new(749_993));
+        assert_eq!(position.in_balance, Uint128::new(250_007));
+        let stream = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(
+            stream.dist_index,
+            Decimal256::from_str("0.749993000000000000").unwrap()
+        );
+
+        // can update position after stream ends
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info("creator1", &[]);
+        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(stream.dist_index, Decimal256::from_str("1").unwrap());
+        assert_eq!(stream.in_supply, Uint128::zero());
+        let position = query_position(deps.as_ref(), env, 1, "creator1".to_string()).unwrap();
+        assert_eq!(position.index, Decimal256::from_str("1").unwrap());
+        assert_eq!(position.spent, Uint128::new(1_000_000));
+        assert_eq!(position.in_balance, Uint128::zero());
+
+        assert_eq!(stream.out_supply, Uint128::new(1_000_000));
+        assert_eq!(position.purchased, stream.out_supply);
+    }
+
+    // this is for testing the leftover amount with bigger values
+    #[test]
+    fn test_rounding_leftover() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(1000),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(1);
+        let info = mock_info(
+            "creator",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(100);
+        let info = mock_info("creator1", &[Coin::new(1_000_000_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // second subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(100_000);
+        let info = mock_info("creator2", &[Coin::new(3_000_000_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // update position creator1
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        let info = mock_info("creator1", &[]);
+        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "creator1".to_string()).unwrap();
+        assert_eq!(
+            position.index,
+            Decimal256::from_str("202.813614449380587585").unwrap()
+        );
+        assert_eq!(position.purchased, Uint128::new(202_813_614_449));
+        assert_eq!(position.spent, Uint128::new(749_993_750));
+        assert_eq!(position.in_balance, Uint128::new(250_006_250));
+        let stream = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(
+            stream.dist_index,
+            Decimal256::from_str("202.813614449380587585").unwrap()
+        );
+
+        // update position creator2
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_575_000);
+        let info = mock_info("creator2", &[]);
+        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "creator2".to_string()).unwrap();
+        assert_eq!(
+            position.index,
+            Decimal256::from_str("238.074595237060799266").unwrap()
+        );
+        assert_eq!(position.purchased, Uint128::new(655672748445));
+        assert_eq!(position.spent, Uint128::new(2673076923));
+        assert_eq!(position.in_balance, Uint128::new(326923077));
+        let stream = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(
+            stream.dist_index,
+            Decimal256::from_str("238.074595237060799266").unwrap()
+        );
+
+        // update position after stream ends
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info("creator1", &[]);
+        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(
+            stream.dist_index,
+            Decimal256::from_str("264.137059297637397644").unwrap()
+        );
+        assert_eq!(stream.in_supply, Uint128::zero());
+        let position1 = query_position(deps.as_ref(), env, 1, "creator1".to_string()).unwrap();
+        assert_eq!(
+            position1.index,
+            Decimal256::from_str("264.137059297637397644").unwrap()
+        );
+        assert_eq!(position1.spent, Uint128::new(1_000_000_000));
+        assert_eq!(position1.in_balance, Uint128::zero());
+
+        // update position after stream ends
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info("creator2", &[]);
+        execute_update_position(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(
+            stream.dist_index,
+            Decimal256::from_str("264.137059297637397644").unwrap()
+        );
+        assert_eq!(stream.in_supply, Uint128::zero());
+        let position2 = query_position(deps.as_ref(), env, 1, "creator2".to_string()).unwrap();
+        assert_eq!(
+            position2.index,
+            Decimal256::from_str("264.137059297637397644").unwrap()
+        );
+        assert_eq!(position2.spent, Uint128::new(3_000_000_000));
+        assert_eq!(position2.in_balance, Uint128::zero());
+
+        assert_eq!(stream.out_remaining, Uint128::zero());
+        assert_eq!(
+            position1
+                .purchased
+                .checked_add(position2.purchased)
+                .unwrap(),
+            // 1 difference due to rounding
+            stream.out_supply.sub(Uint128::new(1u128))
+        );
+    }
+
+    #[test]
+    fn test_withdraw() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(0);
+        let funds = Coin::new(2_000_000_000_000, "in");
+        let info = mock_info("creator1", std::slice::from_ref(&funds));
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // withdraw with cap
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(5000);
+        let info = mock_info("creator1", &[]);
+        // withdraw amount zero
+        let cap = Uint128::zero();
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(cap),
+            operator_target: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidWithdrawAmount {});
+        // withdraw amount too high
+        let cap = Uint128::new(2_250_000_000_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(cap),
+            operator_target: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::WithdrawAmountExceedsBalance(Uint128::new(2250000000000))
+        );
+        //withdraw with valid cap
+        let cap = Uint128::new(25_000_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: Some(cap),
+            operator_target: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let position =
+            query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
+        assert_eq!(position.in_balance, Uint128::new(1_997_475_000_000));
+        assert_eq!(position.spent, Uint128::new(2_500_000_000));
+        assert_eq!(position.purchased, Uint128::new(1_250_000_000));
+        // first fund amount should be equal to in_balance + spent + cap
+        assert_eq!(position.in_balance + position.spent + cap, funds.amount);
+
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let info = mock_info("creator1", &[]);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let position =
+            query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
+        assert_eq!(position.in_balance, Uint128::zero());
+        assert_eq!(position.spent, Uint128::new(499_993_773_466));
+        assert_eq!(position.purchased, Uint128::new(249_999_999_998));
+        assert_eq!(position.shares, Uint128::zero());
+        let msg = res.messages.first().unwrap();
+        assert_eq!(
+            msg.msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator1".to_string(),
+                amount: vec![Coin::new(1_499_981_226_534, "in")]
+            })
+        );
+
+        // can't withdraw after stream ends
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info("creator1", &[]);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::StreamEnded {});
+    }
+
+    #[test]
+    fn test_finalize_stream() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(2_000_000_000_000, "in");
+        let info = mock_info("creator1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // only treasury can finalize
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info("random", &[]);
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // can't finalize before stream ends
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1);
+        let info = mock_info(treasury.as_str(), &[]);
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
+        assert_eq!(res, ContractError::StreamNotEnded {});
+
+        // happy path
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info(treasury.as_str(), &[]);
+        execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
+
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "finalize_stream"),
+                attr("stream_id", "1"),
+                attr("treasury", "treasury"),
+                attr("fee_collectors", "collector:1"),
+                attr("creators_revenue", "1980000000000"),
+                attr("refunded_out_remaining", "0"),
+                attr("total_sold", "1000000000000"),
+                attr("swap_fee", "20000000000"),
+                attr("creation_fee", "100"),
+                attr("creation_fee_denom", "fee"),
+            ]
+        );
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![Coin {
+                    denom: "in".to_string(),
+                    amount: Uint128::new(1_980_000_000_000),
+                }],
+            }),],
+        );
+        // fees are accrued to the collector
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector"),
+            vec![Coin::new(100, "fee"), Coin::new(20_000_000_000, "in")]
+        );
+    }
+
+    #[test]
+    fn test_creation_fee_options() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        let mut deps = mock_dependencies();
+        let mut msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+
+        // options need distinct denoms and non zero amounts
+        for invalid in [
+            vec![Coin::new(0, "alt")],
+            vec![Coin::new(50, "fee")],
+            vec![Coin::new(50, "alt"), Coin::new(60, "alt")],
+        ] {
+            msg.stream_creation_fee_options = invalid;
+            let err = instantiate(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("creator", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidStreamCreationFeeOptions {});
+        }
+
+        msg.stream_creation_fee_options = vec![Coin::new(50, "alt"), Coin::new(10, out_denom)];
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let create = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, funds: &[Coin]| {
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                mock_info("creator1", funds),
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+            )
+        };
+
+        // wrong amount of an alternative fee
+        let err = create(
+            &mut deps,
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(49, "alt"),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamCreationFeeRequired {});
+
+        // only one fee option can be paid
+        let err = create(
+            &mut deps,
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(50, "alt"),
+                Coin::new(100, "fee"),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidFunds {});
+
+        // alternative fee is stored on the stream
+        create(
+            &mut deps,
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(50, "alt"),
+            ],
+        )
+        .unwrap();
+        let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(stream.stream_creation_denom, "alt");
+        assert_eq!(stream.stream_creation_fee, Uint128::new(50));
+
+        // fee paid in out denom
+        create(&mut deps, &[Coin::new(out_supply.u128() + 10, out_denom)]).unwrap();
+        let stream = query_stream(deps.as_ref(), mock_env(), 2).unwrap();
+        assert_eq!(stream.stream_creation_denom, out_denom);
+        assert_eq!(stream.stream_creation_fee, Uint128::new(10));
+
+        // cancel refunds the paid option
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1);
+        let info = mock_info("protocol_admin", &[]);
+        execute_pause_stream(deps.as_mut(), env.clone(), info.clone(), 1).unwrap();
+        let res = execute_cancel_stream(deps.as_mut(), env, info, 1).unwrap();
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(BankMsg::Send {
+                to_address: treasury.to_string(),
+                amount: vec![Coin::new(50, "alt")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_fee_discounts() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(2),
+            exit_fee_tiers: vec![ExitFeeTier {
+                min_spent: Uint128::new(1_000),
+                fee_percent: Decimal::percent(1),
+            }],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let create =
+            |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, creator: &str, funds: &[Coin]| {
+                let mut env = mock_env();
+                env.block.time = Timestamp::from_seconds(0);
+                execute_create_stream(
+                    deps.as_mut(),
+                    env,
+                    mock_info(creator, funds),
+                    "treasury".to_string(),
+                    "test".to_string(),
+                    Some("https://sample.url".to_string()),
+                    "in".to_string(),
+                    out_denom.to_string(),
+                    out_supply,
+                    start,
+                    end,
+                    None,
+                )
+            };
+
+        // only protocol admin can manage discounts
+        let discount = FeeDiscount {
+            creation_fee_discount: Decimal::percent(50),
+            exit_fee_discount: Decimal::percent(50),
+        };
+        let msg = crate::msg::ExecuteMsg::SetFeeDiscount {
+            address: "whitelisted".to_string(),
+            discount: Some(discount.clone()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // discounts can not exceed one
+        let invalid = crate::msg::ExecuteMsg::SetFeeDiscount {
+            address: "whitelisted".to_string(),
+            discount: Some(FeeDiscount {
+                creation_fee_discount: Decimal::percent(101),
+                exit_fee_discount: Decimal::zero(),
+            }),
+        };
+        let info = mock_info("protocol_admin", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), invalid).unwrap_err();
+        assert_eq!(err, ContractError::InvalidFeeDiscount {});
+
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = query_fee_discount(deps.as_ref(), "whitelisted".to_string()).unwrap();
+        assert_eq!(res.discount, Some(discount.clone()));
+
+        // full fee is not accepted from a discounted creator
+        let err = create(
+            &mut deps,
+            "whitelisted",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamCreationFeeRequired {});
+
+        // discount is snapshotted on the stream
+        create(
+            &mut deps,
+            "whitelisted",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(50, "fee"),
+            ],
+        )
+        .unwrap();
+        let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(stream.stream_creation_fee, Uint128::new(50));
+        assert_eq!(stream.exit_fee_percent, Decimal::percent(1));
+        assert_eq!(stream.exit_fee_tiers[0].fee_percent, Decimal::permille(5));
+        assert_eq!(stream.fee_discount, Some(discount));
+
+        // holders of a denom get the first matching discount
+        let msg = crate::msg::ExecuteMsg::SetHolderFeeDiscounts {
+            discounts: vec![
+                HolderFeeDiscount {
+                    denom: "gov".to_string(),
+                    min_balance: Uint128::new(1_000),
+                    discount: FeeDiscount {
+                        creation_fee_discount: Decimal::one(),
+                        exit_fee_discount: Decimal::zero(),
+                    },
+                },
+                HolderFeeDiscount {
+                    denom: "gov".to_string(),
+                    min_balance: Uint128::new(100),
+                    discount: FeeDiscount {
+                        creation_fee_discount: Decimal::percent(10),
+                        exit_fee_discount: Decimal::zero(),
+                    },
+                },
+            ],
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        deps.querier
+            .update_balance("small_holder", vec![Coin::new(100, "gov")]);
+        deps.querier
+            .update_balance("large_holder", vec![Coin::new(1_000, "gov")]);
+        let res = query_fee_discount(deps.as_ref(), "random".to_string()).unwrap();
+        assert_eq!(res.discount, None);
+
+        create(
+            &mut deps,
+            "small_holder",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(90, "fee"),
+            ],
+        )
+        .unwrap();
+        let stream = query_stream(deps.as_ref(), mock_env(), 2).unwrap();
+        assert_eq!(stream.stream_creation_fee, Uint128::new(90));
+        assert_eq!(stream.exit_fee_percent, Decimal::percent(2));
+
+        // fully waived creation fee is not sent
+        create(
+            &mut deps,
+            "large_holder",
+            &[Coin::new(out_supply.u128(), out_denom)],
+        )
+        .unwrap();
+        let stream = query_stream(deps.as_ref(), mock_env(), 3).unwrap();
+        assert_eq!(stream.stream_creation_fee, Uint128::zero());
+    }
+
+    #[test]
+    fn test_fee_side() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        let mut deps = mock_dependencies();
+        let mut msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(2),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::Both {
+                out_share: Decimal::one(),
+            },
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidFeeSide {});
+
+        msg.fee_side = FeeSide::Both {
+            out_share: Decimal::percent(50),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // config changes do not affect existing streams
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: None,
+            fee_side: Some(FeeSide::In),
+            keeper_reward_percent: None,
+            position_nfts: None,
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(query_config(deps.as_ref()).unwrap().fee_side, FeeSide::In);
+
+        let mut env = mock_env();
+        env.block.time = start;
+        let info = mock_info("subscriber", &[Coin::new(2_000_000_000_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // finalize withholds only the in side of the fee
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res = execute_finalize_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(treasury.as_str(), &[]),
+            1,
+            None,
+        )
+        .unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("creators_revenue", "1980000000000")));
+        assert!(res.attributes.contains(&attr("swap_fee", "20000000000")));
+
+        // exit sends the out side of the fee to the collector
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber", &[]),
+            1,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "subscriber".to_string(),
+                amount: vec![Coin::new(990_000_000_000, out_denom)],
+            }),]
+        );
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector"),
+            vec![
+                Coin::new(100, "fee"),
+                Coin::new(20_000_000_000, "in"),
+                Coin::new(10_000_000_000, out_denom),
+            ]
+        );
+        assert!(res
+            .attributes
+            .contains(&attr("swap_fee_out", "10000000000")));
+        let stream = query_stream(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(stream.out_remaining, Uint128::zero());
+    }
+
+    #[test]
+    fn test_fee_collectors() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+        let collectors = vec![
+            FeeCollectorMsg {
+                address: "collector1".to_string(),
+                weight: Decimal::percent(30),
+            },
+            FeeCollectorMsg {
+                address: "collector2".to_string(),
+                weight: Decimal::percent(70),
+            },
+        ];
+
+        let mut deps = mock_dependencies();
+        let mut msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(101),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: vec![],
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+
+        // weights must sum up to one
+        for invalid in [
+            vec![],
+            vec![FeeCollectorMsg {
+                address: "collector1".to_string(),
+                weight: Decimal::percent(99),
+            }],
+            vec![
+                FeeCollectorMsg {
+                    address: "collector1".to_string(),
+                    weight: Decimal::one(),
+                },
+                FeeCollectorMsg {
+                    address: "collector2".to_string(),
+                    weight: Decimal::zero(),
+                },
+            ],
+            vec![
+                FeeCollectorMsg {
+                    address: "collector1".to_string(),
+                    weight: Decimal::percent(50),
+                },
+                FeeCollectorMsg {
+                    address: "collector1".to_string(),
+                    weight: Decimal::percent(50),
+                },
+            ],
+        ] {
+            msg.fee_collectors = invalid;
+            let err = instantiate(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("creator", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::InvalidFeeCollectors {});
+        }
+
+        msg.fee_collectors = collectors;
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(101, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let info = mock_info("creator1", &[Coin::new(2_000_000_000_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // fees are split by weight, rounding leftover goes to the first collector
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info(treasury.as_str(), &[]);
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("fee_collectors", "collector1:0.3,collector2:0.7")));
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector1"),
+            vec![Coin::new(31, "fee"), Coin::new(6_000_000_000, "in")]
+        );
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector2"),
+            vec![Coin::new(70, "fee"), Coin::new(14_000_000_000, "in")]
+        );
+
+        // only the collector or protocol admin can claim
+        let msg = crate::msg::ExecuteMsg::ClaimFees {
+            collector: Some("collector1".to_string()),
+            recipient: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("random", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        let msg = crate::msg::ExecuteMsg::ClaimFees {
+            collector: None,
+            recipient: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("collector1", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "collector1".to_string(),
+                amount: vec![Coin::new(31, "fee"), Coin::new(6_000_000_000, "in")],
+            })]
+        );
+        assert_eq!(unclaimed_fees(deps.as_ref(), "collector1"), vec![]);
+        let err =
+            execute(deps.as_mut(), mock_env(), mock_info("collector1", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoFeesToClaim {});
+
+        // protocol admin can't send fees of a collector elsewhere
+        let msg = crate::msg::ExecuteMsg::ClaimFees {
+            collector: Some("collector2".to_string()),
+            recipient: Some("recovery".to_string()),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // protocol admin claims to the collector by default
+        let msg = crate::msg::ExecuteMsg::ClaimFees {
+            collector: Some("collector2".to_string()),
+            recipient: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "collector2".to_string(),
+                amount: vec![Coin::new(70, "fee"), Coin::new(14_000_000_000, "in")],
+            })]
+        );
+
+        // update config validates collectors
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: Some(vec![FeeCollectorMsg {
+                address: "collector1".to_string(),
+                weight: Decimal::percent(30),
+            }]),
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
+        };
+        let info = mock_info("protocol_admin", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidFeeCollectors {});
+
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: Some(single_collector("collector3")),
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let config = query_config(deps.as_ref()).unwrap();
+        assert_eq!(
+            config.fee_collectors,
+            vec![FeeCollector {
+                address: Addr::unchecked("collector3"),
+                weight: Decimal::one(),
+            }]
+        );
+    }
+    #[test]
+    fn test_recurring_finalize_stream_calls() {
+        let malicious_treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(10);
+        let end = Timestamp::from_seconds(110);
+        let out_supply = Uint128::new(1000);
+        let out_denom = "myToken";
+        let in_denom = "uosmo";
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(100),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: in_denom.to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        // Create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            malicious_treasury.as_str(),
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            malicious_treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            in_denom.to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+        // First subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1);
+        let funds = Coin::new(200, in_denom.to_string());
+        let info = mock_info("user1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        // Update
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info(malicious_treasury.as_str(), &[]);
+        execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
+        // First call
+        let res =
+            execute_finalize_stream(deps.as_mut(), env.clone(), info.clone(), 1, None).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: malicious_treasury.to_string(),
+                amount: vec![Coin {
+                    denom: in_denom.to_string(),
+                    amount: Uint128::new(198),
+                }],
+            }),],
+        );
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector"),
+            vec![Coin::new(100, "fee"), Coin::new(2, in_denom)]
+        );
+        // Check stream status
+        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+        assert_eq!(stream.status, Status::Finalized);
+        // Sequential calls, anyone could force this sequential calls
+        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
+        assert_eq!(res, ContractError::StreamAlreadyFinalized {});
+    }
+
+    #[test]
+    fn test_exit_stream() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(2_000_000_000_000, "in");
+        let info = mock_info("creator1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // can't exit before stream ends
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(2_000_000);
+        let info = mock_info("creator1", &[]);
+        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None, None).unwrap_err();
+        assert_eq!(res, ContractError::StreamNotEnded {});
+
+        //failed exit from random address
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(3_000_000);
+        let info = mock_info("random", &[]);
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            1,
+            Some("creator1".to_string()),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+        // can exit
+        let info = mock_info("creator1", &[]);
+        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None, None).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator1".to_string(),
+                amount: vec![Coin::new(
+                    Uint128::new(1_000_000_000_000).u128(),
+                    "out_denom"
+                )]
+            }))]
+        );
+
+        // position deleted
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(4_000_000);
+        let info = mock_info("creator1", &[]);
+        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None, None).unwrap_err();
+        assert!(matches!(res, ContractError::Std(StdError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_distribute_exits() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(6_000_000);
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // keeper reward is a share of the exit fee
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: Some(Decimal::percent(101)),
+            position_nfts: None,
+        };
+        let info = mock_info("protocol_admin", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidKeeperRewardPercent {});
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: Some(Decimal::percent(10)),
+            position_nfts: None,
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            query_config(deps.as_ref()).unwrap().keeper_reward_percent,
+            Decimal::percent(10)
+        );
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator",
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            "treasury".to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            "out".to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = start;
+        for (subscriber, amount) in [
+            ("sub1", 1_000_000),
+            ("sub2", 2_000_000),
+            ("sub3", 3_000_000),
+        ] {
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+            };
+            let info = mock_info(subscriber, &[Coin::new(amount, "in")]);
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // can't distribute before the stream ends
+        let msg = crate::msg::ExecuteMsg::DistributeExits {
+            stream_id: 1,
+            limit: Some(2),
+        };
+        env.block.time = end;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamNotEnded {});
+
+        // keeper exits positions on behalf of owners and gets a share of their exit fee
+        env.block.time = end.plus_seconds(1);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            crate::msg::ExecuteMsg::DistributeExits {
+                stream_id: 1,
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "sub1".to_string(),
+                    amount: vec![Coin::new(1_000_000, "out")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "keeper".to_string(),
+                    amount: vec![Coin::new(1_000, "in")],
+                }),
+            ]
+        );
+        assert!(res.attributes.contains(&attr("exited_positions", "1")));
+        assert_eq!(
+            STREAMS.load(&deps.storage, 1).unwrap().keeper_rewards,
+            Uint128::new(1_000)
+        );
+
+        // finalize collects the fee left after keeper rewards, holding back the keeper share
+        // of the remaining positions
+        let res = execute_finalize_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("treasury", &[]),
+            1,
+            None,
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("swap_fee", "54000")));
+        assert!(res
+            .attributes
+            .contains(&attr("creators_revenue", "5940000")));
+        assert_eq!(
+            STREAMS.load(&deps.storage, 1).unwrap().keeper_reserve,
+            Uint128::new(5_000)
+        );
+
+        // the keeper share of a position exiting by itself goes to the collectors
+        let msg = crate::msg::ExecuteMsg::ExitStream {
+            stream_id: 1,
             operator_target: None,
+            recipient: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        assert_eq!(res, ContractError::InvalidWithdrawAmount {});
-        // withdraw amount too high
-        let cap = Uint128::new(2_250_000_000_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
+        execute(deps.as_mut(), env.clone(), mock_info("sub2", &[]), msg).unwrap();
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector"),
+            vec![Coin::new(100, "fee"), Coin::new(56_000, "in")]
+        );
+
+        // keeper gets its share of the fee of positions exited after finalize
+        let msg = crate::msg::ExecuteMsg::DistributeExits {
             stream_id: 1,
-            cap: Some(cap),
+            limit: Some(2),
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("keeper", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "sub3".to_string(),
+                    amount: vec![Coin::new(3_000_000, "out")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "keeper".to_string(),
+                    amount: vec![Coin::new(3_000, "in")],
+                }),
+            ]
+        );
+        assert_eq!(
+            STREAMS.load(&deps.storage, 1).unwrap().keeper_reserve,
+            Uint128::zero()
+        );
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector"),
+            vec![Coin::new(100, "fee"), Coin::new(56_000, "in")]
+        );
+        assert_eq!(query_stats(deps.as_ref()).unwrap().open_positions, 0);
+
+        let err = execute(deps.as_mut(), env, mock_info("keeper", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoExitsToDistribute {});
+    }
+
+    #[test]
+    fn test_claim_purchased() {
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // stream 1 without threshold, stream 2 with a threshold that is not reached
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        for threshold in [None, Some(Threshold::MinSpentIn(Uint128::new(10_000_000)))] {
+            let info = mock_info(
+                "creator",
+                &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                "out".to_string(),
+                out_supply,
+                start,
+                end,
+                threshold,
+            )
+            .unwrap();
+        }
+
+        let mut env = mock_env();
+        env.block.time = start;
+        for stream_id in [1, 2] {
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id,
+                operator_target: None,
+                operator: None,
+            };
+            let info = mock_info("subscriber", &[Coin::new(1_000_000, "in")]);
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // a quarter of the stream is purchased
+        env.block.time = start.plus_seconds(1_000_000);
+        let msg = crate::msg::ExecuteMsg::ClaimPurchased {
+            stream_id: 2,
+            cap: None,
             operator_target: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            msg,
+        )
+        .unwrap_err();
         assert_eq!(
-            res,
-            ContractError::WithdrawAmountExceedsBalance(Uint128::new(2250000000000))
+            err,
+            ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
         );
-        //withdraw with valid cap
-        let cap = Uint128::new(25_000_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
+
+        let msg = crate::msg::ExecuteMsg::ClaimPurchased {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("random", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+
+        let msg = crate::msg::ExecuteMsg::ClaimPurchased {
+            stream_id: 1,
+            cap: Some(Uint128::new(250_001)),
+            operator_target: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ClaimAmountExceedsPurchased(Uint128::new(250_001))
+        );
+
+        let msg = crate::msg::ExecuteMsg::ClaimPurchased {
+            stream_id: 1,
+            cap: Some(Uint128::new(100_000)),
+            operator_target: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "subscriber".to_string(),
+                amount: vec![Coin::new(100_000, "out")],
+            })]
+        );
+
+        // claims the rest of purchased tokens
+        let msg = crate::msg::ExecuteMsg::ClaimPurchased {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("claim_amount", "150000")));
+        assert!(res.attributes.contains(&attr("claimed", "250000")));
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidClaimAmount {});
+
+        // exit pays the remainder only
+        env.block.time = end;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("subscriber", &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StreamEnded {});
+        env.block.time = end.plus_seconds(1);
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env,
+            mock_info("subscriber", &[]),
+            1,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "subscriber".to_string(),
+                amount: vec![Coin::new(750_000, "out")],
+            })]
+        );
+    }
+
+    #[test]
+    fn test_withdraw_all_before_exit_case() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info(
+            "creator1",
+            &[
+                Coin::new(out_supply.u128(), out_denom),
+                Coin::new(100, "fee"),
+            ],
+        );
+        execute_create_stream(
+            deps.as_mut(),
+            env,
+            info,
+            treasury.to_string(),
+            "test".to_string(),
+            Some("https://sample.url".to_string()),
+            "in".to_string(),
+            out_denom.to_string(),
+            out_supply,
+            start,
+            end,
+            None,
+        )
+        .unwrap();
+
+        // first subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(2_000_000_000_000, "in");
+        let info = mock_info("creator1", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
-            cap: Some(cap),
             operator_target: None,
+            operator: None,
         };
         let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-        let position =
-            query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
-        assert_eq!(position.in_balance, Uint128::new(1_997_475_000_000));
-        assert_eq!(position.spent, Uint128::new(2_500_000_000));
-        assert_eq!(position.purchased, Uint128::new(1_250_000_000));
-        // first fund amount should be equal to in_balance + spent + cap
-        assert_eq!(position.in_balance + position.spent + cap, funds.amount);
 
+        // second subscription
         let mut env = mock_env();
         env.block.time = start.plus_seconds(1_000_000);
+        let funds = Coin::new(1_000_000_000_000, "in");
+        let info = mock_info("creator2", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // first withdraw
         let info = mock_info("creator1", &[]);
+        let mut env = mock_env();
+        env.block.time = end.minus_seconds(1_000_000);
         let msg = crate::msg::ExecuteMsg::Withdraw {
             stream_id: 1,
             cap: None,
             operator_target: None,
+            recipient: None,
         };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap();
-        let position =
-            query_position(deps.as_ref(), mock_env(), 1, "creator1".to_string()).unwrap();
-        assert_eq!(position.in_balance, Uint128::zero());
-        assert_eq!(position.spent, Uint128::new(499_993_773_466));
-        assert_eq!(position.purchased, Uint128::new(249_999_999_998));
-        assert_eq!(position.shares, Uint128::zero());
-        let msg = res.messages.get(0).unwrap();
-        assert_eq!(
-            msg.msg,
-            CosmosMsg::Bank(BankMsg::Send {
-                to_address: "creator1".to_string(),
-                amount: vec![Coin::new(1_499_981_226_534, "in")]
-            })
-        );
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-        // can't withdraw after stream ends
+        // second withdraw
+        let info = mock_info("creator2", &[]);
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(1);
+        env.block.time = end.minus_seconds(1_000_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // can exit
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1_000_000);
+        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1_000_001);
         let info = mock_info("creator1", &[]);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
-            stream_id: 1,
-            cap: None,
-            operator_target: None,
-        };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(res, ContractError::StreamEnded {});
+        execute_exit_stream(deps.as_mut(), env, info, 1, None, None).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1_000_002);
+        let info = mock_info("creator2", &[]);
+        execute_exit_stream(deps.as_mut(), env, info, 1, None, None).unwrap();
     }
 
     #[test]
-    fn test_finalize_stream() {
+    fn test_price_feed() {
         let treasury = Addr::unchecked("treasury");
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
-        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_supply = Uint128::new(1_000_000);
         let out_denom = "out_denom";
 
         // instantiate
@@ -1897,8 +5417,11 @@ mod test_module {
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -1933,7 +5456,7 @@ mod test_module {
         // first subscription
         let mut env = mock_env();
         env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(2_000_000_000_000, "in");
+        let funds = Coin::new(3_000, "in");
         let info = mock_info("creator1", &[funds]);
         let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
@@ -1942,110 +5465,291 @@ mod test_module {
         };
         let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-        // only treasury can finalize
+        //check current streamed price before update
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(1);
-        let info = mock_info("random", &[]);
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert_eq!(res, ContractError::Unauthorized {});
+        env.block.time = start.plus_seconds(2_000_000);
+        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(res.current_streamed_price, Decimal::new(Uint128::new(0)));
 
-        // can't finalize before stream ends
+        //check current streamed price after update
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(1);
-        let info = mock_info(treasury.as_str(), &[]);
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert_eq!(res, ContractError::StreamNotEnded {});
+        env.block.time = start.plus_seconds(2_000_000);
+        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        //approx 1000/333333
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal::from_str("0.002997002997002997").unwrap()
+        );
+        // second subscription
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(2_000_000);
+        let funds = Coin::new(1_000, "in");
+        let info = mock_info("creator2", &[funds]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
-        // happy path
+        //check current streamed price before update
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(1);
-        let info = mock_info(treasury.as_str(), &[]);
-        execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
+        env.block.time = start.plus_seconds(3_000_000);
+        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal::from_str("0.002997002997002997").unwrap()
+        );
 
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        //check current streamed price after update
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        //approx 2000/333333
         assert_eq!(
-            res.attributes,
-            vec![
-                attr("action", "finalize_stream"),
-                attr("stream_id", "1"),
-                attr("treasury", "treasury"),
-                attr("fee_collector", "collector"),
-                attr("creators_revenue", "1980000000000"),
-                attr("refunded_out_remaining", "0"),
-                attr("total_sold", "1000000000000"),
-                attr("swap_fee", "20000000000"),
-                attr("creation_fee", "100"),
-            ]
+            res.current_streamed_price,
+            Decimal::from_str("0.0045000045000045").unwrap()
         );
+
+        //check average streamed price
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_000_000);
+        let res = query_average_price(deps.as_ref(), env, 1).unwrap();
+        //approx 2500/333333
         assert_eq!(
-            res.messages,
-            vec![
-                SubMsg::new(BankMsg::Send {
-                    to_address: "treasury".to_string(),
-                    amount: vec![Coin {
-                        denom: "in".to_string(),
-                        amount: Uint128::new(1_980_000_000_000),
-                    }],
-                }),
-                SubMsg::new(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin {
-                        denom: "fee".to_string(),
-                        amount: Uint128::new(100),
-                    }],
-                }),
-                SubMsg::new(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin {
-                        denom: "in".to_string(),
-                        amount: Uint128::new(20_000_000_000),
-                    }],
-                }),
-            ],
+            res.average_price,
+            Decimal::from_str("0.003748503748503748").unwrap()
+        );
+
+        //withdraw creator 1
+        let info = mock_info("creator1", &[]);
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_500_000);
+        let msg = crate::msg::ExecuteMsg::Withdraw {
+            stream_id: 1,
+            cap: None,
+            operator_target: None,
+            recipient: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal::from_str("0.004499991000017999").unwrap()
+        );
+
+        //test price after withdraw
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(3_750_000);
+        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        //approx 2500/333333
+        assert_eq!(
+            res.current_streamed_price,
+            Decimal::from_str("0.001500006000024000").unwrap()
         );
     }
 
     #[test]
-    fn test_recurring_finalize_stream_calls() {
-        let malicious_treasury = Addr::unchecked("treasury");
-        let start = Timestamp::from_seconds(10);
-        let end = Timestamp::from_seconds(110);
-        let out_supply = Uint128::new(1000);
-        let out_denom = "myToken";
-        let in_denom = "uosmo";
+    fn test_update_protocol_admin() {
         // instantiate
         let mut deps = mock_dependencies();
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
-            min_stream_seconds: Uint64::new(100),
+            min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
-            accepted_in_denom: in_denom.to_string(),
+            accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
-        // Create stream
+
+        // random cannot update
+        let env = mock_env();
+        let msg = UpdateProtocolAdmin {
+            new_protocol_admin: "new_protocol_admin".to_string(),
+        };
+        let info = mock_info("random", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // protocol admin can update
+        let info = mock_info("protocol_admin", &[]);
+        execute(deps.as_mut(), env, info, msg).unwrap();
+        let query = query_config(deps.as_ref()).unwrap();
+        assert_eq!(query.protocol_admin, "new_protocol_admin".to_string());
+    }
+    #[test]
+    fn test_execute_update_config() {
+        // instantiate
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        //query config
+        let config_response = query_config(deps.as_ref()).unwrap();
+        //check config
+        assert_eq!(config_response.min_stream_seconds, Uint64::new(1000));
+        assert_eq!(config_response.min_seconds_until_start_time, Uint64::new(0));
+        assert_eq!(config_response.stream_creation_denom, "fee".to_string());
+        assert_eq!(config_response.stream_creation_fee, Uint128::new(100));
+        assert_eq!(
+            config_response.fee_collectors,
+            vec![FeeCollector {
+                address: Addr::unchecked("collector"),
+                weight: Decimal::one()
+            }]
+        );
+        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
+        assert_eq!(config_response.accepted_in_denom, "in".to_string());
+
+        // random user cant update config
+        let mut env = mock_env();
+        let info = mock_info("random", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(200)),
+            stream_creation_fee_options: None,
+            fee_collectors: Some(single_collector("collector2")),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal::percent(2)),
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::Unauthorized {});
+
+        // wrong fee amount
+        let mut env = mock_env();
+        let info = mock_info("protocol_admin", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(0)),
+            stream_creation_fee_options: None,
+            fee_collectors: Some(single_collector("collector2")),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal::percent(2)),
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidStreamCreationFee {});
+
+        // wrong exit fee percent
+        let mut env = mock_env();
+        let info = mock_info("protocol_admin", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(200)),
+            stream_creation_fee_options: None,
+            fee_collectors: Some(single_collector("collector2")),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal::percent(101)),
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(res, ContractError::InvalidExitFeePercent {});
+
+        // protocol admin can update config
+        let mut env = mock_env();
+        let info = mock_info("protocol_admin", &[]);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(2000)),
+            min_duration_until_start_time: Some(Uint64::new(2000)),
+            stream_creation_denom: Some("fee2".to_string()),
+            stream_creation_fee: Some(Uint128::new(200)),
+            stream_creation_fee_options: None,
+            fee_collectors: Some(single_collector("collector2")),
+            accepted_in_denom: Some("new_denom".to_string()),
+            exit_fee_percent: Some(Decimal::percent(2)),
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+
+        //query config
+        let config_response = query_config(deps.as_ref()).unwrap();
+        //check config
+        assert_eq!(config_response.min_stream_seconds, Uint64::new(2000));
+        assert_eq!(
+            config_response.min_seconds_until_start_time,
+            Uint64::new(2000)
+        );
+        assert_eq!(config_response.stream_creation_denom, "fee2".to_string());
+        assert_eq!(config_response.stream_creation_fee, Uint128::new(200));
+        assert_eq!(
+            config_response.fee_collectors[0].address,
+            Addr::unchecked("collector2")
+        );
+        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
+        assert_eq!(config_response.accepted_in_denom, "new_denom".to_string());
+        assert_eq!(config_response.exit_fee_percent, Decimal::percent(2));
+
+        // create stream
+        let out_supply = Uint128::new(1000);
+        let out_denom = "out";
+        let start = Timestamp::from_seconds(10000);
+        let end = Timestamp::from_seconds(1000000);
+        let treasury = "treasury";
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
-            malicious_treasury.as_str(),
+            "creator1",
             &[
                 Coin::new(out_supply.u128(), out_denom),
-                Coin::new(100, "fee"),
+                Coin::new(200, "fee2"),
             ],
         );
         execute_create_stream(
             deps.as_mut(),
             env,
             info,
-            malicious_treasury.to_string(),
+            treasury.to_string(),
             "test".to_string(),
             Some("https://sample.url".to_string()),
-            in_denom.to_string(),
+            "new_denom".to_string(),
             out_denom.to_string(),
             out_supply,
             start,
@@ -2053,78 +5757,72 @@ mod test_module {
             None,
         )
         .unwrap();
-        // First subscription
+
+        // update config during stream
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(1);
-        let funds = Coin::new(200, in_denom.to_string());
-        let info = mock_info("user1", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
-            stream_id: 1,
-            operator_target: None,
-            operator: None,
+        env.block.time = Timestamp::from_seconds(100000);
+        let info = mock_info("protocol_admin", &[]);
+        let msg = crate::msg::ExecuteMsg::UpdateConfig {
+            min_stream_duration: Some(Uint64::new(3000)),
+            min_duration_until_start_time: Some(Uint64::new(4000)),
+            stream_creation_denom: Some("fee3".to_string()),
+            stream_creation_fee: Some(Uint128::new(300)),
+            stream_creation_fee_options: None,
+            fee_collectors: Some(single_collector("collector3")),
+            accepted_in_denom: Some("new_denom2".to_string()),
+            exit_fee_percent: Some(Decimal::percent(5)),
+            exit_fee_tiers: None,
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-        // Update
-        let mut env = mock_env();
-        env.block.time = end.plus_seconds(1);
-        let info = mock_info(malicious_treasury.as_str(), &[]);
-        execute_update_stream(deps.as_mut(), env.clone(), 1).unwrap();
-        // First call
-        let res =
-            execute_finalize_stream(deps.as_mut(), env.clone(), info.clone(), 1, None).unwrap();
+        execute(deps.as_mut(), env, info, msg).unwrap();
+        //query config
+        let config_response = query_config(deps.as_ref()).unwrap();
+        //check config
+        assert_eq!(config_response.min_stream_seconds, Uint64::new(3000));
         assert_eq!(
-            res.messages,
-            vec![
-                SubMsg::new(BankMsg::Send {
-                    to_address: malicious_treasury.to_string(),
-                    amount: vec![Coin {
-                        denom: in_denom.to_string(),
-                        amount: Uint128::new(198),
-                    }],
-                }),
-                SubMsg::new(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin {
-                        denom: "fee".to_string(),
-                        amount: Uint128::new(100),
-                    }],
-                }),
-                SubMsg::new(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin {
-                        denom: in_denom.to_string(),
-                        amount: Uint128::new(2),
-                    }],
-                }),
-            ],
+            config_response.min_seconds_until_start_time,
+            Uint64::new(4000)
         );
-        // Check stream status
-        let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
-        assert_eq!(stream.status, Status::Finalized);
-        // Sequential calls, anyone could force this sequential calls
-        let res = execute_finalize_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert_eq!(res, ContractError::StreamAlreadyFinalized {});
+        assert_eq!(config_response.stream_creation_denom, "fee3".to_string());
+        assert_eq!(config_response.stream_creation_fee, Uint128::new(300));
+        assert_eq!(
+            config_response.fee_collectors[0].address,
+            Addr::unchecked("collector3")
+        );
+        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
+        assert_eq!(config_response.accepted_in_denom, "new_denom2".to_string());
+        assert_eq!(config_response.exit_fee_percent, Decimal::percent(5));
+
+        // check stream
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100000);
+        let stream_response = query_stream(deps.as_ref(), env, 1).unwrap();
+        assert_eq!(stream_response.exit_fee_percent, Decimal::percent(2));
+        assert_eq!(stream_response.stream_creation_fee, Uint128::new(200));
     }
 
     #[test]
-    fn test_exit_stream() {
+    fn test_estimate_subscription() {
         let treasury = Addr::unchecked("treasury");
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
-        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_supply = Uint128::new(1_000_000);
         let out_denom = "out_denom";
 
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
@@ -2134,7 +5832,7 @@ mod test_module {
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
-            "creator1",
+            "creator",
             &[
                 Coin::new(out_supply.u128(), out_denom),
                 Coin::new(100, "fee"),
@@ -2142,7 +5840,7 @@ mod test_module {
         );
         execute_create_stream(
             deps.as_mut(),
-            env,
+            env.clone(),
             info,
             treasury.to_string(),
             "test".to_string(),
@@ -2156,215 +5854,246 @@ mod test_module {
         )
         .unwrap();
 
+        // estimate before any subscription, whole supply goes to the only subscriber
+        let res =
+            query_estimate_subscription(deps.as_ref(), env, 1, Uint128::new(1_000), None).unwrap();
+        assert_eq!(res.shares, Uint128::new(1_000));
+        assert_eq!(res.expected_out, Uint128::new(1_000_000));
+        assert_eq!(res.expected_spent, Uint128::new(1_000));
+        assert_eq!(res.implied_price, Decimal::from_str("0.001").unwrap());
+        assert_eq!(res.exit_fee, Uint128::new(10));
+
         // first subscription
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(2_000_000_000_000, "in");
-        let info = mock_info("creator1", &[funds]);
+        env.block.time = start;
+        let info = mock_info("creator1", &[Coin::new(1_000, "in")]);
         let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
             operator_target: None,
             operator: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-
-        // can't exit before stream ends
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        let info = mock_info("creator1", &[]);
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert_eq!(res, ContractError::StreamNotEnded {});
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-        //failed exit from random address
-        let mut env = mock_env();
-        env.block.time = end.plus_seconds(3_000_000);
-        let info = mock_info("random", &[]);
-        let res = execute_exit_stream(
-            deps.as_mut(),
+        // estimate at the middle of the stream, half of the supply is left to share
+        let mid = start.plus_seconds(2_000_000);
+        let res = query_estimate_subscription(
+            deps.as_ref(),
             env.clone(),
-            info,
             1,
-            Some("creator1".to_string()),
+            Uint128::new(1_000),
+            Some(mid),
+        )
+        .unwrap();
+        assert_eq!(res.shares, Uint128::new(2_000));
+        assert_eq!(res.expected_out, Uint128::new(333_333));
+        assert_eq!(res.expected_spent, Uint128::new(1_000));
+        assert_eq!(res.exit_fee, Uint128::new(10));
+
+        // can't estimate before the current block or the last stream update
+        let err = query_estimate_subscription(
+            deps.as_ref(),
+            env.clone(),
+            1,
+            Uint128::new(1_000),
+            Some(start.minus_seconds(1)),
         )
         .unwrap_err();
-        assert_eq!(res, ContractError::Unauthorized {});
-        // can exit
-        let info = mock_info("creator1", &[]);
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap();
         assert_eq!(
-            res.messages,
-            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "creator1".to_string(),
-                amount: vec![Coin::new(
-                    Uint128::new(1_000_000_000_000).u128(),
-                    "out_denom"
-                )]
-            }))]
+            err,
+            StdError::generic_err(ContractError::InvalidEstimateTime {}.to_string())
         );
 
-        // position deleted
+        // estimate matches the actual subscription
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(4_000_000);
-        let info = mock_info("creator1", &[]);
-        let res = execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap_err();
-        assert!(matches!(res, ContractError::Std(StdError::NotFound { .. })));
+        env.block.time = mid;
+        let info = mock_info("creator2", &[Coin::new(1_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 1,
+            operator_target: None,
+            operator: None,
+        };
+        execute(deps.as_mut(), env, info, msg).unwrap();
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        execute_update_position(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator2", &[]),
+            1,
+            None,
+        )
+        .unwrap();
+        let position =
+            query_position(deps.as_ref(), env.clone(), 1, "creator2".to_string()).unwrap();
+        assert_eq!(position.purchased, Uint128::new(333_333));
+        assert_eq!(position.spent, Uint128::new(1_000));
+
+        // can't estimate after the stream ends
+        let err = query_estimate_subscription(deps.as_ref(), env, 1, Uint128::new(1_000), None)
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Stream Ended"));
     }
 
     #[test]
-    fn test_withdraw_all_before_exit_case() {
+    fn test_exit_preview() {
         let treasury = Addr::unchecked("treasury");
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
-        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_supply = Uint128::new(1_000_000);
         let out_denom = "out_denom";
 
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        // create stream
+        // create a stream without threshold and a stream with an unreachable threshold
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
-        let info = mock_info(
-            "creator1",
-            &[
-                Coin::new(out_supply.u128(), out_denom),
-                Coin::new(100, "fee"),
-            ],
-        );
-        execute_create_stream(
-            deps.as_mut(),
-            env,
-            info,
-            treasury.to_string(),
-            "test".to_string(),
-            Some("https://sample.url".to_string()),
-            "in".to_string(),
-            out_denom.to_string(),
-            out_supply,
-            start,
-            end,
-            None,
-        )
-        .unwrap();
-
-        // first subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(2_000_000_000_000, "in");
-        let info = mock_info("creator1", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
-            stream_id: 1,
-            operator_target: None,
-            operator: None,
-        };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-
-        // second subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(1_000_000_000_000, "in");
-        let info = mock_info("creator2", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
-            stream_id: 1,
-            operator_target: None,
-            operator: None,
-        };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-
-        // first withdraw
-        let info = mock_info("creator1", &[]);
-        let mut env = mock_env();
-        env.block.time = end.minus_seconds(1_000_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
-            stream_id: 1,
-            cap: None,
-            operator_target: None,
-        };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-
-        // second withdraw
-        let info = mock_info("creator2", &[]);
-        let mut env = mock_env();
-        env.block.time = end.minus_seconds(1_000_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
-            stream_id: 1,
-            cap: None,
-            operator_target: None,
-        };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        for threshold in [None, Some(Threshold::MinSpentIn(Uint128::new(1_000_000)))] {
+            let info = mock_info(
+                "creator",
+                &[
+                    Coin::new(out_supply.u128(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                threshold,
+            )
+            .unwrap();
+        }
 
-        // can exit
+        // subscribe to both streams in the middle
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(1_000_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
+        env.block.time = start.plus_seconds(2_000_000);
+        for stream_id in [1, 2] {
+            let info = mock_info("creator1", &[Coin::new(1_000, "in")]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id,
+                operator_target: None,
+                operator: None,
+            };
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
 
-        let mut env = mock_env();
-        env.block.time = end.plus_seconds(1_000_001);
-        let info = mock_info("creator1", &[]);
-        execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        // can't preview before stream ends
+        let err = query_exit_preview(deps.as_ref(), env, 1, "creator1".to_string()).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Stream not ended"));
 
+        // preview matches the exit
         let mut env = mock_env();
-        env.block.time = end.plus_seconds(1_000_002);
-        let info = mock_info("creator2", &[]);
-        execute_exit_stream(deps.as_mut(), env, info, 1, None).unwrap();
+        env.block.time = end.plus_seconds(1);
+        let preview =
+            query_exit_preview(deps.as_ref(), env.clone(), 1, "creator1".to_string()).unwrap();
+        assert_eq!(preview.purchased, Uint128::new(1_000_000));
+        assert_eq!(preview.refund, Uint128::zero());
+        assert_eq!(preview.swap_fee, Uint128::new(10));
+        assert!(!preview.cancelled);
+        assert!(!preview.threshold_not_reached);
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator1", &[]),
+            1,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator1".to_string(),
+                amount: vec![Coin::new(1_000_000, "out_denom")],
+            })]
+        );
+        assert!(res.attributes.contains(&attr("swap_fee_paid", "10")));
+
+        // threshold not reached, position is refunded as cancelled
+        let preview =
+            query_exit_preview(deps.as_ref(), env.clone(), 2, "creator1".to_string()).unwrap();
+        assert_eq!(preview.purchased, Uint128::zero());
+        assert_eq!(preview.refund, Uint128::new(1_000));
+        assert_eq!(preview.swap_fee, Uint128::zero());
+        assert!(preview.cancelled);
+        assert!(preview.threshold_not_reached);
+        let res = crate::killswitch::execute_exit_cancelled(
+            deps.as_mut(),
+            env,
+            mock_info("creator1", &[]),
+            2,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator1".to_string(),
+                amount: vec![Coin::new(1_000, "in")],
+            })]
+        );
     }
 
     #[test]
-    fn test_price_feed() {
-        let treasury = Addr::unchecked("treasury");
+    fn test_exit_preview_claimed() {
         let start = Timestamp::from_seconds(1_000_000);
         let end = Timestamp::from_seconds(5_000_000);
         let out_supply = Uint128::new(1_000_000);
-        let out_denom = "out_denom";
 
-        // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
-
-        // create stream
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
-            "creator1",
-            &[
-                Coin::new(out_supply.u128(), out_denom),
-                Coin::new(100, "fee"),
-            ],
+            "creator",
+            &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
         );
         execute_create_stream(
             deps.as_mut(),
             env,
             info,
-            treasury.to_string(),
+            "treasury".to_string(),
             "test".to_string(),
-            Some("https://sample.url".to_string()),
+            None,
             "in".to_string(),
-            out_denom.to_string(),
+            "out".to_string(),
             out_supply,
             start,
             end,
@@ -2372,267 +6101,518 @@ mod test_module {
         )
         .unwrap();
 
-        // first subscription
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(1_000_000);
-        let funds = Coin::new(3_000, "in");
-        let info = mock_info("creator1", &[funds]);
+        env.block.time = start;
         let msg = crate::msg::ExecuteMsg::Subscribe {
             stream_id: 1,
             operator_target: None,
             operator: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let info = mock_info("alice", &[Coin::new(1_000, "in")]);
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-        //check current streamed price before update
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(res.current_streamed_price, Decimal::new(Uint128::new(0)));
-
-        //check current streamed price after update
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
-        //approx 1000/333333
-        assert_eq!(
-            res.current_streamed_price,
-            Decimal::from_str("0.002997002997002997").unwrap()
-        );
-        // second subscription
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(2_000_000);
-        let funds = Coin::new(1_000, "in");
-        let info = mock_info("creator2", &[funds]);
-        let msg = crate::msg::ExecuteMsg::Subscribe {
+        // claim a quarter way, then the stream is cancelled
+        env.block.time = start.plus_seconds(1_000_000);
+        let msg = crate::msg::ExecuteMsg::ClaimPurchased {
             stream_id: 1,
+            cap: None,
             operator_target: None,
-            operator: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg).unwrap();
+        execute_pause_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            1,
+        )
+        .unwrap();
+        execute_cancel_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            1,
+        )
+        .unwrap();
 
-        //check current streamed price before update
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        let res = query_last_streamed_price(deps.as_ref(), env, 1).unwrap();
+        // the preview matches the exit, the claimed part of the spent tokens goes to the treasury
+        let preview =
+            query_exit_preview(deps.as_ref(), env.clone(), 1, "alice".to_string()).unwrap();
+        assert!(preview.cancelled);
+        assert_eq!(preview.refund, Uint128::new(750));
+        assert_eq!(preview.treasury_refund, Uint128::new(250));
+        let msg = crate::msg::ExecuteMsg::ExitCancelled {
+            stream_id: 1,
+            operator_target: None,
+            recipient: None,
+        };
+        let res = execute(deps.as_mut(), env, mock_info("alice", &[]), msg).unwrap();
         assert_eq!(
-            res.current_streamed_price,
-            Decimal::from_str("0.002997002997002997").unwrap()
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "alice".to_string(),
+                    amount: vec![Coin::new(preview.refund.u128(), "in")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(preview.treasury_refund.u128(), "in")],
+                }),
+            ]
         );
+    }
 
-        //check current streamed price after update
+    #[test]
+    fn test_finalize_preview() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000_000_000);
+        let out_denom = "out_denom";
+
+        // instantiate
+        let mut deps = mock_dependencies();
+        let msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(1),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // create a stream without threshold and a stream with an unreachable threshold
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
-        //approx 2000/333333
-        assert_eq!(
-            res.current_streamed_price,
-            Decimal::from_str("0.0045000045000045").unwrap()
-        );
+        env.block.time = Timestamp::from_seconds(0);
+        for threshold in [None, Some(Threshold::MinSpentIn(Uint128::new(u128::MAX)))] {
+            let info = mock_info(
+                "creator1",
+                &[
+                    Coin::new(out_supply.u128(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                threshold,
+            )
+            .unwrap();
+        }
 
-        //check average streamed price
+        // subscribe to both streams
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_000_000);
-        let res = query_average_price(deps.as_ref(), env, 1).unwrap();
-        //approx 2500/333333
-        assert_eq!(
-            res.average_price,
-            Decimal::from_str("0.003748503748503748").unwrap()
-        );
+        env.block.time = start.plus_seconds(1_000_000);
+        for stream_id in [1, 2] {
+            let info = mock_info("creator1", &[Coin::new(2_000_000_000_000, "in")]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id,
+                operator_target: None,
+                operator: None,
+            };
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
 
-        //withdraw creator 1
-        let info = mock_info("creator1", &[]);
+        // can't preview before stream ends
+        let err = query_finalize_preview(deps.as_ref(), env, 1).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Stream not ended"));
+
+        // preview matches finalize
         let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_500_000);
-        let msg = crate::msg::ExecuteMsg::Withdraw {
-            stream_id: 1,
-            cap: None,
-            operator_target: None,
-        };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
+        env.block.time = end.plus_seconds(1);
+        let preview = query_finalize_preview(deps.as_ref(), env.clone(), 1).unwrap();
         assert_eq!(
-            res.current_streamed_price,
-            Decimal::from_str("0.004499991000017999").unwrap()
+            preview,
+            FinalizePreviewResponse {
+                treasury: "treasury".to_string(),
+                creator_revenue: Uint128::new(1_980_000_000_000),
+                swap_fee: Uint128::new(20_000_000_000),
+                fee_collectors: vec![FeeCollector {
+                    address: Addr::unchecked("collector"),
+                    weight: Decimal::one(),
+                }],
+                creation_fee: Coin::new(100, "fee"),
+                refunded_out_remaining: Uint128::zero(),
+                total_sold: Uint128::new(1_000_000_000_000),
+                threshold_not_reached: false,
+            }
         );
-
-        //test price after withdraw
-        let mut env = mock_env();
-        env.block.time = start.plus_seconds(3_750_000);
-        execute_update_stream(deps.as_mut(), env, 1).unwrap();
-        let res = query_last_streamed_price(deps.as_ref(), mock_env(), 1).unwrap();
-        //approx 2500/333333
+        let info = mock_info(treasury.as_str(), &[]);
+        let res = execute_finalize_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("creators_revenue", "1980000000000")));
+        assert!(res.attributes.contains(&attr("swap_fee", "20000000000")));
+
+        // finalized stream can't be previewed
+        let err = query_finalize_preview(deps.as_ref(), env.clone(), 1).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Stream is already finalized"));
+
+        // threshold not reached blocks finalize
+        let preview = query_finalize_preview(deps.as_ref(), env.clone(), 2).unwrap();
+        assert!(preview.threshold_not_reached);
+        let info = mock_info(treasury.as_str(), &[]);
+        let err = execute_finalize_stream(deps.as_mut(), env, info, 2, None).unwrap_err();
         assert_eq!(
-            res.current_streamed_price,
-            Decimal::from_str("0.001500006000024000").unwrap()
+            err,
+            ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
         );
     }
 
     #[test]
-    fn test_update_protocol_admin() {
+    fn test_protocol_stats() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+        let out_denom = "out_denom";
+
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert_eq!(
+            query_stats(deps.as_ref()).unwrap(),
+            ProtocolStats::default()
+        );
 
-        // random cannot update
-        let env = mock_env();
-        let msg = UpdateProtocolAdmin {
-            new_protocol_admin: "new_protocol_admin".to_string(),
-        };
-        let info = mock_info("random", &[]);
-        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
-        assert_eq!(err, ContractError::Unauthorized {});
+        // create two streams
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        for _ in 0..2 {
+            let info = mock_info(
+                "creator",
+                &[
+                    Coin::new(out_supply.u128(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+            )
+            .unwrap();
+        }
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.total_streams, 2);
+        assert_eq!(stats.waiting_streams, 2);
+
+        // without any transaction after the start, stream queries report the streams active,
+        // stats count them as waiting until they are activated
+        let mut env = mock_env();
+        env.block.time = start;
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.waiting_streams, 2);
+        assert_eq!(stats.active_streams, 0);
+        assert_eq!(
+            query_stream(deps.as_ref(), env.clone(), 1).unwrap().status,
+            Status::Active
+        );
+        let streams = list_streams(deps.as_ref(), env.clone(), None, None)
+            .unwrap()
+            .streams;
+        assert!(streams.iter().all(|stream| stream.status == Status::Active));
+        let streams = query_streams_by_ids(deps.as_ref(), env, vec![1, 2])
+            .unwrap()
+            .streams;
+        assert!(streams
+            .iter()
+            .all(|result| result.stream.as_ref().unwrap().status == Status::Active));
+
+        // pausing a started stream activates it first, resuming keeps it active
+        let mut env = mock_env();
+        env.block.time = start;
+        crate::contract::sudo(
+            deps.as_mut(),
+            env.clone(),
+            crate::msg::SudoMsg::PauseStream { stream_id: 2 },
+        )
+        .unwrap();
+        crate::contract::sudo(
+            deps.as_mut(),
+            env,
+            crate::msg::SudoMsg::ResumeStream { stream_id: 2 },
+        )
+        .unwrap();
+        let stored = STATS.load(&deps.storage).unwrap();
+        assert_eq!(stored.waiting_streams, 1);
+        assert_eq!(stored.active_streams, 1);
+        assert_eq!(stored.paused_streams, 0);
+
+        // subscribe, subscriber1 joins both streams
+        let mut env = mock_env();
+        env.block.time = start;
+        for (stream_id, subscriber) in [(1, "subscriber1"), (1, "subscriber2"), (2, "subscriber1")]
+        {
+            let info = mock_info(subscriber, &[Coin::new(1_000, "in")]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id,
+                operator_target: None,
+                operator: None,
+            };
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.waiting_streams, 0);
+        assert_eq!(stats.active_streams, 2);
+        assert_eq!(stats.unique_subscribers, 2);
+        assert_eq!(stats.open_positions, 3);
 
-        // protocol admin can update
+        // pause and cancel the second stream
+        let mut env = mock_env();
+        env.block.time = start.plus_seconds(1_000);
         let info = mock_info("protocol_admin", &[]);
-        execute(deps.as_mut(), env, info, msg).unwrap();
-        let query = query_config(deps.as_ref()).unwrap();
-        assert_eq!(query.protocol_admin, "new_protocol_admin".to_string());
+        execute_pause_stream(deps.as_mut(), env.clone(), info.clone(), 2).unwrap();
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.active_streams, 1);
+        assert_eq!(stats.paused_streams, 1);
+        crate::killswitch::execute_cancel_stream(deps.as_mut(), env, info, 2).unwrap();
+
+        // finalize the first stream
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let info = mock_info(treasury.as_str(), &[]);
+        execute_finalize_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(
+            stats,
+            ProtocolStats {
+                total_streams: 2,
+                waiting_streams: 0,
+                active_streams: 0,
+                paused_streams: 0,
+                finalized_streams: 1,
+                cancelled_streams: 1,
+                in_volume: vec![Coin::new(2_000, "in")],
+                fees_collected: vec![Coin::new(100, "fee"), Coin::new(20, "in")],
+                unique_subscribers: 2,
+                open_positions: 3,
+            }
+        );
+
+        // migration rebuilds the same stats from streams and positions
+        STATS.remove(deps.as_mut().storage);
+        migrate(deps.as_mut(), env.clone(), crate::msg::MigrateMsg {}).unwrap();
+        assert_eq!(query_stats(deps.as_ref()).unwrap(), stats);
+
+        // exits close positions
+        let info = mock_info("subscriber1", &[]);
+        execute_exit_stream(deps.as_mut(), env.clone(), info.clone(), 1, None, None).unwrap();
+        crate::killswitch::execute_exit_cancelled(deps.as_mut(), env, info, 2, None, None).unwrap();
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(stats.open_positions, 1);
+        assert_eq!(stats.unique_subscribers, 2);
     }
+
     #[test]
-    fn test_execute_update_config() {
+    fn test_batch_queries() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+        let out_denom = "out_denom";
+
         // instantiate
         let mut deps = mock_dependencies();
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(0);
         let msg = crate::msg::InstantiateMsg {
             min_stream_seconds: Uint64::new(1000),
             min_seconds_until_start_time: Uint64::new(0),
             stream_creation_denom: "fee".to_string(),
             stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
             exit_fee_percent: Decimal::percent(1),
-            fee_collector: "collector".to_string(),
+            exit_fee_tiers: vec![],
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
             protocol_admin: "protocol_admin".to_string(),
             accepted_in_denom: "in".to_string(),
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        //query config
-        let config_response = query_config(deps.as_ref()).unwrap();
-        //check config
-        assert_eq!(config_response.min_stream_seconds, Uint64::new(1000));
-        assert_eq!(config_response.min_seconds_until_start_time, Uint64::new(0));
-        assert_eq!(config_response.stream_creation_denom, "fee".to_string());
-        assert_eq!(config_response.stream_creation_fee, Uint128::new(100));
-        assert_eq!(config_response.fee_collector, "collector".to_string());
-        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
-        assert_eq!(config_response.accepted_in_denom, "in".to_string());
-
-        // random user cant update config
-        let mut env = mock_env();
-        let info = mock_info("random", &[]);
-        env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(200)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal::percent(2)),
-        };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(res, ContractError::Unauthorized {});
-
-        // wrong fee amount
+        // create two streams
         let mut env = mock_env();
-        let info = mock_info("protocol_admin", &[]);
         env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(0)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal::percent(2)),
-        };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(res, ContractError::InvalidStreamCreationFee {});
+        for _ in 0..2 {
+            let info = mock_info(
+                "creator",
+                &[
+                    Coin::new(out_supply.u128(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in".to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                None,
+            )
+            .unwrap();
+        }
 
-        // wrong exit fee percent
+        // subscribe to the second stream
         let mut env = mock_env();
-        let info = mock_info("protocol_admin", &[]);
-        env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(200)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal::percent(101)),
+        env.block.time = start;
+        let info = mock_info("subscriber", &[Coin::new(1_000, "in")]);
+        let msg = crate::msg::ExecuteMsg::Subscribe {
+            stream_id: 2,
+            operator_target: None,
+            operator: None,
         };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(res, ContractError::InvalidExitFeePercent {});
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-        // protocol admin can update config
-        let mut env = mock_env();
-        let info = mock_info("protocol_admin", &[]);
-        env.block.time = Timestamp::from_seconds(0);
-        let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(2000)),
-            min_duration_until_start_time: Some(Uint64::new(2000)),
-            stream_creation_denom: Some("fee2".to_string()),
-            stream_creation_fee: Some(Uint128::new(200)),
-            fee_collector: Some("collector2".to_string()),
-            accepted_in_denom: Some("new_denom".to_string()),
-            exit_fee_percent: Some(Decimal::percent(2)),
-        };
-        execute(deps.as_mut(), env, info, msg).unwrap();
+        // streams are returned in the requested order with missing ones marked
+        let res = query_streams_by_ids(deps.as_ref(), mock_env(), vec![2, 3, 1]).unwrap();
+        assert_eq!(
+            res.streams
+                .iter()
+                .map(|s| (s.id, s.stream.is_some()))
+                .collect::<Vec<_>>(),
+            vec![(2, true), (3, false), (1, true)]
+        );
+        assert_eq!(
+            res.streams[0].stream,
+            Some(query_stream(deps.as_ref(), env.clone(), 2).unwrap())
+        );
 
-        //query config
-        let config_response = query_config(deps.as_ref()).unwrap();
-        //check config
-        assert_eq!(config_response.min_stream_seconds, Uint64::new(2000));
+        // positions are returned in the requested order with missing ones marked
+        let res = query_positions_batch(
+            deps.as_ref(),
+            vec![
+                (1, "subscriber".to_string()),
+                (2, "subscriber".to_string()),
+                (2, "random".to_string()),
+                (2, "Subscriber".to_string()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(res.positions.len(), 4);
+        assert_eq!(res.positions[0].position, None);
         assert_eq!(
-            config_response.min_seconds_until_start_time,
-            Uint64::new(2000)
+            res.positions[1].position,
+            Some(query_position(deps.as_ref(), env, 2, "subscriber".to_string()).unwrap())
         );
-        assert_eq!(config_response.stream_creation_denom, "fee2".to_string());
-        assert_eq!(config_response.stream_creation_fee, Uint128::new(200));
-        assert_eq!(config_response.fee_collector, "collector2".to_string());
-        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
-        assert_eq!(config_response.accepted_in_denom, "new_denom".to_string());
-        assert_eq!(config_response.exit_fee_percent, Decimal::percent(2));
+        assert_eq!(res.positions[2].owner, "random".to_string());
+        assert_eq!(res.positions[2].position, None);
+        // invalid owners are marked missing without failing the batch
+        assert_eq!(res.positions[3].owner, "Subscriber".to_string());
+        assert_eq!(res.positions[3].position, None);
+
+        // batch size is limited
+        let err = query_streams_by_ids(deps.as_ref(), mock_env(), (0..51).collect()).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Batch size exceeds the limit of 50")
+        );
+    }
+
+    #[test]
+    fn test_exit_fee_tiers() {
+        let treasury = Addr::unchecked("treasury");
+        let start = Timestamp::from_seconds(1_000_000);
+        let end = Timestamp::from_seconds(5_000_000);
+        let out_supply = Uint128::new(1_000_000);
+        let out_denom = "out_denom";
+        let tiers = vec![
+            ExitFeeTier {
+                min_spent: Uint128::new(1_000),
+                fee_percent: Decimal::percent(1),
+            },
+            ExitFeeTier {
+                min_spent: Uint128::new(10_000),
+                fee_percent: Decimal::permille(5),
+            },
+        ];
+
+        // tier fees can't exceed the previous tier
+        let mut deps = mock_dependencies();
+        let mut msg = crate::msg::InstantiateMsg {
+            min_stream_seconds: Uint64::new(1000),
+            min_seconds_until_start_time: Uint64::new(0),
+            stream_creation_denom: "fee".to_string(),
+            stream_creation_fee: Uint128::new(100),
+            stream_creation_fee_options: vec![],
+            exit_fee_percent: Decimal::percent(2),
+            exit_fee_tiers: tiers.iter().rev().cloned().collect(),
+            fee_side: FeeSide::In,
+            fee_collectors: single_collector("collector"),
+            protocol_admin: "protocol_admin".to_string(),
+            accepted_in_denom: "in".to_string(),
+        };
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidExitFeeTiers {});
+
+        // instantiate
+        msg.exit_fee_tiers = tiers.clone();
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
         // create stream
-        let out_supply = Uint128::new(1000);
-        let out_denom = "out";
-        let start = Timestamp::from_seconds(10000);
-        let end = Timestamp::from_seconds(1000000);
-        let treasury = "treasury";
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info(
-            "creator1",
+            "creator",
             &[
                 Coin::new(out_supply.u128(), out_denom),
-                Coin::new(200, "fee2"),
+                Coin::new(100, "fee"),
             ],
         );
         execute_create_stream(
             deps.as_mut(),
-            env,
+            env.clone(),
             info,
             treasury.to_string(),
             "test".to_string(),
             Some("https://sample.url".to_string()),
-            "new_denom".to_string(),
+            "in".to_string(),
             out_denom.to_string(),
             out_supply,
             start,
@@ -2641,41 +6621,120 @@ mod test_module {
         )
         .unwrap();
 
-        // update config during stream
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100000);
-        let info = mock_info("protocol_admin", &[]);
+        // config changes do not affect existing streams
         let msg = crate::msg::ExecuteMsg::UpdateConfig {
-            min_stream_duration: Some(Uint64::new(3000)),
-            min_duration_until_start_time: Some(Uint64::new(4000)),
-            stream_creation_denom: Some("fee3".to_string()),
-            stream_creation_fee: Some(Uint128::new(300)),
-            fee_collector: Some("collector3".to_string()),
-            accepted_in_denom: Some("new_denom2".to_string()),
-            exit_fee_percent: Some(Decimal::percent(5)),
+            min_stream_duration: None,
+            min_duration_until_start_time: None,
+            stream_creation_denom: None,
+            stream_creation_fee: None,
+            stream_creation_fee_options: None,
+            fee_collectors: None,
+            accepted_in_denom: None,
+            exit_fee_percent: None,
+            exit_fee_tiers: Some(vec![]),
+            fee_side: None,
+            keeper_reward_percent: None,
+            position_nfts: None,
         };
-        execute(deps.as_mut(), env, info, msg).unwrap();
-        //query config
-        let config_response = query_config(deps.as_ref()).unwrap();
-        //check config
-        assert_eq!(config_response.min_stream_seconds, Uint64::new(3000));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("protocol_admin", &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(query_config(deps.as_ref()).unwrap().exit_fee_tiers, vec![]);
         assert_eq!(
-            config_response.min_seconds_until_start_time,
-            Uint64::new(4000)
+            query_stream(deps.as_ref(), env, 1).unwrap().exit_fee_tiers,
+            tiers
         );
-        assert_eq!(config_response.stream_creation_denom, "fee3".to_string());
-        assert_eq!(config_response.stream_creation_fee, Uint128::new(300));
-        assert_eq!(config_response.fee_collector, "collector3".to_string());
-        assert_eq!(config_response.protocol_admin, "protocol_admin".to_string());
-        assert_eq!(config_response.accepted_in_denom, "new_denom2".to_string());
-        assert_eq!(config_response.exit_fee_percent, Decimal::percent(5));
 
-        // check stream
+        // subscriptions in each tier
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100000);
-        let stream_response = query_stream(deps.as_ref(), env, 1).unwrap();
-        assert_eq!(stream_response.exit_fee_percent, Decimal::percent(2));
-        assert_eq!(stream_response.stream_creation_fee, Uint128::new(200));
+        env.block.time = start;
+        for (subscriber, amount) in [("small", 500), ("medium", 2_000), ("large", 20_000)] {
+            let info = mock_info(subscriber, &[Coin::new(amount, "in")]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+            };
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+
+        // base fee is charged below the first tier
+        let mut env = mock_env();
+        env.block.time = end.plus_seconds(1);
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("small", &[]),
+            1,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert!(res.attributes.contains(&attr("swap_fee_paid", "10")));
+
+        // lower tier fee is rebated
+        let preview =
+            query_exit_preview(deps.as_ref(), env.clone(), 1, "medium".to_string()).unwrap();
+        assert_eq!(preview.refund, Uint128::new(20));
+        assert_eq!(preview.swap_fee, Uint128::new(20));
+        let res = execute_exit_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("medium", &[]),
+            1,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(BankMsg::Send {
+                to_address: "medium".to_string(),
+                amount: vec![Coin::new(20, "in")],
+            })
+        );
+        assert!(res.attributes.contains(&attr("swap_fee_paid", "20")));
+        assert!(res.attributes.contains(&attr("swap_fee_rebate", "20")));
+
+        // finalize withholds the base fee and collects fees of exited positions
+        let res = execute_finalize_stream(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(treasury.as_str(), &[]),
+            1,
+            None,
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&attr("creators_revenue", "22050")));
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector"),
+            vec![Coin::new(100, "fee"), Coin::new(30, "in")]
+        );
+
+        // positions exiting after finalize accrue their fee to the collector
+        let res = execute_exit_stream(deps.as_mut(), env, mock_info("large", &[]), 1, None, None)
+            .unwrap();
+        assert_eq!(
+            res.messages[1..],
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "large".to_string(),
+                amount: vec![Coin::new(300, "in")],
+            }),]
+        );
+        assert_eq!(
+            unclaimed_fees(deps.as_ref(), "collector"),
+            vec![Coin::new(100, "fee"), Coin::new(130, "in")]
+        );
+        let stats = query_stats(deps.as_ref()).unwrap();
+        assert_eq!(
+            stats.fees_collected,
+            vec![Coin::new(100, "fee"), Coin::new(130, "in")]
+        );
     }
 
     #[cfg(test)]
@@ -2706,8 +6765,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -2820,6 +6882,7 @@ mod test_module {
                 stream_id: 1,
                 cap: None,
                 operator_target: None,
+                recipient: None,
             };
             let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
             assert_eq!(res, ContractError::StreamKillswitchActive {});
@@ -2848,7 +6911,7 @@ mod test_module {
             let mut env = mock_env();
             env.block.time = end.plus_seconds(1_000_002);
             let info = mock_info("position1", &[]);
-            let res = execute_exit_stream(deps.as_mut(), env, info, 1, None);
+            let res = execute_exit_stream(deps.as_mut(), env, info, 1, None, None);
             assert_eq!(res, Err(ContractError::StreamKillswitchActive {}));
         }
 
@@ -2869,8 +6932,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -3012,8 +7078,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -3138,8 +7207,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -3175,7 +7247,7 @@ mod test_module {
             let mut env = mock_env();
             env.block.time = start.plus_seconds(0);
             let funds = Coin::new(2_000_000_000_000, "in");
-            let info = mock_info("creator1", &[funds.clone()]);
+            let info = mock_info("creator1", std::slice::from_ref(&funds));
             let msg = crate::msg::ExecuteMsg::Subscribe {
                 stream_id: 1,
                 operator_target: None,
@@ -3192,6 +7264,7 @@ mod test_module {
                 stream_id: 1,
                 cap: Some(cap),
                 operator_target: None,
+                recipient: None,
             };
             let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 
@@ -3207,7 +7280,8 @@ mod test_module {
             let mut env = mock_env();
             env.block.time = start.plus_seconds(6000);
             let info = mock_info("creator1", &[]);
-            let err = execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None).unwrap_err();
+            let err =
+                execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None, None).unwrap_err();
             assert_eq!(err, ContractError::StreamNotPaused {});
 
             // pause
@@ -3230,6 +7304,7 @@ mod test_module {
                 1,
                 None,
                 Some("creator1".to_string()),
+                None,
             )
             .unwrap_err();
 
@@ -3245,6 +7320,7 @@ mod test_module {
                 1,
                 Some(Uint128::new(2_000_000_000_000 + 1)),
                 None,
+                None,
             )
             .unwrap_err();
             assert_eq!(
@@ -3255,9 +7331,16 @@ mod test_module {
             let mut env = mock_env();
             env.block.time = start.plus_seconds(7000);
             let info = mock_info("creator1", &[]);
-            let res =
-                execute_withdraw_paused(deps.as_mut(), env, info, 1, Some(Uint128::zero()), None)
-                    .unwrap_err();
+            let res = execute_withdraw_paused(
+                deps.as_mut(),
+                env,
+                info,
+                1,
+                Some(Uint128::zero()),
+                None,
+                None,
+            )
+            .unwrap_err();
             assert_eq!(res, ContractError::InvalidWithdrawAmount {});
 
             //withdraw with cap
@@ -3265,13 +7348,14 @@ mod test_module {
             env.block.time = start.plus_seconds(7000);
             let info = mock_info("creator1", &[]);
             let cap = Uint128::new(25_000_000);
-            execute_withdraw_paused(deps.as_mut(), env, info, 1, Some(cap), None).unwrap();
+            execute_withdraw_paused(deps.as_mut(), env, info, 1, Some(cap), None, None).unwrap();
 
             // withdraw after pause
             let mut env = mock_env();
             env.block.time = start.plus_seconds(7000);
             let info = mock_info("creator1", &[]);
-            let res = execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None).unwrap();
+            let res =
+                execute_withdraw_paused(deps.as_mut(), env, info, 1, None, None, None).unwrap();
             assert_eq!(
                 res.messages,
                 vec![SubMsg {
@@ -3327,8 +7411,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -3414,8 +7501,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -3461,12 +7551,13 @@ mod test_module {
             env.block.time = Timestamp::from_seconds(3_000_000);
             let res = sudo_pause_stream(deps.as_mut(), env, 1).unwrap();
             assert_eq!(
-                res,
-                Response::new()
-                    .add_attribute("action", "sudo_pause_stream")
-                    .add_attribute("stream_id", "1")
-                    .add_attribute("is_paused", "true")
-                    .add_attribute("pause_date", "3000000.000000000")
+                res.attributes,
+                vec![
+                    attr("action", "sudo_pause_stream"),
+                    attr("stream_id", "1"),
+                    attr("is_paused", "true"),
+                    attr("pause_date", "3000000.000000000"),
+                ]
             );
 
             let mut env = mock_env();
@@ -3492,8 +7583,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(1000),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -3542,7 +7636,7 @@ mod test_module {
             )
             .unwrap();
 
-            let res = list_streams(deps.as_ref(), None, None).unwrap();
+            let res = list_streams(deps.as_ref(), mock_env(), None, None).unwrap();
             assert_eq!(res.streams.len(), 2);
 
             // first subscription to first stream
@@ -3588,8 +7682,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: "in".to_string(),
             };
@@ -3645,191 +7742,519 @@ mod test_module {
             let info = mock_info("protocol_admin", &[]);
             execute_pause_stream(deps.as_mut(), env, info, 1).unwrap();
 
-            //can't exit before cancel
+            //can't exit before cancel
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(2_250_000);
+            let info = mock_info("creator1", &[]);
+            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None, None).unwrap_err();
+            assert_eq!(res, ContractError::StreamNotCancelled {});
+
+            //cancel
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(2_500_000);
+            let response = sudo_cancel_stream(deps.as_mut(), env, 1).unwrap();
+            //out_tokens and the creation fee are sent back to the treasury upon cancellation
+            assert_eq!(
+                response.messages,
+                [
+                    SubMsg {
+                        id: 0,
+                        msg: Bank(BankMsg::Send {
+                            to_address: "treasury".to_string(),
+                            amount: Vec::from([Coin {
+                                denom: "out_denom".to_string(),
+                                amount: Uint128::new(1000000000000)
+                            }])
+                        }),
+                        gas_limit: None,
+                        reply_on: ReplyOn::Never
+                    },
+                    SubMsg {
+                        id: 0,
+                        msg: Bank(BankMsg::Send {
+                            to_address: "treasury".to_string(),
+                            amount: Vec::from([Coin {
+                                denom: "fee".to_string(),
+                                amount: Uint128::new(100)
+                            }])
+                        }),
+                        gas_limit: None,
+                        reply_on: ReplyOn::Never
+                    }
+                ]
+            );
+
+            //random operator can't exit
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(2_250_000);
+            let info = mock_info("random", &[]);
+            let res = execute_exit_cancelled(
+                deps.as_mut(),
+                env,
+                info,
+                1,
+                Some("creator1".to_string()),
+                None,
+            )
+            .unwrap_err();
+            assert_eq!(res, ContractError::Unauthorized {});
+
+            // exit
+            let mut env = mock_env();
+            env.block.time = start.plus_seconds(3_000_000);
+            let info = mock_info("creator1", &[]);
+            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None, None).unwrap();
+            let msg = res.messages.first().unwrap();
+            assert_eq!(
+                msg.msg,
+                Bank(BankMsg::Send {
+                    to_address: "creator1".to_string(),
+                    amount: vec![Coin::new(2000000000000, "in")]
+                })
+            );
+        }
+    }
+    mod threshold {
+        use crate::{
+            killswitch::{execute_cancel_stream_with_threshold, execute_exit_cancelled},
+            threshold::{Threshold, ThresholdError},
+        };
+
+        // Create a stream with a threshold
+        // Subscribe to the stream
+        use super::*;
+
+        #[test]
+        fn test_threshold_reached() {
+            let treasury = Addr::unchecked("treasury");
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint128::new(500);
+            let out_denom = "out_denom";
+            let in_denom = "in_denom";
+
+            // threshold = 500*0.5 / 1-0.01 =252.5
+
+            // instantiate
+            let mut deps = mock_dependencies();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
+                exit_fee_percent: Decimal::percent(1),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: in_denom.to_string(),
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            // create stream
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator",
+                &[
+                    Coin::new(out_supply.u128(), out_denom),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                treasury.to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                in_denom.to_string(),
+                out_denom.to_string(),
+                out_supply,
+                start,
+                end,
+                Some(Threshold::MinSpentIn(Uint128::from(250u128))),
+            )
+            .unwrap();
+
+            // subscription
+            let mut env = mock_env();
+            env.block.time = start;
+            let funds = Coin::new(252, "in_denom");
+            let info = mock_info("subscriber", &[funds]);
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: Some("operator".to_string()),
+            };
+            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+            // Threshold should be reached
+            let mut env = mock_env();
+            env.block.time = end.plus_seconds(1);
+
+            // Exit should be possible
+            // Since there is only one subscriber all out denom should be sent to subscriber
+            // In calculations we are always rounding down that one token will be left in the stream
+            // Asuming token is 6 decimals
+            // This amount could be considered as insignificant
+            let info = mock_info("subscriber", &[]);
+            let res = execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None, None).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "subscriber".to_string(),
+                    amount: vec![Coin::new(499, "out_denom")],
+                })],
+            );
+
+            // Creator finalizes the stream
+            let info = mock_info("treasury", &[]);
+            let res = execute_finalize_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+            // Creator's revenue
+            assert_eq!(
+                res.messages[0].msg,
+                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![Coin::new(250, "in_denom")],
+                })
+            );
+            assert_eq!(
+                unclaimed_fees(deps.as_ref(), "collector"),
+                vec![Coin::new(100, "fee"), Coin::new(2, "in_denom")]
+            )
+        }
+
+        #[test]
+        fn test_threshold_status() {
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint128::new(1_000);
+
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
+                exit_fee_percent: Decimal::percent(1),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in_denom".to_string(),
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator",
+                &[
+                    Coin::new(out_supply.u128(), "out_denom"),
+                    Coin::new(100, "fee"),
+                ],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in_denom".to_string(),
+                "out_denom".to_string(),
+                out_supply,
+                start,
+                end,
+                Some(Threshold::MinSpentIn(Uint128::new(1_000))),
+            )
+            .unwrap();
+
+            // no threshold, no status
+            assert_eq!(query_threshold_state(deps.as_ref(), env, 2), Ok(None));
+
+            let mut env = mock_env();
+            env.block.time = start;
+            let msg = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+            };
+            let info = mock_info("subscriber", &[Coin::new(2_000, "in_denom")]);
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+            // nothing spent yet, all of in_supply is projected to be spent
+            let status = query_threshold_state(deps.as_ref(), env.clone(), 1)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                status,
+                crate::msg::ThresholdStatus {
+                    threshold: Threshold::MinSpentIn(Uint128::new(1_000)),
+                    current: Threshold::MinSpentIn(Uint128::zero()),
+                    spent_in: Uint128::zero(),
+                    percent_reached: Decimal::zero(),
+                    projected: Threshold::MinSpentIn(Uint128::new(2_000)),
+                    projected_spent_in: Uint128::new(2_000),
+                    reached: false,
+                    guaranteed: false,
+                }
+            );
+
+            // a quarter of the stream
+            env.block.time = start.plus_seconds(1_000_000);
+            let status = query_threshold_state(deps.as_ref(), env.clone(), 1)
+                .unwrap()
+                .unwrap();
+            assert_eq!(status.spent_in, Uint128::new(500));
+            assert_eq!(status.percent_reached, Decimal::percent(50));
+            assert_eq!(status.projected_spent_in, Uint128::new(2_000));
+            assert!(!status.reached);
+            assert!(!status.guaranteed);
+
+            // half of in_supply withdrawn, projection drops
+            let msg = crate::msg::ExecuteMsg::Withdraw {
+                stream_id: 1,
+                cap: Some(Uint128::new(750)),
+                operator_target: None,
+                recipient: None,
+            };
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("subscriber", &[]),
+                msg,
+            )
+            .unwrap();
+            let status = query_threshold_state(deps.as_ref(), env.clone(), 1)
+                .unwrap()
+                .unwrap();
+            assert_eq!(status.projected_spent_in, Uint128::new(1_250));
+
+            // past the threshold
+            env.block.time = start.plus_seconds(3_400_000);
+            let status = query_threshold_state(deps.as_ref(), env.clone(), 1)
+                .unwrap()
+                .unwrap();
+            assert_eq!(status.spent_in, Uint128::new(1_100));
+            assert_eq!(status.percent_reached, Decimal::percent(110));
+            assert!(status.reached);
+            assert!(status.guaranteed);
+
+            // stream ended, projection matches the final state
+            env.block.time = end.plus_seconds(1);
+            let status = query_threshold_state(deps.as_ref(), env, 1)
+                .unwrap()
+                .unwrap();
+            assert_eq!(status.spent_in, Uint128::new(1_250));
+            assert_eq!(status.projected_spent_in, Uint128::new(1_250));
+            assert_eq!(status.percent_reached, Decimal::percent(125));
+        }
+
+        #[test]
+        fn test_update_threshold() {
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
+                exit_fee_percent: Decimal::percent(1),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in_denom".to_string(),
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
             let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_250_000);
-            let info = mock_info("creator1", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None).unwrap_err();
-            assert_eq!(res, ContractError::StreamNotCancelled {});
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator",
+                &[Coin::new(1_000, "out_denom"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env.clone(),
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                Some("https://sample.url".to_string()),
+                "in_denom".to_string(),
+                "out_denom".to_string(),
+                Uint128::new(1_000),
+                Timestamp::from_seconds(1_000_000),
+                Timestamp::from_seconds(5_000_000),
+                None,
+            )
+            .unwrap();
 
-            //cancel
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_500_000);
-            let response = sudo_cancel_stream(deps.as_mut(), env, 1).unwrap();
-            //out_tokens and the creation fee are sent back to the treasury upon cancellation
+            // only treasury can update the threshold
+            let msg = crate::msg::ExecuteMsg::UpdateThreshold {
+                stream_id: 1,
+                threshold: Some(Threshold::MinSpentIn(Uint128::new(500))),
+            };
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("creator", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::Unauthorized {});
+
+            let res = execute(deps.as_mut(), env.clone(), mock_info("treasury", &[]), msg).unwrap();
             assert_eq!(
-                response.messages,
-                [
-                    SubMsg {
-                        id: 0,
-                        msg: Bank(BankMsg::Send {
-                            to_address: "treasury".to_string(),
-                            amount: Vec::from([Coin {
-                                denom: "out_denom".to_string(),
-                                amount: Uint128::new(1000000000000)
-                            }])
-                        }),
-                        gas_limit: None,
-                        reply_on: ReplyOn::Never
-                    },
-                    SubMsg {
-                        id: 0,
-                        msg: Bank(BankMsg::Send {
-                            to_address: "treasury".to_string(),
-                            amount: Vec::from([Coin {
-                                denom: "fee".to_string(),
-                                amount: Uint128::new(100)
-                            }])
-                        }),
-                        gas_limit: None,
-                        reply_on: ReplyOn::Never
-                    }
+                res.attributes,
+                vec![
+                    attr("action", "update_threshold"),
+                    attr("stream_id", "1"),
+                    attr("previous_threshold", "none"),
+                    attr("threshold", "min_spent_in:500"),
                 ]
             );
 
-            //random operator can't exit
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(2_250_000);
-            let info = mock_info("random", &[]);
-            let res =
-                execute_exit_cancelled(deps.as_mut(), env, info, 1, Some("creator1".to_string()))
-                    .unwrap_err();
-            assert_eq!(res, ContractError::Unauthorized {});
-
-            // exit
-            let mut env = mock_env();
-            env.block.time = start.plus_seconds(3_000_000);
-            let info = mock_info("creator1", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env, info, 1, None).unwrap();
-            let msg = res.messages.get(0).unwrap();
+            // once started it can't be raised
+            env.block.time = Timestamp::from_seconds(1_000_000);
+            let msg = crate::msg::ExecuteMsg::UpdateThreshold {
+                stream_id: 1,
+                threshold: Some(Threshold::MinSpentIn(Uint128::new(600))),
+            };
+            let err =
+                execute(deps.as_mut(), env.clone(), mock_info("treasury", &[]), msg).unwrap_err();
             assert_eq!(
-                msg.msg,
-                Bank(BankMsg::Send {
-                    to_address: "creator1".to_string(),
-                    amount: vec![Coin::new(2000000000000, "in")]
-                })
+                err,
+                ContractError::ThresholdError(ThresholdError::ThresholdCanOnlyBeLowered {})
             );
         }
-    }
-    mod threshold {
-        use crate::{
-            killswitch::{execute_cancel_stream_with_threshold, execute_exit_cancelled},
-            threshold::ThresholdError,
-        };
-
-        // Create a stream with a threshold
-        // Subscribe to the stream
-        use super::*;
 
         #[test]
-        fn test_threshold_reached() {
-            let treasury = Addr::unchecked("treasury");
+        fn test_process_refunds() {
             let start = Timestamp::from_seconds(1_000_000);
             let end = Timestamp::from_seconds(5_000_000);
-            let out_supply = Uint128::new(500);
-            let out_denom = "out_denom";
-            let in_denom = "in_denom";
-
-            // threshold = 500*0.5 / 1-0.01 =252.5
-
-            // instantiate
             let mut deps = mock_dependencies();
-            let mut env = mock_env();
-            env.block.time = Timestamp::from_seconds(0);
             let msg = crate::msg::InstantiateMsg {
                 min_stream_seconds: Uint64::new(1000),
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
-                accepted_in_denom: in_denom.to_string(),
+                accepted_in_denom: "in_denom".to_string(),
             };
             instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-            // create stream
             let mut env = mock_env();
             env.block.time = Timestamp::from_seconds(0);
             let info = mock_info(
                 "creator",
-                &[
-                    Coin::new(out_supply.u128(), out_denom),
-                    Coin::new(100, "fee"),
-                ],
+                &[Coin::new(1_000, "out_denom"), Coin::new(100, "fee")],
             );
             execute_create_stream(
                 deps.as_mut(),
                 env,
                 info,
-                treasury.to_string(),
+                "treasury".to_string(),
                 "test".to_string(),
                 Some("https://sample.url".to_string()),
-                in_denom.to_string(),
-                out_denom.to_string(),
-                out_supply,
+                "in_denom".to_string(),
+                "out_denom".to_string(),
+                Uint128::new(1_000),
                 start,
                 end,
-                Some(Uint128::from(250u128)),
+                Some(Threshold::MinSpentIn(Uint128::new(1_000_000))),
             )
             .unwrap();
 
-            // subscription
             let mut env = mock_env();
             env.block.time = start;
-            let funds = Coin::new(252, "in_denom");
-            let info = mock_info("subscriber", &[funds]);
-            let msg = crate::msg::ExecuteMsg::Subscribe {
+            for (subscriber, amount) in [("sub1", 100), ("sub2", 200), ("sub3", 300), ("sub4", 400)]
+            {
+                let msg = crate::msg::ExecuteMsg::Subscribe {
+                    stream_id: 1,
+                    operator_target: None,
+                    operator: None,
+                };
+                let info = mock_info(subscriber, &[Coin::new(amount, "in_denom")]);
+                execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+            }
+
+            // stream not ended yet
+            let msg = crate::msg::ExecuteMsg::ProcessRefunds {
                 stream_id: 1,
-                operator_target: None,
-                operator: Some("operator".to_string()),
+                limit: Some(2),
             };
-            let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+            env.block.time = end.minus_seconds(1);
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("keeper", &[]),
+                msg.clone(),
+            )
+            .unwrap_err();
+            assert_eq!(err, ContractError::StreamNotCancelled {});
 
-            // Threshold should be reached
-            let mut env = mock_env();
+            // anyone can refund positions page by page
             env.block.time = end.plus_seconds(1);
-
-            // Exit should be possible
-            // Since there is only one subscriber all out denom should be sent to subscriber
-            // In calculations we are always rounding down that one token will be left in the stream
-            // Asuming token is 6 decimals
-            // This amount could be considered as insignificant
-            let info = mock_info("subscriber", &[]);
-            let res = execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("keeper", &[]),
+                msg.clone(),
+            )
+            .unwrap();
             assert_eq!(
                 res.messages,
-                vec![SubMsg::new(BankMsg::Send {
-                    to_address: "subscriber".to_string(),
-                    amount: vec![Coin::new(499, "out_denom")],
-                })],
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "sub1".to_string(),
+                        amount: vec![Coin::new(100, "in_denom")],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "sub2".to_string(),
+                        amount: vec![Coin::new(200, "in_denom")],
+                    }),
+                ]
             );
+            assert!(res.attributes.contains(&attr("completed", "false")));
 
-            // Creator finalizes the stream
-            let info = mock_info("treasury", &[]);
-            let res = execute_finalize_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap();
-            // Creator's revenue
-            assert_eq!(
-                res.messages[0].msg,
-                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
-                    to_address: "treasury".to_string(),
-                    amount: vec![Coin::new(250, "in_denom")],
-                })
-            );
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("keeper", &[]),
+                msg.clone(),
+            )
+            .unwrap();
+            // the last page is full, it completes the refunds anyway
             assert_eq!(
-                res.messages[1].msg,
-                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin::new(100, "fee")],
-                })
+                res.messages,
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "sub3".to_string(),
+                        amount: vec![Coin::new(300, "in_denom")],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "sub4".to_string(),
+                        amount: vec![Coin::new(400, "in_denom")],
+                    }),
+                ]
             );
-            assert_eq!(
-                res.messages[2].msg,
-                cosmwasm_std::CosmosMsg::Bank(BankMsg::Send {
-                    to_address: "collector".to_string(),
-                    amount: vec![Coin::new(2, "in_denom")],
-                })
-            )
+            assert!(res.attributes.contains(&attr("completed", "true")));
+            assert_eq!(query_stats(deps.as_ref()).unwrap().open_positions, 0);
+
+            let err = execute(deps.as_mut(), env, mock_info("keeper", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::NoRefundsToProcess {});
         }
 
         #[test]
@@ -3852,8 +8277,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: in_denom.to_string(),
             };
@@ -3881,7 +8309,7 @@ mod test_module {
                 out_supply,
                 start,
                 end,
-                Some(500u128.into()),
+                Some(Threshold::MinSpentIn(500u128.into())),
             )
             .unwrap();
 
@@ -3913,7 +8341,8 @@ mod test_module {
 
             // Exit should not be possible
             let info = mock_info("subscriber", &[]);
-            let res = execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None).unwrap_err();
+            let res =
+                execute_exit_stream(deps.as_mut(), env.clone(), info, 1, None, None).unwrap_err();
             assert_eq!(
                 res,
                 ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
@@ -3930,7 +8359,8 @@ mod test_module {
 
             // Subscriber one executes exit cancelled before creator cancels stream
             let info = mock_info("subscriber", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+            let res =
+                execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None, None).unwrap();
             assert_eq!(
                 res.messages,
                 vec![SubMsg::new(BankMsg::Send {
@@ -3966,7 +8396,8 @@ mod test_module {
 
             // Subscriber 2 executes exit cancelled after creator cancels stream
             let info = mock_info("subscriber2", &[]);
-            let res = execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None).unwrap();
+            let res =
+                execute_exit_cancelled(deps.as_mut(), env.clone(), info, 1, None, None).unwrap();
             assert_eq!(
                 // In denom refunded
                 res.messages,
@@ -3997,8 +8428,11 @@ mod test_module {
                 min_seconds_until_start_time: Uint64::new(0),
                 stream_creation_denom: "fee".to_string(),
                 stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
                 exit_fee_percent: Decimal::percent(1),
-                fee_collector: "collector".to_string(),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
                 protocol_admin: "protocol_admin".to_string(),
                 accepted_in_denom: in_denom.to_string(),
             };
@@ -4026,7 +8460,7 @@ mod test_module {
                 out_supply,
                 start,
                 end,
-                Some(1_000u128.into()),
+                Some(Threshold::MinSpentIn(1_000u128.into())),
             )
             .unwrap();
 
@@ -4089,5 +8523,139 @@ mod test_module {
             let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
             assert_eq!(stream.status, Status::Cancelled);
         }
+
+        #[test]
+        fn test_min_subscribers_withdrawn() {
+            let start = Timestamp::from_seconds(1_000_000);
+            let end = Timestamp::from_seconds(5_000_000);
+            let out_supply = Uint128::new(1_000_000);
+
+            let mut deps = mock_dependencies();
+            let msg = crate::msg::InstantiateMsg {
+                min_stream_seconds: Uint64::new(1000),
+                min_seconds_until_start_time: Uint64::new(0),
+                stream_creation_denom: "fee".to_string(),
+                stream_creation_fee: Uint128::new(100),
+                stream_creation_fee_options: vec![],
+                exit_fee_percent: Decimal::percent(1),
+                exit_fee_tiers: vec![],
+                fee_side: FeeSide::In,
+                fee_collectors: single_collector("collector"),
+                protocol_admin: "protocol_admin".to_string(),
+                accepted_in_denom: "in".to_string(),
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+            let mut env = mock_env();
+            env.block.time = Timestamp::from_seconds(0);
+            let info = mock_info(
+                "creator",
+                &[Coin::new(out_supply.u128(), "out"), Coin::new(100, "fee")],
+            );
+            execute_create_stream(
+                deps.as_mut(),
+                env,
+                info,
+                "treasury".to_string(),
+                "test".to_string(),
+                None,
+                "in".to_string(),
+                "out".to_string(),
+                out_supply,
+                start,
+                end,
+                Some(Threshold::MinSubscribers(2)),
+            )
+            .unwrap();
+
+            let mut env = mock_env();
+            env.block.time = start;
+            let subscribe = crate::msg::ExecuteMsg::Subscribe {
+                stream_id: 1,
+                operator_target: None,
+                operator: None,
+            };
+            let withdraw = crate::msg::ExecuteMsg::Withdraw {
+                stream_id: 1,
+                cap: None,
+                operator_target: None,
+                recipient: None,
+            };
+            for subscriber in ["alice", "bob"] {
+                let info = mock_info(subscriber, &[Coin::new(100, "in")]);
+                execute(deps.as_mut(), env.clone(), info, subscribe.clone()).unwrap();
+            }
+            let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+            assert_eq!(stream.subscribers, 2);
+
+            // positions withdrawn in full no longer count
+            env.block.time = start.plus_seconds(1_000);
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("bob", &[]),
+                withdraw.clone(),
+            )
+            .unwrap();
+            let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+            assert_eq!(stream.subscribers, 1);
+
+            // and count again once they subscribe
+            let info = mock_info("bob", &[Coin::new(100, "in")]);
+            execute(deps.as_mut(), env.clone(), info, subscribe.clone()).unwrap();
+            let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+            assert_eq!(stream.subscribers, 2);
+
+            // legacy streams without a subscriber count are recounted on migrate
+            let mut stream = STREAMS.load(&deps.storage, 1).unwrap();
+            stream.subscribers = 0;
+            STREAMS.save(deps.as_mut().storage, 1, &stream).unwrap();
+            migrate(deps.as_mut(), env.clone(), crate::msg::MigrateMsg {}).unwrap();
+            let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+            assert_eq!(stream.subscribers, 2);
+
+            // merged positions count once
+            let msg = crate::msg::ExecuteMsg::TransferPosition {
+                stream_id: 1,
+                recipient: "alice".to_string(),
+            };
+            execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), msg).unwrap();
+            let stream = query_stream(deps.as_ref(), env.clone(), 1).unwrap();
+            assert_eq!(stream.subscribers, 1);
+
+            // dust positions withdrawn before the end don't reach the threshold
+            let info = mock_info("carol", &[Coin::new(1, "in")]);
+            execute(deps.as_mut(), env.clone(), info, subscribe).unwrap();
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("carol", &[]),
+                crate::msg::ExecuteMsg::ClaimPurchased {
+                    stream_id: 1,
+                    cap: None,
+                    operator_target: None,
+                },
+            )
+            .unwrap_err();
+            assert_eq!(
+                err,
+                ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
+            );
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("carol", &[]),
+                withdraw,
+            )
+            .unwrap();
+
+            env.block.time = end.plus_seconds(1);
+            let err =
+                execute_finalize_stream(deps.as_mut(), env, mock_info("treasury", &[]), 1, None)
+                    .unwrap_err();
+            assert_eq!(
+                err,
+                ContractError::ThresholdError(ThresholdError::ThresholdNotReached {})
+            );
+        }
     }
 }
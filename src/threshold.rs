@@ -1,10 +1,107 @@
-use cosmwasm_std::{StdError, Storage, Uint128};
-use cw_storage_plus::Map;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, StdError, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{KeyDeserialize, Map};
+use std::fmt;
 use thiserror::Error;
 
-use crate::state::Stream;
+use crate::state::{Status, Stream};
 
-pub type Threshold = Uint128;
+#[cw_serde]
+pub enum Threshold {
+    /// Minimum amount of `token_in` spent.
+    MinSpentIn(Uint128),
+    /// Minimum average price, `spent_in / sold out`.
+    MinAveragePrice(Decimal),
+    /// Minimum number of subscribers holding shares of the stream.
+    MinSubscribers(u64),
+}
+
+impl Threshold {
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Threshold::MinSpentIn(spent_in) => spent_in.is_zero(),
+            Threshold::MinAveragePrice(price) => price.is_zero(),
+            Threshold::MinSubscribers(subscribers) => *subscribers == 0,
+        }
+    }
+
+    // returns current value of the stream measured in the same kind as the threshold
+    pub fn progress(&self, stream: &Stream) -> Threshold {
+        match self {
+            Threshold::MinSpentIn(_) => Threshold::MinSpentIn(stream.spent_in),
+            Threshold::MinAveragePrice(_) => {
+                let sold = stream.out_supply - stream.out_remaining;
+                if sold.is_zero() {
+                    Threshold::MinAveragePrice(Decimal::zero())
+                } else {
+                    Threshold::MinAveragePrice(Decimal::from_ratio(stream.spent_in, sold))
+                }
+            }
+            Threshold::MinSubscribers(_) => Threshold::MinSubscribers(stream.subscribers),
+        }
+    }
+
+    pub fn is_reached(&self, stream: &Stream) -> bool {
+        match (self, self.progress(stream)) {
+            (Threshold::MinSpentIn(min), Threshold::MinSpentIn(current)) => current >= *min,
+            (Threshold::MinAveragePrice(min), Threshold::MinAveragePrice(current)) => {
+                current >= *min
+            }
+            (Threshold::MinSubscribers(min), Threshold::MinSubscribers(current)) => current >= *min,
+            _ => false,
+        }
+    }
+
+    // returns true if self is the same kind as other and not higher
+    pub fn is_not_higher_than(&self, other: &Threshold) -> bool {
+        match (self, other) {
+            (Threshold::MinSpentIn(new), Threshold::MinSpentIn(old)) => new <= old,
+            (Threshold::MinAveragePrice(new), Threshold::MinAveragePrice(old)) => new <= old,
+            (Threshold::MinSubscribers(new), Threshold::MinSubscribers(old)) => new <= old,
+            _ => false,
+        }
+    }
+
+    // returns progress of the stream as a ratio of the threshold, one meaning reached
+    pub fn percent_reached(&self, stream: &Stream) -> StdResult<Decimal> {
+        let percent = match (self, self.progress(stream)) {
+            (Threshold::MinSpentIn(min), Threshold::MinSpentIn(current)) => {
+                Decimal::checked_from_ratio(current, *min)
+            }
+            (Threshold::MinAveragePrice(min), Threshold::MinAveragePrice(current)) => {
+                current.checked_div(*min)
+            }
+            (Threshold::MinSubscribers(min), Threshold::MinSubscribers(current)) => {
+                Decimal::checked_from_ratio(current, *min)
+            }
+            _ => Ok(Decimal::zero()),
+        };
+        percent.map_err(|err| StdError::generic_err(err.to_string()))
+    }
+
+    // returns true if the threshold stays reached whatever happens until the end of the stream.
+    // spent_in never decreases, the average price is guaranteed once spent_in covers the price
+    // of the whole out supply. Subscribers can withdraw in full until the end, so never guaranteed
+    pub fn is_guaranteed(&self, stream: &Stream) -> bool {
+        match self {
+            Threshold::MinSpentIn(_) => self.is_reached(stream),
+            Threshold::MinSubscribers(_) => false,
+            Threshold::MinAveragePrice(min) => {
+                Decimal::from_ratio(stream.spent_in, stream.out_supply) >= *min
+            }
+        }
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Threshold::MinSpentIn(spent_in) => write!(f, "min_spent_in:{}", spent_in),
+            Threshold::MinAveragePrice(price) => write!(f, "min_average_price:{}", price),
+            Threshold::MinSubscribers(subscribers) => write!(f, "min_subscribers:{}", subscribers),
+        }
+    }
+}
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ThresholdError {
@@ -20,20 +117,35 @@ pub enum ThresholdError {
     #[error("Threshold not set")]
     ThresholdNotSet {},
 
-    #[error("Min price can't be zero")]
+    #[error("Threshold can't be zero")]
     ThresholdZero {},
+
+    #[error("Threshold can only be updated before the stream ends")]
+    ThresholdUpdateNotAllowed {},
+
+    #[error("Threshold can only be lowered once the stream is started")]
+    ThresholdCanOnlyBeLowered {},
 }
 pub const THRESHOLDS_STATE_KEY: &str = "thresholds";
 
+/// Thresholds stored as a minimum `spent_in` amount before threshold kinds were introduced.
+const LEGACY_THRESHOLDS: Map<u64, Uint128> = Map::new(THRESHOLDS_STATE_KEY);
+
 pub struct ThresholdState<'a>(Map<'a, u64, Threshold>);
 
+impl<'a> Default for ThresholdState<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> ThresholdState<'a> {
     pub fn new() -> Self {
         ThresholdState(Map::new(THRESHOLDS_STATE_KEY))
     }
     pub fn set_threshold_if_any(
         &self,
-        threshold: Option<Uint128>,
+        threshold: Option<Threshold>,
         stream_id: u64,
         storage: &mut dyn Storage,
     ) -> Result<(), ThresholdError> {
@@ -48,6 +160,37 @@ impl<'a> ThresholdState<'a> {
             None => Ok(()),
         }
     }
+    // sets, lowers or removes the threshold of a stream, returns the previous threshold.
+    // any change is allowed while the stream is waiting, once started the threshold
+    // can only be lowered keeping the same kind
+    pub fn update_threshold(
+        &self,
+        stream_id: u64,
+        storage: &mut dyn Storage,
+        stream: &Stream,
+        now: Timestamp,
+        threshold: Option<Threshold>,
+    ) -> Result<Option<Threshold>, ThresholdError> {
+        if stream.is_killswitch_active()
+            || stream.status == Status::Finalized
+            || now >= stream.end_time
+        {
+            return Err(ThresholdError::ThresholdUpdateNotAllowed {});
+        }
+        let previous = self.0.may_load(storage, stream_id)?;
+        if now >= stream.start_time {
+            match (&threshold, &previous) {
+                (Some(new), Some(old)) if new.is_not_higher_than(old) => {}
+                _ => return Err(ThresholdError::ThresholdCanOnlyBeLowered {}),
+            }
+        }
+        match threshold {
+            Some(threshold) => self.set_threshold_if_any(Some(threshold), stream_id, storage)?,
+            None => self.0.remove(storage, stream_id),
+        }
+        Ok(previous)
+    }
+
     pub fn error_if_not_reached(
         &self,
         stream_id: u64,
@@ -58,7 +201,7 @@ impl<'a> ThresholdState<'a> {
         // If threshold is set, It returns error if threshold is not reached
         let threshold = self.0.may_load(storage, stream_id)?;
         if let Some(threshold) = threshold {
-            if stream.spent_in < threshold {
+            if !threshold.is_reached(stream) {
                 Err(ThresholdError::ThresholdNotReached {})
             } else {
                 Ok(())
@@ -76,7 +219,7 @@ impl<'a> ThresholdState<'a> {
     ) -> Result<(), ThresholdError> {
         let threshold = self.0.may_load(storage, stream_id)?;
         if let Some(threshold) = threshold {
-            if stream.spent_in >= threshold {
+            if threshold.is_reached(stream) {
                 Err(ThresholdError::ThresholdReached {})
             } else {
                 Ok(())
@@ -85,6 +228,21 @@ impl<'a> ThresholdState<'a> {
             Ok(())
         }
     }
+    // returns error if threshold is set and can still be missed
+    pub fn error_if_not_guaranteed(
+        &self,
+        stream_id: u64,
+        storage: &dyn Storage,
+        stream: &Stream,
+    ) -> Result<(), ThresholdError> {
+        let threshold = self.0.may_load(storage, stream_id)?;
+        match threshold {
+            Some(threshold) if !threshold.is_guaranteed(stream) => {
+                Err(ThresholdError::ThresholdNotReached {})
+            }
+            _ => Ok(()),
+        }
+    }
     pub fn check_if_threshold_set(
         &self,
         stream_id: u64,
@@ -103,12 +261,33 @@ impl<'a> ThresholdState<'a> {
     }
 }
 
+// converts thresholds stored as a plain spent_in amount to the min spent_in kind.
+// both share the same namespace, entries that fail to load in either format are errors
+pub fn migrate_legacy_thresholds(storage: &mut dyn Storage) -> StdResult<()> {
+    let thresholds = ThresholdState::new();
+    // raw keys, loading keys with range or keys would parse values in the legacy format
+    let stream_ids = LEGACY_THRESHOLDS
+        .keys_raw(storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(u64::from_vec)
+        .collect::<StdResult<Vec<_>>>()?;
+    for stream_id in stream_ids {
+        if thresholds.0.load(storage, stream_id).is_ok() {
+            continue;
+        }
+        let spent_in = LEGACY_THRESHOLDS.load(storage, stream_id)?;
+        thresholds
+            .0
+            .save(storage, stream_id, &Threshold::MinSpentIn(spent_in))?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
     use super::*;
-    use crate::state::Stream;
+    use crate::state::{FeeSide, Stream};
     use cosmwasm_std::testing::MockStorage;
     use cosmwasm_std::{Addr, Decimal, Decimal256, Timestamp, Uint128};
 
@@ -136,9 +315,18 @@ mod tests {
             stream_creation_denom: "uusd".to_string(),
             stream_creation_fee: Uint128::new(0),
             stream_exit_fee_percent: Decimal::from_str("0.042").unwrap(),
+            stream_exit_fee_tiers: vec![],
+            exit_fees_accrued: Uint128::zero(),
+            keeper_rewards: Uint128::zero(),
+            keeper_reserve: Uint128::zero(),
+            out_claimed: Uint128::zero(),
+            fee_discount: None,
+            fee_side: FeeSide::In,
+            subscribers: 0,
+            pre_committed_in: Uint128::zero(),
             treasury: Addr::unchecked("treasury"),
         };
-        let threshold = Uint128::new(1_500_000_000_000);
+        let threshold = Threshold::MinSpentIn(Uint128::new(1_500_000_000_000));
         let stream_id = 1;
 
         thresholds
@@ -147,9 +335,218 @@ mod tests {
 
         stream.spent_in = Uint128::new(1_500_000_000_000 - 1);
         let result = thresholds.error_if_not_reached(stream_id, &storage, &stream.clone());
-        assert_eq!(result.is_err(), true);
+        assert!(result.is_err());
         stream.spent_in = Uint128::new(1_500_000_000_000);
         let result = thresholds.error_if_not_reached(stream_id, &storage, &stream.clone());
-        assert_eq!(result.is_err(), false);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_threshold_kinds() {
+        let mut storage = MockStorage::new();
+        let thresholds = ThresholdState::new();
+        let mut stream = Stream::new(
+            "test".to_string(),
+            Addr::unchecked("treasury"),
+            None,
+            "uluna".to_string(),
+            Uint128::new(1000),
+            "uusd".to_string(),
+            Timestamp::from_seconds(0),
+            Timestamp::from_seconds(100),
+            Timestamp::from_seconds(0),
+            "uusd".to_string(),
+            Uint128::new(0),
+            Decimal::percent(1),
+            vec![],
+        );
+
+        // zero thresholds are rejected for every kind
+        for threshold in [
+            Threshold::MinSpentIn(Uint128::zero()),
+            Threshold::MinAveragePrice(Decimal::zero()),
+            Threshold::MinSubscribers(0),
+        ] {
+            let err = thresholds
+                .set_threshold_if_any(Some(threshold), 1, &mut storage)
+                .unwrap_err();
+            assert_eq!(err, ThresholdError::ThresholdZero {});
+        }
+
+        // average price is spent_in over sold out tokens
+        let threshold = Threshold::MinAveragePrice(Decimal::percent(150));
+        thresholds
+            .set_threshold_if_any(Some(threshold.clone()), 1, &mut storage)
+            .unwrap();
+        assert_eq!(
+            threshold.progress(&stream),
+            Threshold::MinAveragePrice(Decimal::zero())
+        );
+        thresholds.error_if_reached(1, &storage, &stream).unwrap();
+        stream.spent_in = Uint128::new(140);
+        stream.out_remaining = Uint128::new(900);
+        assert_eq!(
+            thresholds.error_if_not_reached(1, &storage, &stream),
+            Err(ThresholdError::ThresholdNotReached {})
+        );
+        stream.spent_in = Uint128::new(150);
+        thresholds
+            .error_if_not_reached(1, &storage, &stream)
+            .unwrap();
+        assert_eq!(threshold.percent_reached(&stream), Ok(Decimal::one()));
+        // remaining out supply could still be sold cheaper
+        assert!(!threshold.is_guaranteed(&stream));
+        stream.spent_in = Uint128::new(1500);
+        assert!(threshold.is_guaranteed(&stream));
+        stream.spent_in = Uint128::new(150);
+        assert_eq!(
+            thresholds.error_if_reached(1, &storage, &stream),
+            Err(ThresholdError::ThresholdReached {})
+        );
+
+        // distinct subscribers
+        let threshold = Threshold::MinSubscribers(2);
+        thresholds
+            .set_threshold_if_any(Some(threshold.clone()), 2, &mut storage)
+            .unwrap();
+        stream.subscribers = 1;
+        assert_eq!(
+            thresholds.error_if_not_reached(2, &storage, &stream),
+            Err(ThresholdError::ThresholdNotReached {})
+        );
+        stream.subscribers = 2;
+        assert_eq!(threshold.progress(&stream), Threshold::MinSubscribers(2));
+        thresholds
+            .error_if_not_reached(2, &storage, &stream)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_migrate_legacy_thresholds() {
+        let mut storage = MockStorage::new();
+        LEGACY_THRESHOLDS
+            .save(&mut storage, 1, &Uint128::new(100))
+            .unwrap();
+        let thresholds = ThresholdState::new();
+        thresholds
+            .set_threshold_if_any(Some(Threshold::MinSubscribers(3)), 2, &mut storage)
+            .unwrap();
+
+        migrate_legacy_thresholds(&mut storage).unwrap();
+        assert_eq!(
+            thresholds.get_threshold(1, &storage).unwrap(),
+            Some(Threshold::MinSpentIn(Uint128::new(100)))
+        );
+        assert_eq!(
+            thresholds.get_threshold(2, &storage).unwrap(),
+            Some(Threshold::MinSubscribers(3))
+        );
+
+        // entries in neither format fail the migration instead of being dropped
+        Map::<u64, String>::new(THRESHOLDS_STATE_KEY)
+            .save(&mut storage, 3, &"invalid".to_string())
+            .unwrap();
+        migrate_legacy_thresholds(&mut storage).unwrap_err();
+    }
+
+    #[test]
+    fn test_update_threshold() {
+        let mut storage = MockStorage::new();
+        let thresholds = ThresholdState::new();
+        let mut stream = Stream::new(
+            "test".to_string(),
+            Addr::unchecked("treasury"),
+            None,
+            "uluna".to_string(),
+            Uint128::new(1000),
+            "uusd".to_string(),
+            Timestamp::from_seconds(100),
+            Timestamp::from_seconds(200),
+            Timestamp::from_seconds(0),
+            "uusd".to_string(),
+            Uint128::new(0),
+            Decimal::percent(1),
+            vec![],
+        );
+        let waiting = Timestamp::from_seconds(50);
+        let active = Timestamp::from_seconds(150);
+        let spent_in = |amount: u128| Some(Threshold::MinSpentIn(Uint128::new(amount)));
+
+        // waiting: set, raise, change kind and remove
+        let previous = thresholds
+            .update_threshold(1, &mut storage, &stream, waiting, spent_in(100))
+            .unwrap();
+        assert_eq!(previous, None);
+        let previous = thresholds
+            .update_threshold(1, &mut storage, &stream, waiting, spent_in(200))
+            .unwrap();
+        assert_eq!(previous, spent_in(100));
+        thresholds
+            .update_threshold(
+                1,
+                &mut storage,
+                &stream,
+                waiting,
+                Some(Threshold::MinSubscribers(2)),
+            )
+            .unwrap();
+        let previous = thresholds
+            .update_threshold(1, &mut storage, &stream, waiting, None)
+            .unwrap();
+        assert_eq!(previous, Some(Threshold::MinSubscribers(2)));
+        assert_eq!(thresholds.get_threshold(1, &storage).unwrap(), None);
+        let err = thresholds
+            .update_threshold(1, &mut storage, &stream, waiting, spent_in(0))
+            .unwrap_err();
+        assert_eq!(err, ThresholdError::ThresholdZero {});
+
+        // active without threshold: can't set one
+        let err = thresholds
+            .update_threshold(1, &mut storage, &stream, active, spent_in(100))
+            .unwrap_err();
+        assert_eq!(err, ThresholdError::ThresholdCanOnlyBeLowered {});
+
+        // active: lower only, same kind, no removal
+        thresholds
+            .update_threshold(1, &mut storage, &stream, waiting, spent_in(200))
+            .unwrap();
+        stream.status = Status::Active;
+        for threshold in [spent_in(201), Some(Threshold::MinSubscribers(1)), None] {
+            let err = thresholds
+                .update_threshold(1, &mut storage, &stream, active, threshold)
+                .unwrap_err();
+            assert_eq!(err, ThresholdError::ThresholdCanOnlyBeLowered {});
+        }
+        let previous = thresholds
+            .update_threshold(1, &mut storage, &stream, active, spent_in(150))
+            .unwrap();
+        assert_eq!(previous, spent_in(200));
+        assert_eq!(
+            thresholds.get_threshold(1, &storage).unwrap(),
+            spent_in(150)
+        );
+
+        // ended, stopped or finalized streams can't be updated
+        let err = thresholds
+            .update_threshold(
+                1,
+                &mut storage,
+                &stream,
+                Timestamp::from_seconds(200),
+                spent_in(100),
+            )
+            .unwrap_err();
+        assert_eq!(err, ThresholdError::ThresholdUpdateNotAllowed {});
+        for status in [Status::Paused, Status::Cancelled, Status::Finalized] {
+            stream.status = status;
+            let err = thresholds
+                .update_threshold(1, &mut storage, &stream, active, spent_in(100))
+                .unwrap_err();
+            assert_eq!(err, ThresholdError::ThresholdUpdateNotAllowed {});
+        }
+        assert_eq!(
+            thresholds.get_threshold(1, &storage).unwrap(),
+            spent_in(150)
+        );
     }
 }